@@ -1,4 +1,5 @@
 use crate::clean::PrimitiveType;
+use crate::fuzz_targets_gen::callback;
 use crate::fuzz_targets_gen::fuzz_type::FuzzableType;
 use rustc_data_structures::fx::FxHashSet;
 
@@ -30,6 +31,8 @@ pub(crate) enum _AflHelpers {
     _Str,
     _Slice(Box<_AflHelpers>),
     _Tuple(Vec<Box<_AflHelpers>>),
+    /// 合成的回调，里面是构造返回值用到的helper
+    _Callback(Box<_AflHelpers>),
 }
 
 impl _AflHelpers {
@@ -67,6 +70,15 @@ pub(crate) fn _new_from_fuzzable(fuzzable: &FuzzableType) -> Self {
                     .collect();
                 _AflHelpers::_Tuple(inner_afl_helpers)
             }
+            FuzzableType::Callback(signature) => {
+                let output_afl_helper = match &signature.output {
+                    Some(output) => {
+                        _AflHelpers::_new_from_fuzzable(&FuzzableType::Primitive(output.clone()))
+                    }
+                    None => _AflHelpers::_NoHelper,
+                };
+                _AflHelpers::_Callback(Box::new(output_afl_helper))
+            }
         }
     }
 
@@ -81,6 +93,13 @@ pub(crate) fn _get_all_dependent_afl_helpers(&self) -> Vec<_AflHelpers> {
                 let mut inner_dependent = afl_helper._get_all_dependent_afl_helpers();
                 helpers.append(&mut inner_dependent);
             }
+        } else if let _AflHelpers::_Callback(output_helper) = self {
+            //回调的辅助函数只需要定义一次，和返回值类型无关
+            helpers.push(_AflHelpers::_Callback(Box::new(_AflHelpers::_NoHelper)));
+            if **output_helper != _AflHelpers::_NoHelper {
+                let mut output_dependent = output_helper._get_all_dependent_afl_helpers();
+                helpers.append(&mut output_dependent);
+            }
         } else {
             helpers.push(self.clone());
             match self {
@@ -139,7 +158,7 @@ pub(crate) fn _get_all_dependent_afl_helpers(&self) -> Vec<_AflHelpers> {
                     let mut u32_dependency = _AflHelpers::_U32._get_all_dependent_afl_helpers();
                     helpers.append(&mut u32_dependency);
                 }
-                _AflHelpers::_Tuple(..) => {}
+                _AflHelpers::_Tuple(..) | _AflHelpers::_Callback(..) => {}
             }
         }
         helpers
@@ -167,6 +186,7 @@ pub(crate) fn _to_full_function(&self) -> &'static str {
             _AflHelpers::_Str => _data_to_str(),
             _AflHelpers::_Slice(..) => _data_to_slice(),
             _AflHelpers::_Tuple(..) => "",
+            _AflHelpers::_Callback(..) => callback::_callback_helpers(),
         }
     }
 
@@ -191,6 +211,7 @@ pub(crate) fn _type_name(&self) -> String {
             _AflHelpers::_Char => "char".to_string(),
             _AflHelpers::_Str => "str".to_string(),
             _AflHelpers::_Slice(..) => "slice".to_string(),
+            _AflHelpers::_Callback(..) => "callback".to_string(),
             _AflHelpers::_Tuple(inner_afl_helpers) => {
                 let mut type_name = "(".to_string();
                 let inner_afl_helpers_length = inner_afl_helpers.len();
@@ -220,7 +241,7 @@ pub(crate) fn _to_function_name(&self) -> String {
                     inner_type_name = inner_type_name
                 )
             }
-            _AflHelpers::_Tuple(..) => String::new(),
+            _AflHelpers::_Tuple(..) | _AflHelpers::_Callback(..) => String::new(),
             _ => {
                 format!("_to_{type_name}", type_name = self._type_name())
             }
@@ -247,6 +268,7 @@ pub(crate) fn _print_all() {
         println!("{}", _data_to_slice());
         println!("{}", _data_to_f32());
         println!("{}", _data_to_f64());
+        println!("{}", callback::_callback_helpers());
     }
 
     //may remove later
@@ -389,6 +411,21 @@ pub(crate) fn _generate_param_initial_rhs(
                     "Type not match in afl_util".to_string()
                 }
             }
+            _AflHelpers::_Callback(output_helper) => {
+                if let FuzzableType::Callback(signature) = origin_fuzzable_type {
+                    let output_length = match &signature.output {
+                        Some(output) => FuzzableType::Primitive(output.clone())._min_length(),
+                        None => 0,
+                    };
+                    signature._to_initial_rhs(
+                        fixed_start_index,
+                        output_helper._to_function_name().as_str(),
+                        output_length,
+                    )
+                } else {
+                    "Type not match in afl_util".to_string()
+                }
+            }
             _AflHelpers::_NoHelper => {
                 format!("No helper")
             }
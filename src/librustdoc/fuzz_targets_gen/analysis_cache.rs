@@ -0,0 +1,1396 @@
+//! 在多次运行之间缓存分析好的API图
+//! 设置`FRIES_CACHE_DIR`时，过滤、合成构造函数和迭代器、依赖分析之后的API图按
+//! (crate名, 版本, 打开的feature, 工具版本, 源文件的hash)缓存在这个目录里，只改变max_num、max_len或者生成策略时可以直接复用
+//! 工具版本包括rustc的版本和生成器本身的构建hash，重新编译生成器之后缓存失效
+//! 命中时在抽取API之前就恢复API图，抽取impl和函数、过滤、合成和依赖分析都不再运行；
+//! 库模式下clean::Crate和Cache仍然由编译器生成，完全不经过编译器的方式是先生成rustdoc JSON，再用`rustdoc fries-json`
+//! 缓存的内容：API（类型写成CachedType）、依赖边和调用方式、api_ids、被过滤的API和原因、合成的构造函数和迭代器、
+//! 实现了Clone和其他标准库trait的类型，以及生成时用到的Cache里的路径
+//! DefId只在缓存内部用来区分类型，恢复之后不会再拿去查询编译器
+//! key以外影响分析结果的输入（support_generic、差分测试时另一个版本的签名）记在指纹里，指纹不同时缓存失效；
+//! API图里有写不进缓存的类型（需要BodyId的常量）时不写缓存
+
+use crate::clean::{self, PrimitiveType};
+use crate::formats::cache::Cache;
+use crate::formats::item_type::ItemType;
+use crate::fuzz_targets_gen::api_function::{ApiAsyncness, ApiFunction, ApiUnsafety};
+use crate::fuzz_targets_gen::api_graph::{ApiDependency, ApiGraph, ApiType};
+use crate::fuzz_targets_gen::api_id::ApiId;
+use crate::fuzz_targets_gen::call_type::CallType;
+use crate::fuzz_targets_gen::constructor::{Constructor, ConstructorVariant, FieldShape};
+use crate::fuzz_targets_gen::differential;
+use crate::fuzz_targets_gen::impl_util::{FullNameMap, StdTrait};
+use crate::fuzz_targets_gen::iterator::IteratorInfo;
+use itertools::Itertools;
+use rustc_data_structures::fx::{FxHashMap, FxHashSet, FxHasher};
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::{CrateNum, DefId, DefIndex, CRATE_DEF_ID};
+use rustc_hir::{self as hir, Mutability};
+use rustc_middle::ty::{TyCtxt, Visibility};
+use rustc_span::{sym, Symbol};
+use rustc_target::spec::abi;
+use serde::{Deserialize, Serialize};
+use std::fmt::Debug;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::PathBuf;
+
+static CACHE_DIR_ENV: &'static str = "FRIES_CACHE_DIR";
+/// 缓存格式改变的时候加一
+static CACHE_FORMAT_VERSION: usize = 3;
+
+/// 类型路径中会出现的DefKind，其他的写不进缓存
+static CACHED_DEF_KINDS: [DefKind; 17] = [
+    DefKind::Mod,
+    DefKind::Struct,
+    DefKind::Union,
+    DefKind::Enum,
+    DefKind::Variant,
+    DefKind::Trait,
+    DefKind::TyAlias,
+    DefKind::ForeignTy,
+    DefKind::TraitAlias,
+    DefKind::AssocTy,
+    DefKind::TyParam,
+    DefKind::Fn,
+    DefKind::Const,
+    DefKind::AssocFn,
+    DefKind::AssocConst,
+    DefKind::OpaqueTy,
+    DefKind::ImplTraitPlaceholder,
+];
+
+static ITEM_TYPES: [ItemType; 26] = [
+    ItemType::Module,
+    ItemType::ExternCrate,
+    ItemType::Import,
+    ItemType::Struct,
+    ItemType::Enum,
+    ItemType::Function,
+    ItemType::Typedef,
+    ItemType::Static,
+    ItemType::Trait,
+    ItemType::Impl,
+    ItemType::TyMethod,
+    ItemType::Method,
+    ItemType::StructField,
+    ItemType::Variant,
+    ItemType::Macro,
+    ItemType::Primitive,
+    ItemType::AssocType,
+    ItemType::Constant,
+    ItemType::AssocConst,
+    ItemType::Union,
+    ItemType::ForeignType,
+    ItemType::Keyword,
+    ItemType::OpaqueTy,
+    ItemType::ProcAttribute,
+    ItemType::ProcDerive,
+    ItemType::TraitAlias,
+];
+
+static TRAIT_BOUND_MODIFIERS: [hir::TraitBoundModifier; 3] = [
+    hir::TraitBoundModifier::None,
+    hir::TraitBoundModifier::Maybe,
+    hir::TraitBoundModifier::MaybeConst,
+];
+
+/// 缓存的key
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub(crate) struct CacheKey {
+    pub(crate) crate_name: String,
+    pub(crate) version: String,
+    pub(crate) features: Vec<String>,
+    pub(crate) tool_version: String,
+    /// 库模式是当前crate所有源文件的hash，json前端是JSON文件内容的hash
+    pub(crate) source_hash: u64,
+}
+
+impl CacheKey {
+    pub(crate) fn new(tcx: TyCtxt<'_>, crate_name: &str, crate_version: Option<&String>) -> Self {
+        let version = _crate_version(crate_version);
+        let features = tcx
+            .sess
+            .parse_sess
+            .config
+            .iter()
+            .filter(|(name, _)| *name == sym::feature)
+            .filter_map(|(_, value)| value.map(|value| value.to_string()))
+            .sorted()
+            .collect();
+        let tool_version = format!(
+            "{}+{}+{}",
+            rustc_interface::util::rustc_version_str().unwrap_or("unknown"),
+            _build_hash(),
+            CACHE_FORMAT_VERSION
+        );
+        CacheKey {
+            crate_name: crate_name.to_string(),
+            version,
+            features,
+            tool_version,
+            source_hash: _source_hash(tcx),
+        }
+    }
+
+    /// 从rustdoc JSON抽取API时没有session，拿不到打开的feature，工具版本记成JSON的格式版本
+    pub(crate) fn from_json(
+        crate_name: &str,
+        crate_version: Option<&String>,
+        format_version: u32,
+        source_hash: u64,
+    ) -> Self {
+        CacheKey {
+            crate_name: crate_name.to_string(),
+            version: _crate_version(crate_version),
+            features: Vec::new(),
+            tool_version: format!(
+                "json{}+{}+{}",
+                format_version,
+                _build_hash(),
+                CACHE_FORMAT_VERSION
+            ),
+            source_hash,
+        }
+    }
+
+    fn to_line(&self) -> String {
+        format!(
+            "{} {} [{}] {} {:016x}",
+            self.crate_name,
+            self.version,
+            self.features.join(","),
+            self.tool_version,
+            self.source_hash
+        )
+    }
+
+    /// 源文件的hash不放在文件名里，源文件改了之后覆盖原来的缓存
+    fn file_name(&self) -> String {
+        let mut hasher = FxHasher::default();
+        (&self.crate_name, &self.version, &self.features, &self.tool_version).hash(&mut hasher);
+        format!("{}-{}-{:016x}.json", self.crate_name, self.version, hasher.finish())
+    }
+}
+
+/// 生成器的构建hash：当前可执行文件内容的hash，读不到的时候是unknown
+fn _build_hash() -> String {
+    lazy_static! {
+        static ref BUILD_HASH: String = {
+            match std::env::current_exe().and_then(fs::read) {
+                Ok(content) => {
+                    let mut hasher = FxHasher::default();
+                    content.hash(&mut hasher);
+                    format!("{:016x}", hasher.finish())
+                }
+                Err(_) => "unknown".to_string(),
+            }
+        };
+    }
+    BUILD_HASH.clone()
+}
+
+/// 没有指定--crate-version的时候用cargo设置的版本
+fn _crate_version(crate_version: Option<&String>) -> String {
+    crate_version
+        .cloned()
+        .or_else(|| std::env::var("CARGO_PKG_VERSION").ok())
+        .unwrap_or("unknown".to_string())
+}
+
+/// 当前crate的源文件，其他crate导入的不算
+fn _source_hash(tcx: TyCtxt<'_>) -> u64 {
+    let mut hasher = FxHasher::default();
+    for file in tcx.sess.source_map().files().iter().filter(|file| !file.is_imported()) {
+        file.name.hash(&mut hasher);
+        file.src_hash.hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+/// key以外影响分析结果的输入
+fn _fingerprint(support_generic: bool) -> u64 {
+    let mut hasher = FxHasher::default();
+    support_generic.hash(&mut hasher);
+    differential::other_version_signatures()
+        .map(|signatures| signatures.into_iter().sorted().collect_vec())
+        .hash(&mut hasher);
+    hasher.finish()
+}
+
+/// 编译器里的枚举没有实现serde，按Debug的名字在表里查找
+fn _encode_by_name<T: Debug + PartialEq>(table: &[T], value: &T) -> Option<String> {
+    table.contains(value).then(|| format!("{:?}", value))
+}
+
+fn _decode_by_name<T: Debug + Copy>(table: &[T], name: &str) -> Option<T> {
+    table.iter().find(|value| format!("{:?}", value) == name).copied()
+}
+
+type CachedDefId = (u32, u32);
+
+fn _encode_def_id(def_id: DefId) -> CachedDefId {
+    (def_id.krate.as_u32(), def_id.index.as_u32())
+}
+
+fn _decode_def_id(def_id: &CachedDefId) -> DefId {
+    DefId { krate: CrateNum::from_u32(def_id.0), index: DefIndex::from_u32(def_id.1) }
+}
+
+fn _encode_lifetime(lifetime: &clean::Lifetime) -> String {
+    lifetime.0.to_string()
+}
+
+fn _decode_lifetime(lifetime: &String) -> clean::Lifetime {
+    clean::Lifetime(Symbol::intern(lifetime))
+}
+
+fn _mutability(mutable: bool) -> Mutability {
+    if mutable { Mutability::Mut } else { Mutability::Not }
+}
+
+/// clean::Type在缓存里的写法
+#[derive(Serialize, Deserialize)]
+enum CachedType {
+    Path(CachedPath),
+    DynTrait(Vec<CachedPolyTrait>, Option<String>),
+    Generic(String),
+    Primitive(String),
+    BareFunction(Box<CachedBareFunction>),
+    Tuple(Vec<CachedType>),
+    Slice(Box<CachedType>),
+    Array(Box<CachedType>, String),
+    RawPointer(bool, Box<CachedType>),
+    BorrowedRef {
+        lifetime: Option<String>,
+        mutable: bool,
+        type_: Box<CachedType>,
+    },
+    QPath {
+        assoc: CachedSegment,
+        self_type: Box<CachedType>,
+        should_show_cast: bool,
+        trait_: CachedPath,
+    },
+    Infer,
+    ImplTrait(Vec<CachedBound>),
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedPath {
+    kind: String,
+    def_id: CachedDefId,
+    segments: Vec<CachedSegment>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedSegment {
+    name: String,
+    args: CachedGenericArgs,
+}
+
+#[derive(Serialize, Deserialize)]
+enum CachedGenericArgs {
+    AngleBracketed { args: Vec<CachedGenericArg>, bindings: Vec<CachedBinding> },
+    Parenthesized { inputs: Vec<CachedType>, output: Option<Box<CachedType>> },
+}
+
+#[derive(Serialize, Deserialize)]
+enum CachedGenericArg {
+    Lifetime(String),
+    Type(CachedType),
+    Const(CachedConstant),
+    Infer,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedBinding {
+    assoc: CachedSegment,
+    kind: CachedBindingKind,
+}
+
+#[derive(Serialize, Deserialize)]
+enum CachedBindingKind {
+    Equality(CachedTerm),
+    Constraint(Vec<CachedBound>),
+}
+
+#[derive(Serialize, Deserialize)]
+enum CachedTerm {
+    Type(CachedType),
+    Constant(CachedConstant),
+}
+
+/// 只有不需要BodyId的常量能写进缓存
+#[derive(Serialize, Deserialize)]
+enum CachedConstant {
+    TyConst { type_: CachedType, expr: String },
+    Extern { type_: CachedType, def_id: CachedDefId },
+}
+
+#[derive(Serialize, Deserialize)]
+enum CachedBound {
+    Trait { poly_trait: CachedPolyTrait, modifier: String },
+    Outlives(String),
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedPolyTrait {
+    trait_: CachedPath,
+    generic_params: Vec<CachedGenericParam>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedGenerics {
+    params: Vec<CachedGenericParam>,
+    where_predicates: Vec<CachedWherePredicate>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedGenericParam {
+    name: String,
+    kind: CachedGenericParamKind,
+}
+
+#[derive(Serialize, Deserialize)]
+enum CachedGenericParamKind {
+    Lifetime {
+        outlives: Vec<String>,
+    },
+    Type {
+        did: CachedDefId,
+        bounds: Vec<CachedBound>,
+        default: Option<CachedType>,
+        synthetic: bool,
+    },
+    Const {
+        did: CachedDefId,
+        ty: CachedType,
+        default: Option<String>,
+    },
+}
+
+#[derive(Serialize, Deserialize)]
+enum CachedWherePredicate {
+    Bound { ty: CachedType, bounds: Vec<CachedBound>, bound_params: Vec<String> },
+    Region { lifetime: String, bounds: Vec<CachedBound> },
+    Eq { lhs: CachedType, rhs: CachedTerm, bound_params: Vec<String> },
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedBareFunction {
+    is_unsafe: bool,
+    generic_params: Vec<CachedGenericParam>,
+    /// (参数名, 类型, is_const)
+    inputs: Vec<(String, CachedType, bool)>,
+    output: Option<CachedType>,
+    c_variadic: bool,
+    abi: String,
+}
+
+fn _encode_type(ty: &clean::Type) -> Option<CachedType> {
+    let ty = match ty {
+        clean::Type::Path { path } => CachedType::Path(_encode_path(path)?),
+        clean::Type::DynTrait(poly_traits, lifetime) => CachedType::DynTrait(
+            poly_traits.iter().map(_encode_poly_trait).collect::<Option<_>>()?,
+            lifetime.as_ref().map(_encode_lifetime),
+        ),
+        clean::Type::Generic(name) => CachedType::Generic(name.to_string()),
+        clean::Type::Primitive(primitive) => CachedType::Primitive(primitive.as_sym().to_string()),
+        clean::Type::BareFunction(bare_function) => {
+            CachedType::BareFunction(Box::new(_encode_bare_function(bare_function)?))
+        }
+        clean::Type::Tuple(types) => CachedType::Tuple(_encode_types(types)?),
+        clean::Type::Slice(ty) => CachedType::Slice(Box::new(_encode_type(ty)?)),
+        clean::Type::Array(ty, len) => {
+            CachedType::Array(Box::new(_encode_type(ty)?), len.to_string())
+        }
+        clean::Type::RawPointer(mutability, ty) => {
+            CachedType::RawPointer(*mutability == Mutability::Mut, Box::new(_encode_type(ty)?))
+        }
+        clean::Type::BorrowedRef { lifetime, mutability, type_ } => CachedType::BorrowedRef {
+            lifetime: lifetime.as_ref().map(_encode_lifetime),
+            mutable: *mutability == Mutability::Mut,
+            type_: Box::new(_encode_type(type_)?),
+        },
+        clean::Type::QPath(qpath) => CachedType::QPath {
+            assoc: _encode_segment(&qpath.assoc)?,
+            self_type: Box::new(_encode_type(&qpath.self_type)?),
+            should_show_cast: qpath.should_show_cast,
+            trait_: _encode_path(&qpath.trait_)?,
+        },
+        clean::Type::Infer => CachedType::Infer,
+        clean::Type::ImplTrait(bounds) => CachedType::ImplTrait(_encode_bounds(bounds)?),
+    };
+    Some(ty)
+}
+
+fn _decode_type(ty: &CachedType) -> Option<clean::Type> {
+    let ty = match ty {
+        CachedType::Path(path) => clean::Type::Path { path: _decode_path(path)? },
+        CachedType::DynTrait(poly_traits, lifetime) => clean::Type::DynTrait(
+            poly_traits.iter().map(_decode_poly_trait).collect::<Option<_>>()?,
+            lifetime.as_ref().map(_decode_lifetime),
+        ),
+        CachedType::Generic(name) => clean::Type::Generic(Symbol::intern(name)),
+        CachedType::Primitive(name) => {
+            clean::Type::Primitive(PrimitiveType::from_symbol(Symbol::intern(name))?)
+        }
+        CachedType::BareFunction(bare_function) => {
+            clean::Type::BareFunction(Box::new(_decode_bare_function(bare_function)?))
+        }
+        CachedType::Tuple(types) => clean::Type::Tuple(_decode_types(types)?),
+        CachedType::Slice(ty) => clean::Type::Slice(Box::new(_decode_type(ty)?)),
+        CachedType::Array(ty, len) => {
+            clean::Type::Array(Box::new(_decode_type(ty)?), len.clone().into())
+        }
+        CachedType::RawPointer(mutable, ty) => {
+            clean::Type::RawPointer(_mutability(*mutable), Box::new(_decode_type(ty)?))
+        }
+        CachedType::BorrowedRef { lifetime, mutable, type_ } => clean::Type::BorrowedRef {
+            lifetime: lifetime.as_ref().map(_decode_lifetime),
+            mutability: _mutability(*mutable),
+            type_: Box::new(_decode_type(type_)?),
+        },
+        CachedType::QPath { assoc, self_type, should_show_cast, trait_ } => {
+            clean::Type::QPath(Box::new(clean::QPathData {
+                assoc: _decode_segment(assoc)?,
+                self_type: _decode_type(self_type)?,
+                should_show_cast: *should_show_cast,
+                trait_: _decode_path(trait_)?,
+            }))
+        }
+        CachedType::Infer => clean::Type::Infer,
+        CachedType::ImplTrait(bounds) => clean::Type::ImplTrait(_decode_bounds(bounds)?),
+    };
+    Some(ty)
+}
+
+fn _encode_types(types: &[clean::Type]) -> Option<Vec<CachedType>> {
+    types.iter().map(_encode_type).collect()
+}
+
+fn _decode_types(types: &[CachedType]) -> Option<Vec<clean::Type>> {
+    types.iter().map(_decode_type).collect()
+}
+
+/// 没有类型是Some(None)，有类型但是写不进去是None
+fn _encode_option_type(ty: Option<&clean::Type>) -> Option<Option<CachedType>> {
+    match ty {
+        Some(ty) => Some(Some(_encode_type(ty)?)),
+        None => Some(None),
+    }
+}
+
+fn _decode_option_type(ty: Option<&CachedType>) -> Option<Option<clean::Type>> {
+    match ty {
+        Some(ty) => Some(Some(_decode_type(ty)?)),
+        None => Some(None),
+    }
+}
+
+/// 只有Res::Def的路径能写进缓存
+fn _encode_path(path: &clean::Path) -> Option<CachedPath> {
+    let (kind, def_id) = match path.res {
+        Res::Def(kind, def_id) => (kind, def_id),
+        _ => return None,
+    };
+    Some(CachedPath {
+        kind: _encode_by_name(&CACHED_DEF_KINDS, &kind)?,
+        def_id: _encode_def_id(def_id),
+        segments: path.segments.iter().map(_encode_segment).collect::<Option<_>>()?,
+    })
+}
+
+fn _decode_path(path: &CachedPath) -> Option<clean::Path> {
+    Some(clean::Path {
+        res: Res::Def(
+            _decode_by_name(&CACHED_DEF_KINDS, &path.kind)?,
+            _decode_def_id(&path.def_id),
+        ),
+        segments: path.segments.iter().map(_decode_segment).collect::<Option<_>>()?,
+    })
+}
+
+fn _encode_segment(segment: &clean::PathSegment) -> Option<CachedSegment> {
+    Some(CachedSegment {
+        name: segment.name.to_string(),
+        args: _encode_generic_args(&segment.args)?,
+    })
+}
+
+fn _decode_segment(segment: &CachedSegment) -> Option<clean::PathSegment> {
+    Some(clean::PathSegment {
+        name: Symbol::intern(&segment.name),
+        args: _decode_generic_args(&segment.args)?,
+    })
+}
+
+fn _encode_generic_args(args: &clean::GenericArgs) -> Option<CachedGenericArgs> {
+    let args = match args {
+        clean::GenericArgs::AngleBracketed { args, bindings } => {
+            CachedGenericArgs::AngleBracketed {
+                args: args
+                    .iter()
+                    .map(|arg| match arg {
+                        clean::GenericArg::Lifetime(lifetime) => {
+                            Some(CachedGenericArg::Lifetime(_encode_lifetime(lifetime)))
+                        }
+                        clean::GenericArg::Type(ty) => {
+                            Some(CachedGenericArg::Type(_encode_type(ty)?))
+                        }
+                        clean::GenericArg::Const(constant) => {
+                            Some(CachedGenericArg::Const(_encode_constant(constant)?))
+                        }
+                        clean::GenericArg::Infer => Some(CachedGenericArg::Infer),
+                    })
+                    .collect::<Option<_>>()?,
+                bindings: bindings.iter().map(_encode_binding).collect::<Option<_>>()?,
+            }
+        }
+        clean::GenericArgs::Parenthesized { inputs, output } => CachedGenericArgs::Parenthesized {
+            inputs: _encode_types(inputs)?,
+            output: _encode_option_type(output.as_deref())?.map(Box::new),
+        },
+    };
+    Some(args)
+}
+
+fn _decode_generic_args(args: &CachedGenericArgs) -> Option<clean::GenericArgs> {
+    let args = match args {
+        CachedGenericArgs::AngleBracketed { args, bindings } => {
+            clean::GenericArgs::AngleBracketed {
+                args: args
+                    .iter()
+                    .map(|arg| match arg {
+                        CachedGenericArg::Lifetime(lifetime) => {
+                            Some(clean::GenericArg::Lifetime(_decode_lifetime(lifetime)))
+                        }
+                        CachedGenericArg::Type(ty) => {
+                            Some(clean::GenericArg::Type(_decode_type(ty)?))
+                        }
+                        CachedGenericArg::Const(constant) => {
+                            Some(clean::GenericArg::Const(Box::new(_decode_constant(constant)?)))
+                        }
+                        CachedGenericArg::Infer => Some(clean::GenericArg::Infer),
+                    })
+                    .collect::<Option<Vec<_>>>()?
+                    .into(),
+                bindings: bindings.iter().map(_decode_binding).collect::<Option<_>>()?,
+            }
+        }
+        CachedGenericArgs::Parenthesized { inputs, output } => clean::GenericArgs::Parenthesized {
+            inputs: _decode_types(inputs)?.into(),
+            output: _decode_option_type(output.as_deref())?.map(Box::new),
+        },
+    };
+    Some(args)
+}
+
+fn _encode_binding(binding: &clean::TypeBinding) -> Option<CachedBinding> {
+    let kind = match &binding.kind {
+        clean::TypeBindingKind::Equality { term } => {
+            CachedBindingKind::Equality(_encode_term(term)?)
+        }
+        clean::TypeBindingKind::Constraint { bounds } => {
+            CachedBindingKind::Constraint(_encode_bounds(bounds)?)
+        }
+    };
+    Some(CachedBinding { assoc: _encode_segment(&binding.assoc)?, kind })
+}
+
+fn _decode_binding(binding: &CachedBinding) -> Option<clean::TypeBinding> {
+    let kind = match &binding.kind {
+        CachedBindingKind::Equality(term) => {
+            clean::TypeBindingKind::Equality { term: _decode_term(term)? }
+        }
+        CachedBindingKind::Constraint(bounds) => {
+            clean::TypeBindingKind::Constraint { bounds: _decode_bounds(bounds)? }
+        }
+    };
+    Some(clean::TypeBinding { assoc: _decode_segment(&binding.assoc)?, kind })
+}
+
+fn _encode_term(term: &clean::Term) -> Option<CachedTerm> {
+    match term {
+        clean::Term::Type(ty) => Some(CachedTerm::Type(_encode_type(ty)?)),
+        clean::Term::Constant(constant) => Some(CachedTerm::Constant(_encode_constant(constant)?)),
+    }
+}
+
+fn _decode_term(term: &CachedTerm) -> Option<clean::Term> {
+    match term {
+        CachedTerm::Type(ty) => Some(clean::Term::Type(_decode_type(ty)?)),
+        CachedTerm::Constant(constant) => Some(clean::Term::Constant(_decode_constant(constant)?)),
+    }
+}
+
+fn _encode_constant(constant: &clean::Constant) -> Option<CachedConstant> {
+    let type_ = _encode_type(&constant.type_)?;
+    match &constant.kind {
+        clean::ConstantKind::TyConst { expr } => {
+            Some(CachedConstant::TyConst { type_, expr: expr.to_string() })
+        }
+        clean::ConstantKind::Extern { def_id } => {
+            Some(CachedConstant::Extern { type_, def_id: _encode_def_id(*def_id) })
+        }
+        clean::ConstantKind::Anonymous { .. } | clean::ConstantKind::Local { .. } => None,
+    }
+}
+
+fn _decode_constant(constant: &CachedConstant) -> Option<clean::Constant> {
+    match constant {
+        CachedConstant::TyConst { type_, expr } => Some(clean::Constant {
+            type_: _decode_type(type_)?,
+            kind: clean::ConstantKind::TyConst { expr: expr.clone().into() },
+        }),
+        CachedConstant::Extern { type_, def_id } => Some(clean::Constant {
+            type_: _decode_type(type_)?,
+            kind: clean::ConstantKind::Extern { def_id: _decode_def_id(def_id) },
+        }),
+    }
+}
+
+fn _encode_bounds(bounds: &[clean::GenericBound]) -> Option<Vec<CachedBound>> {
+    bounds
+        .iter()
+        .map(|bound| match bound {
+            clean::GenericBound::TraitBound(poly_trait, modifier) => Some(CachedBound::Trait {
+                poly_trait: _encode_poly_trait(poly_trait)?,
+                modifier: _encode_by_name(&TRAIT_BOUND_MODIFIERS, modifier)?,
+            }),
+            clean::GenericBound::Outlives(lifetime) => {
+                Some(CachedBound::Outlives(_encode_lifetime(lifetime)))
+            }
+        })
+        .collect()
+}
+
+fn _decode_bounds(bounds: &[CachedBound]) -> Option<Vec<clean::GenericBound>> {
+    bounds
+        .iter()
+        .map(|bound| match bound {
+            CachedBound::Trait { poly_trait, modifier } => Some(clean::GenericBound::TraitBound(
+                _decode_poly_trait(poly_trait)?,
+                _decode_by_name(&TRAIT_BOUND_MODIFIERS, modifier)?,
+            )),
+            CachedBound::Outlives(lifetime) => {
+                Some(clean::GenericBound::Outlives(_decode_lifetime(lifetime)))
+            }
+        })
+        .collect()
+}
+
+fn _encode_poly_trait(poly_trait: &clean::PolyTrait) -> Option<CachedPolyTrait> {
+    Some(CachedPolyTrait {
+        trait_: _encode_path(&poly_trait.trait_)?,
+        generic_params: poly_trait
+            .generic_params
+            .iter()
+            .map(_encode_generic_param)
+            .collect::<Option<_>>()?,
+    })
+}
+
+fn _decode_poly_trait(poly_trait: &CachedPolyTrait) -> Option<clean::PolyTrait> {
+    Some(clean::PolyTrait {
+        trait_: _decode_path(&poly_trait.trait_)?,
+        generic_params: poly_trait
+            .generic_params
+            .iter()
+            .map(_decode_generic_param)
+            .collect::<Option<_>>()?,
+    })
+}
+
+fn _encode_generics(generics: &clean::Generics) -> Option<CachedGenerics> {
+    Some(CachedGenerics {
+        params: generics.params.iter().map(_encode_generic_param).collect::<Option<_>>()?,
+        where_predicates: generics
+            .where_predicates
+            .iter()
+            .map(_encode_where_predicate)
+            .collect::<Option<_>>()?,
+    })
+}
+
+fn _decode_generics(generics: &CachedGenerics) -> Option<clean::Generics> {
+    Some(clean::Generics {
+        params: generics.params.iter().map(_decode_generic_param).collect::<Option<_>>()?,
+        where_predicates: generics
+            .where_predicates
+            .iter()
+            .map(_decode_where_predicate)
+            .collect::<Option<_>>()?,
+    })
+}
+
+fn _encode_generic_param(param: &clean::GenericParamDef) -> Option<CachedGenericParam> {
+    let kind = match &param.kind {
+        clean::GenericParamDefKind::Lifetime { outlives } => CachedGenericParamKind::Lifetime {
+            outlives: outlives.iter().map(_encode_lifetime).collect(),
+        },
+        clean::GenericParamDefKind::Type { did, bounds, default, synthetic } => {
+            CachedGenericParamKind::Type {
+                did: _encode_def_id(*did),
+                bounds: _encode_bounds(bounds)?,
+                default: _encode_option_type(default.as_deref())?,
+                synthetic: *synthetic,
+            }
+        }
+        clean::GenericParamDefKind::Const { did, ty, default } => CachedGenericParamKind::Const {
+            did: _encode_def_id(*did),
+            ty: _encode_type(ty)?,
+            default: default.as_deref().cloned(),
+        },
+    };
+    Some(CachedGenericParam { name: param.name.to_string(), kind })
+}
+
+fn _decode_generic_param(param: &CachedGenericParam) -> Option<clean::GenericParamDef> {
+    let kind = match &param.kind {
+        CachedGenericParamKind::Lifetime { outlives } => clean::GenericParamDefKind::Lifetime {
+            outlives: outlives.iter().map(_decode_lifetime).collect(),
+        },
+        CachedGenericParamKind::Type { did, bounds, default, synthetic } => {
+            clean::GenericParamDefKind::Type {
+                did: _decode_def_id(did),
+                bounds: _decode_bounds(bounds)?,
+                default: _decode_option_type(default.as_ref())?.map(Box::new),
+                synthetic: *synthetic,
+            }
+        }
+        CachedGenericParamKind::Const { did, ty, default } => clean::GenericParamDefKind::Const {
+            did: _decode_def_id(did),
+            ty: Box::new(_decode_type(ty)?),
+            default: default.clone().map(Box::new),
+        },
+    };
+    Some(clean::GenericParamDef { name: Symbol::intern(&param.name), kind })
+}
+
+fn _encode_where_predicate(predicate: &clean::WherePredicate) -> Option<CachedWherePredicate> {
+    let predicate = match predicate {
+        clean::WherePredicate::BoundPredicate { ty, bounds, bound_params } => {
+            CachedWherePredicate::Bound {
+                ty: _encode_type(ty)?,
+                bounds: _encode_bounds(bounds)?,
+                bound_params: bound_params.iter().map(_encode_lifetime).collect(),
+            }
+        }
+        clean::WherePredicate::RegionPredicate { lifetime, bounds } => {
+            CachedWherePredicate::Region {
+                lifetime: _encode_lifetime(lifetime),
+                bounds: _encode_bounds(bounds)?,
+            }
+        }
+        clean::WherePredicate::EqPredicate { lhs, rhs, bound_params } => CachedWherePredicate::Eq {
+            lhs: _encode_type(lhs)?,
+            rhs: _encode_term(rhs)?,
+            bound_params: bound_params.iter().map(_encode_lifetime).collect(),
+        },
+    };
+    Some(predicate)
+}
+
+fn _decode_where_predicate(predicate: &CachedWherePredicate) -> Option<clean::WherePredicate> {
+    let predicate = match predicate {
+        CachedWherePredicate::Bound { ty, bounds, bound_params } => {
+            clean::WherePredicate::BoundPredicate {
+                ty: _decode_type(ty)?,
+                bounds: _decode_bounds(bounds)?,
+                bound_params: bound_params.iter().map(_decode_lifetime).collect(),
+            }
+        }
+        CachedWherePredicate::Region { lifetime, bounds } => {
+            clean::WherePredicate::RegionPredicate {
+                lifetime: _decode_lifetime(lifetime),
+                bounds: _decode_bounds(bounds)?,
+            }
+        }
+        CachedWherePredicate::Eq { lhs, rhs, bound_params } => clean::WherePredicate::EqPredicate {
+            lhs: Box::new(_decode_type(lhs)?),
+            rhs: Box::new(_decode_term(rhs)?),
+            bound_params: bound_params.iter().map(_decode_lifetime).collect(),
+        },
+    };
+    Some(predicate)
+}
+
+fn _encode_bare_function(bare_function: &clean::BareFunctionDecl) -> Option<CachedBareFunction> {
+    Some(CachedBareFunction {
+        is_unsafe: bare_function.unsafety == hir::Unsafety::Unsafe,
+        generic_params: bare_function
+            .generic_params
+            .iter()
+            .map(_encode_generic_param)
+            .collect::<Option<_>>()?,
+        inputs: bare_function
+            .decl
+            .inputs
+            .values
+            .iter()
+            .map(|argument| {
+                Some((argument.name.to_string(), _encode_type(&argument.type_)?, argument.is_const))
+            })
+            .collect::<Option<_>>()?,
+        output: match &bare_function.decl.output {
+            clean::FnRetTy::Return(ty) => Some(_encode_type(ty)?),
+            clean::FnRetTy::DefaultReturn => None,
+        },
+        c_variadic: bare_function.decl.c_variadic,
+        abi: bare_function.abi.name().to_string(),
+    })
+}
+
+fn _decode_bare_function(bare_function: &CachedBareFunction) -> Option<clean::BareFunctionDecl> {
+    Some(clean::BareFunctionDecl {
+        unsafety: if bare_function.is_unsafe {
+            hir::Unsafety::Unsafe
+        } else {
+            hir::Unsafety::Normal
+        },
+        generic_params: bare_function
+            .generic_params
+            .iter()
+            .map(_decode_generic_param)
+            .collect::<Option<_>>()?,
+        decl: clean::FnDecl {
+            inputs: clean::Arguments {
+                values: bare_function
+                    .inputs
+                    .iter()
+                    .map(|(name, ty, is_const)| {
+                        Some(clean::Argument {
+                            type_: _decode_type(ty)?,
+                            name: Symbol::intern(name),
+                            is_const: *is_const,
+                        })
+                    })
+                    .collect::<Option<_>>()?,
+            },
+            output: match &bare_function.output {
+                Some(ty) => clean::FnRetTy::Return(_decode_type(ty)?),
+                None => clean::FnRetTy::DefaultReturn,
+            },
+            c_variadic: bare_function.c_variadic,
+        },
+        abi: abi::lookup(&bare_function.abi)?,
+    })
+}
+
+/// CallType在缓存里的写法
+#[derive(Serialize, Deserialize)]
+enum CachedCallType {
+    NotCompatible,
+    DirectCall,
+    BorrowedRef(Box<CachedCallType>),
+    MutBorrowedRef(Box<CachedCallType>),
+    ConstRawPointer(Box<CachedCallType>, CachedType),
+    MutRawPointer(Box<CachedCallType>, CachedType),
+    AsConvert(String),
+    UnsafeDeref(Box<CachedCallType>),
+    Deref(Box<CachedCallType>),
+    UnwrapResult(Box<CachedCallType>),
+    ToResult(Box<CachedCallType>),
+    UnwrapOption(Box<CachedCallType>),
+    ToOption(Box<CachedCallType>),
+    Clone(Box<CachedCallType>),
+}
+
+fn _encode_call_type(call_type: &CallType) -> Option<CachedCallType> {
+    let inner = |inner: &CallType| _encode_call_type(inner).map(Box::new);
+    let call_type = match call_type {
+        CallType::_NotCompatible => CachedCallType::NotCompatible,
+        CallType::_DirectCall => CachedCallType::DirectCall,
+        CallType::_BorrowedRef(call_type) => CachedCallType::BorrowedRef(inner(call_type)?),
+        CallType::_MutBorrowedRef(call_type) => CachedCallType::MutBorrowedRef(inner(call_type)?),
+        CallType::_ConstRawPointer(call_type, ty) => {
+            CachedCallType::ConstRawPointer(inner(call_type)?, _encode_type(ty)?)
+        }
+        CallType::_MutRawPointer(call_type, ty) => {
+            CachedCallType::MutRawPointer(inner(call_type)?, _encode_type(ty)?)
+        }
+        CallType::_AsConvert(ty) => CachedCallType::AsConvert(ty.clone()),
+        CallType::_UnsafeDeref(call_type) => CachedCallType::UnsafeDeref(inner(call_type)?),
+        CallType::_Deref(call_type) => CachedCallType::Deref(inner(call_type)?),
+        CallType::_UnwrapResult(call_type) => CachedCallType::UnwrapResult(inner(call_type)?),
+        CallType::_ToResult(call_type) => CachedCallType::ToResult(inner(call_type)?),
+        CallType::_UnwrapOption(call_type) => CachedCallType::UnwrapOption(inner(call_type)?),
+        CallType::_ToOption(call_type) => CachedCallType::ToOption(inner(call_type)?),
+        CallType::_Clone(call_type) => CachedCallType::Clone(inner(call_type)?),
+    };
+    Some(call_type)
+}
+
+fn _decode_call_type(call_type: &CachedCallType) -> Option<CallType> {
+    let inner = |inner: &CachedCallType| _decode_call_type(inner).map(Box::new);
+    let call_type = match call_type {
+        CachedCallType::NotCompatible => CallType::_NotCompatible,
+        CachedCallType::DirectCall => CallType::_DirectCall,
+        CachedCallType::BorrowedRef(call_type) => CallType::_BorrowedRef(inner(call_type)?),
+        CachedCallType::MutBorrowedRef(call_type) => CallType::_MutBorrowedRef(inner(call_type)?),
+        CachedCallType::ConstRawPointer(call_type, ty) => {
+            CallType::_ConstRawPointer(inner(call_type)?, _decode_type(ty)?)
+        }
+        CachedCallType::MutRawPointer(call_type, ty) => {
+            CallType::_MutRawPointer(inner(call_type)?, _decode_type(ty)?)
+        }
+        CachedCallType::AsConvert(ty) => CallType::_AsConvert(ty.clone()),
+        CachedCallType::UnsafeDeref(call_type) => CallType::_UnsafeDeref(inner(call_type)?),
+        CachedCallType::Deref(call_type) => CallType::_Deref(inner(call_type)?),
+        CachedCallType::UnwrapResult(call_type) => CallType::_UnwrapResult(inner(call_type)?),
+        CachedCallType::ToResult(call_type) => CallType::_ToResult(inner(call_type)?),
+        CachedCallType::UnwrapOption(call_type) => CallType::_UnwrapOption(inner(call_type)?),
+        CachedCallType::ToOption(call_type) => CallType::_ToOption(inner(call_type)?),
+        CachedCallType::Clone(call_type) => CallType::_Clone(inner(call_type)?),
+    };
+    Some(call_type)
+}
+
+/// 可见性只记录是否公开，分析之后只会用到这一点
+#[derive(Serialize, Deserialize)]
+struct CachedFunction {
+    full_name: String,
+    generics: CachedGenerics,
+    generic_substitutions: Vec<(String, CachedType)>,
+    inputs: Vec<CachedType>,
+    output: Option<CachedType>,
+    trait_full_path: Option<String>,
+    is_unsafe: bool,
+    is_async: bool,
+    is_public: bool,
+}
+
+/// 依赖的两端都是BareFunction
+#[derive(Serialize, Deserialize)]
+struct CachedDependency {
+    output_fun: usize,
+    input_fun: usize,
+    input_param_index: usize,
+    call_type: CachedCallType,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedVariant {
+    path: String,
+    shape: FieldShape,
+    fields: Vec<CachedType>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedConstructor {
+    type_path: String,
+    type_: CachedType,
+    is_enum: bool,
+    variants: Vec<CachedVariant>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedIterator {
+    type_: CachedType,
+    item: Option<CachedType>,
+    double_ended: bool,
+}
+
+/// Cache和FullNameMap中的一条路径
+#[derive(Serialize, Deserialize)]
+struct CachedItemPath {
+    def_id: CachedDefId,
+    path: Vec<String>,
+    item_type: String,
+}
+
+/// 缓存文件的内容
+#[derive(Serialize, Deserialize)]
+struct GraphCache {
+    key: String,
+    fingerprint: u64,
+    crate_version: Option<String>,
+    paths: Vec<CachedItemPath>,
+    external_paths: Vec<CachedItemPath>,
+    primitive_locations: Vec<(String, CachedDefId)>,
+    /// path只有一段，是完整的名字
+    full_names: Vec<CachedItemPath>,
+    functions: Vec<CachedFunction>,
+    dependencies: Vec<CachedDependency>,
+    api_ids: Vec<(String, ApiId)>,
+    excluded_functions: Vec<(String, String)>,
+    functions_with_unsupported_fuzzable_types: Vec<String>,
+    mod_visibility: Vec<(String, bool)>,
+    clone_types: Vec<CachedDefId>,
+    std_traits: Vec<(CachedDefId, StdTrait)>,
+    constructors: Vec<(String, CachedConstructor)>,
+    iterator_types: Vec<CachedIterator>,
+}
+
+fn _encode_function(api_fun: &ApiFunction) -> Option<CachedFunction> {
+    Some(CachedFunction {
+        full_name: api_fun.full_name.clone(),
+        generics: _encode_generics(&api_fun._generics)?,
+        generic_substitutions: api_fun
+            .generic_substitutions
+            .iter()
+            .map(|(name, ty)| Some((name.clone(), _encode_type(ty)?)))
+            .collect::<Option<_>>()?,
+        inputs: _encode_types(&api_fun.inputs)?,
+        output: _encode_option_type(api_fun.output.as_ref())?,
+        trait_full_path: api_fun._trait_full_path.clone(),
+        is_unsafe: matches!(api_fun._unsafe_tag, ApiUnsafety::Unsafe),
+        is_async: matches!(api_fun._async_tag, ApiAsyncness::Async),
+        is_public: api_fun.visibility.is_public(),
+    })
+}
+
+fn _decode_function(api_fun: &CachedFunction) -> Option<ApiFunction> {
+    Some(ApiFunction {
+        full_name: api_fun.full_name.clone(),
+        _generics: _decode_generics(&api_fun.generics)?,
+        generic_substitutions: api_fun
+            .generic_substitutions
+            .iter()
+            .map(|(name, ty)| Some((name.clone(), _decode_type(ty)?)))
+            .collect::<Option<_>>()?,
+        inputs: _decode_types(&api_fun.inputs)?,
+        output: _decode_option_type(api_fun.output.as_ref())?,
+        _trait_full_path: api_fun.trait_full_path.clone(),
+        _unsafe_tag: if api_fun.is_unsafe { ApiUnsafety::Unsafe } else { ApiUnsafety::Normal },
+        _async_tag: if api_fun.is_async { ApiAsyncness::Async } else { ApiAsyncness::NotAsync },
+        visibility: _decode_visibility(api_fun.is_public),
+    })
+}
+
+fn _decode_visibility(is_public: bool) -> Visibility {
+    if is_public { Visibility::Public } else { Visibility::Restricted(CRATE_DEF_ID) }
+}
+
+fn _encode_constructor(constructor: &Constructor) -> Option<CachedConstructor> {
+    Some(CachedConstructor {
+        type_path: constructor.type_path.clone(),
+        type_: _encode_type(&constructor.type_)?,
+        is_enum: constructor.is_enum,
+        variants: constructor
+            .variants
+            .iter()
+            .map(|variant| {
+                Some(CachedVariant {
+                    path: variant.path.clone(),
+                    shape: variant.shape.clone(),
+                    fields: _encode_types(&variant.fields)?,
+                })
+            })
+            .collect::<Option<_>>()?,
+    })
+}
+
+fn _decode_constructor(constructor: &CachedConstructor) -> Option<Constructor> {
+    Some(Constructor {
+        type_path: constructor.type_path.clone(),
+        type_: _decode_type(&constructor.type_)?,
+        is_enum: constructor.is_enum,
+        variants: constructor
+            .variants
+            .iter()
+            .map(|variant| {
+                Some(ConstructorVariant {
+                    path: variant.path.clone(),
+                    shape: variant.shape.clone(),
+                    fields: _decode_types(&variant.fields)?,
+                })
+            })
+            .collect::<Option<_>>()?,
+    })
+}
+
+fn _encode_item_paths(
+    paths: &FxHashMap<DefId, (Vec<Symbol>, ItemType)>,
+) -> Option<Vec<CachedItemPath>> {
+    paths
+        .iter()
+        .map(|(def_id, (path, item_type))| {
+            Some(CachedItemPath {
+                def_id: _encode_def_id(*def_id),
+                path: path.iter().map(|symbol| symbol.to_string()).collect(),
+                item_type: _encode_by_name(&ITEM_TYPES, item_type)?,
+            })
+        })
+        .collect()
+}
+
+fn _decode_item_paths(
+    paths: &[CachedItemPath],
+) -> Option<FxHashMap<DefId, (Vec<Symbol>, ItemType)>> {
+    paths
+        .iter()
+        .map(|path| {
+            Some((
+                _decode_def_id(&path.def_id),
+                (
+                    path.path.iter().map(|name| Symbol::intern(name)).collect(),
+                    _decode_by_name(&ITEM_TYPES, &path.item_type)?,
+                ),
+            ))
+        })
+        .collect()
+}
+
+fn _encode_graph(api_graph: &ApiGraph<'_>, key: &CacheKey, fingerprint: u64) -> Option<GraphCache> {
+    let cache = api_graph.cache;
+    Some(GraphCache {
+        key: key.to_line(),
+        fingerprint,
+        crate_version: cache.crate_version.clone(),
+        paths: _encode_item_paths(&cache.paths)?,
+        external_paths: _encode_item_paths(&cache.external_paths)?,
+        primitive_locations: cache
+            .primitive_locations
+            .iter()
+            .map(|(primitive, def_id)| (primitive.as_sym().to_string(), _encode_def_id(*def_id)))
+            .collect(),
+        full_names: api_graph
+            .full_name_map
+            .map
+            .iter()
+            .map(|(def_id, (full_name, item_type))| {
+                Some(CachedItemPath {
+                    def_id: _encode_def_id(*def_id),
+                    path: vec![full_name.clone()],
+                    item_type: _encode_by_name(&ITEM_TYPES, item_type)?,
+                })
+            })
+            .collect::<Option<_>>()?,
+        functions: api_graph.api_functions.iter().map(_encode_function).collect::<Option<_>>()?,
+        dependencies: api_graph
+            .api_dependencies
+            .iter()
+            .map(|dependency| match (&dependency.output_fun, &dependency.input_fun) {
+                ((ApiType::BareFunction, output_fun), (ApiType::BareFunction, input_fun)) => {
+                    Some(CachedDependency {
+                        output_fun: *output_fun,
+                        input_fun: *input_fun,
+                        input_param_index: dependency.input_param_index,
+                        call_type: _encode_call_type(&dependency.call_type)?,
+                    })
+                }
+                _ => None,
+            })
+            .collect::<Option<_>>()?,
+        api_ids: api_graph.api_ids.iter().map(|(name, id)| (name.clone(), id.clone())).collect(),
+        excluded_functions: api_graph
+            .excluded_functions
+            .iter()
+            .map(|(name, reason)| (name.clone(), reason.clone()))
+            .collect(),
+        functions_with_unsupported_fuzzable_types: api_graph
+            .functions_with_unsupported_fuzzable_types
+            .iter()
+            .cloned()
+            .collect(),
+        mod_visibility: api_graph
+            .mod_visibility
+            .inner
+            .iter()
+            .map(|(mod_name, visibility)| (mod_name.clone(), visibility.is_public()))
+            .collect(),
+        clone_types: api_graph.clone_types.iter().map(|def_id| _encode_def_id(*def_id)).collect(),
+        std_traits: api_graph
+            .std_traits
+            .iter()
+            .map(|(def_id, std_trait)| (_encode_def_id(*def_id), *std_trait))
+            .collect(),
+        constructors: api_graph
+            .constructors
+            .iter()
+            .map(|(name, constructor)| Some((name.clone(), _encode_constructor(constructor)?)))
+            .collect::<Option<_>>()?,
+        iterator_types: api_graph
+            .iterator_types
+            .iter()
+            .map(|info| {
+                Some(CachedIterator {
+                    type_: _encode_type(&info.type_)?,
+                    item: _encode_option_type(info.item.as_ref())?,
+                    double_ended: info.double_ended,
+                })
+            })
+            .collect::<Option<_>>()?,
+    })
+}
+
+/// 从缓存里恢复出来的分析结果，恢复的ApiGraph借用其中的Cache
+pub(crate) struct CachedGraph {
+    cache: Cache,
+    crate_name: String,
+    full_name_map: FullNameMap,
+    api_functions: Vec<ApiFunction>,
+    api_dependencies: Vec<ApiDependency>,
+    api_ids: FxHashMap<String, ApiId>,
+    excluded_functions: FxHashMap<String, String>,
+    functions_with_unsupported_fuzzable_types: FxHashSet<String>,
+    mod_visibility: Vec<(String, Visibility)>,
+    clone_types: FxHashSet<DefId>,
+    std_traits: FxHashSet<(DefId, StdTrait)>,
+    constructors: FxHashMap<String, Constructor>,
+    iterator_types: Vec<IteratorInfo>,
+}
+
+impl CachedGraph {
+    pub(crate) fn to_api_graph(&self) -> ApiGraph<'_> {
+        let mut api_graph = ApiGraph::new(&self.crate_name, &self.cache);
+        api_graph.set_full_name_map(&self.full_name_map);
+        for (mod_name, visibility) in &self.mod_visibility {
+            api_graph.add_mod_visibility(mod_name, visibility);
+        }
+        api_graph.api_functions = self.api_functions.clone();
+        api_graph.api_dependencies = self.api_dependencies.clone();
+        api_graph.api_ids = self.api_ids.clone();
+        api_graph.excluded_functions = self.excluded_functions.clone();
+        api_graph.functions_with_unsupported_fuzzable_types =
+            self.functions_with_unsupported_fuzzable_types.clone();
+        api_graph.clone_types = self.clone_types.clone();
+        api_graph.std_traits = self.std_traits.clone();
+        api_graph.constructors = self.constructors.clone();
+        api_graph.iterator_types = self.iterator_types.clone();
+        println!(
+            "从缓存恢复了{}个API和{}条依赖",
+            api_graph.api_functions.len(),
+            api_graph.api_dependencies.len()
+        );
+        api_graph
+    }
+}
+
+fn _decode_graph(key: &CacheKey, graph_cache: &GraphCache) -> Option<CachedGraph> {
+    let mut cache = Cache::default();
+    cache.crate_version = graph_cache.crate_version.clone();
+    cache.paths = _decode_item_paths(&graph_cache.paths)?;
+    cache.external_paths = _decode_item_paths(&graph_cache.external_paths)?;
+    for (primitive, def_id) in &graph_cache.primitive_locations {
+        cache
+            .primitive_locations
+            .insert(PrimitiveType::from_symbol(Symbol::intern(primitive))?, _decode_def_id(def_id));
+    }
+    let mut full_name_map = FullNameMap::new();
+    for full_name in &graph_cache.full_names {
+        full_name_map.push_mapping(
+            _decode_def_id(&full_name.def_id),
+            full_name.path.first()?,
+            _decode_by_name(&ITEM_TYPES, &full_name.item_type)?,
+        );
+    }
+    Some(CachedGraph {
+        cache,
+        crate_name: key.crate_name.clone(),
+        full_name_map,
+        api_functions: graph_cache.functions.iter().map(_decode_function).collect::<Option<_>>()?,
+        api_dependencies: graph_cache
+            .dependencies
+            .iter()
+            .map(|dependency| {
+                Some(ApiDependency {
+                    output_fun: (ApiType::BareFunction, dependency.output_fun),
+                    input_fun: (ApiType::BareFunction, dependency.input_fun),
+                    input_param_index: dependency.input_param_index,
+                    call_type: _decode_call_type(&dependency.call_type)?,
+                })
+            })
+            .collect::<Option<_>>()?,
+        api_ids: graph_cache.api_ids.iter().cloned().collect(),
+        excluded_functions: graph_cache.excluded_functions.iter().cloned().collect(),
+        functions_with_unsupported_fuzzable_types: graph_cache
+            .functions_with_unsupported_fuzzable_types
+            .iter()
+            .cloned()
+            .collect(),
+        mod_visibility: graph_cache
+            .mod_visibility
+            .iter()
+            .map(|(mod_name, is_public)| (mod_name.clone(), _decode_visibility(*is_public)))
+            .collect(),
+        clone_types: graph_cache.clone_types.iter().map(_decode_def_id).collect(),
+        std_traits: graph_cache
+            .std_traits
+            .iter()
+            .map(|(def_id, std_trait)| (_decode_def_id(def_id), *std_trait))
+            .collect(),
+        constructors: graph_cache
+            .constructors
+            .iter()
+            .map(|(name, constructor)| Some((name.clone(), _decode_constructor(constructor)?)))
+            .collect::<Option<_>>()?,
+        iterator_types: graph_cache
+            .iterator_types
+            .iter()
+            .map(|info| {
+                Some(IteratorInfo {
+                    type_: _decode_type(&info.type_)?,
+                    item: _decode_option_type(info.item.as_ref())?,
+                    double_ended: info.double_ended,
+                })
+            })
+            .collect::<Option<_>>()?,
+    })
+}
+
+fn _cache_path(key: &CacheKey) -> Option<PathBuf> {
+    std::env::var(CACHE_DIR_ENV)
+        .ok()
+        .map(|cache_dir| PathBuf::from(cache_dir).join(key.file_name()))
+}
+
+/// 抽取API之前调用，有可用的缓存时返回分析好的API图
+pub(crate) fn load_api_graph(key: &CacheKey, support_generic: bool) -> Option<CachedGraph> {
+    let path = _cache_path(key)?;
+    let content = fs::read_to_string(&path).ok()?;
+    let graph_cache: GraphCache = match serde_json::from_str(&content) {
+        Ok(graph_cache) => graph_cache,
+        Err(e) => {
+            println!("无法解析API图的缓存{}: {}", path.display(), e);
+            return None;
+        }
+    };
+    if graph_cache.key != key.to_line() {
+        println!("源文件或者工具版本变了，API图的缓存{}失效", path.display());
+        return None;
+    }
+    if graph_cache.fingerprint != _fingerprint(support_generic) {
+        println!("影响分析结果的配置变了，API图的缓存{}失效", path.display());
+        return None;
+    }
+    match _decode_graph(key, &graph_cache) {
+        Some(cached_graph) => {
+            println!("使用API图的缓存{}，跳过API抽取和依赖分析", path.display());
+            Some(cached_graph)
+        }
+        None => {
+            println!("API图的缓存{}已经损坏", path.display());
+            None
+        }
+    }
+}
+
+/// 分析完之后调用，设置了缓存目录时写入缓存
+pub(crate) fn save_api_graph(api_graph: &ApiGraph<'_>, key: &CacheKey, support_generic: bool) {
+    let path = match _cache_path(key) {
+        Some(path) => path,
+        None => return,
+    };
+    let graph_cache = match _encode_graph(api_graph, key, _fingerprint(support_generic)) {
+        Some(graph_cache) => graph_cache,
+        None => {
+            println!("API图中有写不进缓存的类型，不写缓存{}", path.display());
+            return;
+        }
+    };
+    let content = match serde_json::to_string(&graph_cache) {
+        Ok(content) => content,
+        Err(e) => {
+            println!("无法序列化API图: {}", e);
+            return;
+        }
+    };
+    match fs::create_dir_all(path.parent().unwrap()).and_then(|_| fs::write(&path, content)) {
+        Ok(()) => println!("API图缓存到了{}", path.display()),
+        Err(e) => println!("无法写入API图的缓存{}: {}", path.display(), e),
+    }
+}
@@ -0,0 +1,241 @@
+//! 比较待测库两个版本的API图，让fuzz集中在改变了的API上
+//! 1. 设置`FRIES_EXPORT_API`时，把当前版本的API图（函数签名和依赖边）导出成快照
+//! 2. 设置`FRIES_API_DIFF_WITH`为旧版本的快照时，和当前版本比较，
+//!    把增加、删除、改变的API和边写到changelog，默认是`<旧快照>.changelog`，也可以用`FRIES_API_CHANGELOG`指定
+//! 3. 再设置`FRIES_FOCUS=changed`时，生成的每个序列都至少要包含一个新增或者签名改变了的API
+//! 快照每行一项，用tab分隔：`fn 路径 签名`，或者`edge 生产者 消费者 参数位置 调用方式`
+
+use crate::fuzz_targets_gen::api_graph::ApiGraph;
+use itertools::Itertools;
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use std::fs;
+
+static EXPORT_API_ENV: &'static str = "FRIES_EXPORT_API";
+static API_DIFF_WITH_ENV: &'static str = "FRIES_API_DIFF_WITH";
+static API_CHANGELOG_ENV: &'static str = "FRIES_API_CHANGELOG";
+static FOCUS_ENV: &'static str = "FRIES_FOCUS";
+
+/// 记录在manifest里的关注范围
+pub(crate) fn manifest_entries() -> Vec<(&'static str, String)> {
+    vec![
+        ("focus", std::env::var(FOCUS_ENV).unwrap_or_default()),
+        ("api_diff_with", std::env::var(API_DIFF_WITH_ENV).unwrap_or_default()),
+    ]
+}
+
+/// 一个版本的API图
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ApiSnapshot {
+    /// 路径 -> 签名，同一个路径可能对应多个函数（比如不同的trait实现）
+    pub(crate) functions: FxHashMap<String, FxHashSet<String>>,
+    /// (生产者, 消费者, 参数位置) -> 调用方式
+    pub(crate) edges: FxHashMap<(String, String, usize), String>,
+}
+
+impl ApiSnapshot {
+    pub(crate) fn from_graph(api_graph: &ApiGraph<'_>) -> Self {
+        let mut snapshot = ApiSnapshot::default();
+        for api_fun in &api_graph.api_functions {
+            snapshot
+                .functions
+                .entry(api_fun.full_name.clone())
+                .or_default()
+                .insert(api_fun._pretty_print(api_graph.cache, &api_graph.full_name_map));
+        }
+        for dependency in &api_graph.api_dependencies {
+            let output_fun = &api_graph.api_functions[dependency.output_fun.1];
+            let input_fun = &api_graph.api_functions[dependency.input_fun.1];
+            snapshot.edges.insert(
+                (
+                    output_fun.full_name.clone(),
+                    input_fun.full_name.clone(),
+                    dependency.input_param_index,
+                ),
+                format!("{:?}", dependency.call_type),
+            );
+        }
+        snapshot
+    }
+
+    pub(crate) fn read(path: &str) -> Option<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) => {
+                println!("无法读取API快照{}: {}", path, e);
+                return None;
+            }
+        };
+        let mut snapshot = ApiSnapshot::default();
+        for line in content.lines() {
+            let parts = line.split('\t').collect_vec();
+            match parts.as_slice() {
+                ["fn", full_name, signature] => {
+                    snapshot
+                        .functions
+                        .entry(full_name.to_string())
+                        .or_default()
+                        .insert(signature.to_string());
+                }
+                ["edge", output_fun, input_fun, param_index, call_type] => {
+                    if let Ok(param_index) = param_index.parse::<usize>() {
+                        snapshot.edges.insert(
+                            (output_fun.to_string(), input_fun.to_string(), param_index),
+                            call_type.to_string(),
+                        );
+                    }
+                }
+                _ => println!("API快照中无法解析的行: {}", line),
+            }
+        }
+        Some(snapshot)
+    }
+
+    pub(crate) fn write(&self, path: &str) {
+        let mut content = String::new();
+        for (full_name, signatures) in self.functions.iter().sorted_by(|x, y| x.0.cmp(y.0)) {
+            for signature in signatures.iter().sorted() {
+                content.push_str(format!("fn\t{}\t{}\n", full_name, signature).as_str());
+            }
+        }
+        for ((output_fun, input_fun, param_index), call_type) in
+            self.edges.iter().sorted_by(|x, y| x.0.cmp(y.0))
+        {
+            content.push_str(
+                format!("edge\t{}\t{}\t{}\t{}\n", output_fun, input_fun, param_index, call_type)
+                    .as_str(),
+            );
+        }
+        match fs::write(path, content) {
+            Ok(()) => println!(
+                "导出了{}个API和{}条依赖边到{}",
+                self.functions.len(),
+                self.edges.len(),
+                path
+            ),
+            Err(e) => println!("无法导出API快照到{}: {}", path, e),
+        }
+    }
+
+    /// 所有函数的签名
+    pub(crate) fn signatures(&self) -> FxHashSet<String> {
+        self.functions.values().flatten().cloned().collect()
+    }
+}
+
+/// 两个版本之间的差异，都按名字排好序
+#[derive(Debug, Clone, Default)]
+pub(crate) struct ApiDiff {
+    pub(crate) added_apis: Vec<String>,
+    pub(crate) removed_apis: Vec<String>,
+    pub(crate) changed_apis: Vec<String>,
+    pub(crate) added_edges: Vec<String>,
+    pub(crate) removed_edges: Vec<String>,
+    pub(crate) changed_edges: Vec<String>,
+}
+
+impl ApiDiff {
+    pub(crate) fn between(old: &ApiSnapshot, new: &ApiSnapshot) -> Self {
+        let mut diff = ApiDiff::default();
+        for (full_name, signatures) in &new.functions {
+            match old.functions.get(full_name) {
+                None => diff.added_apis.push(full_name.clone()),
+                Some(old_signatures) if old_signatures != signatures => {
+                    diff.changed_apis.push(full_name.clone())
+                }
+                _ => {}
+            }
+        }
+        for full_name in old.functions.keys() {
+            if !new.functions.contains_key(full_name) {
+                diff.removed_apis.push(full_name.clone());
+            }
+        }
+
+        let edge_name = |(output_fun, input_fun, param_index): &(String, String, usize)| {
+            format!("{} -> {}#{}", output_fun, input_fun, param_index)
+        };
+        for (edge, call_type) in &new.edges {
+            match old.edges.get(edge) {
+                None => diff.added_edges.push(edge_name(edge)),
+                Some(old_call_type) if old_call_type != call_type => diff
+                    .changed_edges
+                    .push(format!("{} ({} => {})", edge_name(edge), old_call_type, call_type)),
+                _ => {}
+            }
+        }
+        for edge in old.edges.keys() {
+            if !new.edges.contains_key(edge) {
+                diff.removed_edges.push(edge_name(edge));
+            }
+        }
+
+        diff.added_apis.sort();
+        diff.removed_apis.sort();
+        diff.changed_apis.sort();
+        diff.added_edges.sort();
+        diff.removed_edges.sort();
+        diff.changed_edges.sort();
+        diff
+    }
+
+    pub(crate) fn changelog(&self) -> String {
+        let sections = [
+            ("Added APIs", &self.added_apis),
+            ("Removed APIs", &self.removed_apis),
+            ("Changed APIs", &self.changed_apis),
+            ("Added edges", &self.added_edges),
+            ("Removed edges", &self.removed_edges),
+            ("Changed edges", &self.changed_edges),
+        ];
+        let mut res = String::new();
+        for (title, items) in sections {
+            res.push_str(format!("## {} ({})\n", title, items.len()).as_str());
+            for item in items {
+                res.push_str(format!("- {}\n", item).as_str());
+            }
+            res.push('\n');
+        }
+        res
+    }
+
+    /// 新增或者签名改变了的API，删除的API在当前版本里已经没法调用了
+    pub(crate) fn focus_apis(&self) -> FxHashSet<String> {
+        self.added_apis.iter().chain(self.changed_apis.iter()).cloned().collect()
+    }
+}
+
+/// 设置了`FRIES_EXPORT_API`时，导出当前版本的API图
+pub(crate) fn export_snapshot(api_graph: &ApiGraph<'_>) {
+    if let Ok(path) = std::env::var(EXPORT_API_ENV) {
+        ApiSnapshot::from_graph(api_graph).write(&path);
+    }
+}
+
+/// 设置了`FRIES_API_DIFF_WITH`时，和旧版本比较并写changelog，需要的话设置要关注的API
+pub(crate) fn diff_with_old_version(api_graph: &mut ApiGraph<'_>) {
+    let old_path = match std::env::var(API_DIFF_WITH_ENV) {
+        Ok(old_path) => old_path,
+        Err(_) => return,
+    };
+    let old = match ApiSnapshot::read(&old_path) {
+        Some(old) => old,
+        None => return,
+    };
+    let diff = ApiDiff::between(&old, &ApiSnapshot::from_graph(api_graph));
+    let changelog_path =
+        std::env::var(API_CHANGELOG_ENV).unwrap_or(format!("{}.changelog", old_path));
+    match fs::write(&changelog_path, diff.changelog()) {
+        Ok(()) => println!(
+            "API变化：新增{}个，删除{}个，改变{}个，changelog写到了{}",
+            diff.added_apis.len(),
+            diff.removed_apis.len(),
+            diff.changed_apis.len(),
+            changelog_path
+        ),
+        Err(e) => println!("无法写入changelog {}: {}", changelog_path, e),
+    }
+
+    if std::env::var(FOCUS_ENV).map_or(false, |focus| focus == "changed") {
+        api_graph.focus_apis = Some(diff.focus_apis());
+    }
+}
@@ -47,6 +47,36 @@ pub(crate) fn _is_unsafe(&self) -> bool {
     }
 }
 
+/// 用来标识API是否async
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) enum ApiAsyncness {
+    Async,
+    NotAsync,
+}
+
+impl ApiAsyncness {
+    //辅助构造函数作用，标识函数是否是async
+    pub(crate) fn _get_asyncness_from_fnheader(fn_header: &rustc_hir::FnHeader) -> Self {
+        match fn_header.asyncness {
+            rustc_hir::IsAsync::Async => ApiAsyncness::Async,
+            rustc_hir::IsAsync::NotAsync => ApiAsyncness::NotAsync,
+        }
+    }
+
+    //返回是否async
+    pub(crate) fn _is_async(&self) -> bool {
+        match self {
+            ApiAsyncness::Async => true,
+            ApiAsyncness::NotAsync => false,
+        }
+    }
+
+    //async函数声明的返回值是impl Future<Output = T>，取出.await之后的T
+    pub(crate) fn _return_type(&self, decl: &clean::FnDecl) -> clean::FnRetTy {
+        if self._is_async() { decl.sugared_async_return_type() } else { decl.output.clone() }
+    }
+}
+
 /// 用来标识API图中的API
 #[derive(Clone, Debug)]
 pub(crate) struct ApiFunction {
@@ -57,6 +87,7 @@ pub(crate) struct ApiFunction {
     pub(crate) output: Option<clean::Type>, //返回值
     pub(crate) _trait_full_path: Option<String>, //Trait的全限定路径,因为使用trait::fun来调用函数的时候，需要将trait的全路径引入
     pub(crate) _unsafe_tag: ApiUnsafety,         //是否unsafe
+    pub(crate) _async_tag: ApiAsyncness,         //是否async，async函数的output是.await之后的类型
     pub(crate) visibility: Visibility,           //可见性
 }
 
@@ -1,23 +1,44 @@
 use super::api_sequence::ReverseApiSequence;
+use super::async_util;
+use super::borrow_check;
+use super::callback;
+use super::constructor::{self, Constructor};
+use super::differential;
 use super::fuzz_type;
+use super::iterator::{self, IteratorInfo};
 use crate::clean::{self, types};
 use crate::formats::cache::Cache;
 use crate::fuzz_targets_gen::api_function::ApiFunction;
+use crate::fuzz_targets_gen::api_id::ApiId;
 use crate::fuzz_targets_gen::api_sequence::{ApiCall, ApiSequence, ParamType};
 use crate::fuzz_targets_gen::api_util::{self};
 use crate::fuzz_targets_gen::call_type::CallType;
+use crate::fuzz_targets_gen::corpus;
 use crate::fuzz_targets_gen::fuzz_type::FuzzableType;
-use crate::fuzz_targets_gen::impl_util::FullNameMap;
+use crate::fuzz_targets_gen::impl_util::{FullNameMap, StdTrait};
 use crate::fuzz_targets_gen::mod_visibility::ModVisibity;
 use crate::fuzz_targets_gen::prelude_type::{self, PreludeType};
+use crate::fuzz_targets_gen::type_index::ConsumerIndex;
 use itertools::Itertools;
-use rand::thread_rng;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use rustc_hir::def_id::DefId;
 use rustc_middle::ty::Visibility;
-use std::time::Duration;
+use std::cell::RefCell;
+use std::time::{Duration, Instant};
 //use super::generic_function::GenericFunction;
 
+/// 随机数种子，同样的输入和种子生成完全相同的测试，不设置时随机选一个并记录在manifest里
+static SEED_ENV: &'static str = "FRIES_SEED";
+/// 配置文件，每行`key = value`，格式和输出的manifest.txt一样，可以直接用之前的manifest复现
+static CONFIG_ENV: &'static str = "FRIES_CONFIG";
+/// 设置了这个环境变量（并且不是0）的时候，再用原来两两比较的方法找一遍依赖，检查两边得到的边是否一样
+static CHECK_DEPENDENCIES_ENV: &'static str = "FRIES_CHECK_DEPENDENCIES";
+
+/// 有关注范围的时候，最多尝试生成max_num的这么多倍个序列，防止关注的API一直生成不出来
+static MAX_FOCUS_ATTEMPTS_FACTOR: usize = 100;
+
 lazy_static! {
     static ref RANDOM_WALK_STEPS: FxHashMap<&'static str, usize> = {
         let mut m = FxHashMap::default();
@@ -64,6 +85,37 @@ pub(crate) struct ApiGraph<'a> {
     ///暂时不支持的
     //pub(crate) generic_functions: Vec<GenericFunction>,
     pub(crate) functions_with_unsupported_fuzzable_types: FxHashSet<String>,
+
+    /// 被过滤掉的API和过滤的原因，key是full_name，查询为什么某个API没有出现时用
+    pub(crate) excluded_functions: FxHashMap<String, String>,
+
+    /// 实现了Clone的类型，被move之后可以通过clone继续使用
+    pub(crate) clone_types: FxHashSet<DefId>,
+
+    /// 类型实现的其他标准库trait，用来打印错误值和检查trait的约定
+    pub(crate) std_traits: FxHashSet<(DefId, StdTrait)>,
+
+    /// 遍历时找到的可以直接构造的结构体和枚举，等过滤完函数之后再决定是否加入
+    pub(crate) constructor_candidates: Vec<Constructor>,
+
+    /// 加入api_functions的合成构造函数，key是对应ApiFunction的full_name
+    pub(crate) constructors: FxHashMap<String, Constructor>,
+
+    /// 实现了Iterator的类型，以及返回值中的`impl Iterator<..>`
+    pub(crate) iterator_types: Vec<IteratorInfo>,
+
+    /// 只关注这些API的时候，生成的序列至少要包含其中一个，key是full_name
+    pub(crate) focus_apis: Option<FxHashSet<String>>,
+
+    /// 库中API的标识，用来和corpus中的名字对应，key是full_name，json前端和合成的API没有
+    pub(crate) api_ids: FxHashMap<String, ApiId>,
+
+    /// 按种类加权并且对应到full_name之后的corpus信息，生成序列之前读入
+    pub(crate) corpus_info: corpus::CorpusInfo,
+
+    /// 生成过程中所有的随机选择都用这个随机数生成器
+    pub(crate) seed: u64,
+    pub(crate) rng: RefCell<StdRng>,
     pub(crate) cache: &'a Cache,
     //pub(crate) _sequences_of_all_algorithm : FxFxHashMap<GraphTraverseAlgorithm, Vec<ApiSequence>>
 }
@@ -112,6 +164,8 @@ impl<'a> ApiGraph<'a> {
     /// 新建一个api_graph
     pub(crate) fn new(_crate_name: &String, cache: &'a Cache) -> Self {
         //let _sequences_of_all_algorithm = FxFxHashMap::default();
+        let seed = _generation_seed();
+        println!("random seed is {}", seed);
         ApiGraph {
             _crate_name: _crate_name.to_owned(),
             api_functions: Vec::new(),
@@ -122,19 +176,40 @@ pub(crate) fn new(_crate_name: &String, cache: &'a Cache) -> Self {
             mod_visibility: ModVisibity::new(_crate_name),
             //generic_functions: Vec::new(),
             functions_with_unsupported_fuzzable_types: FxHashSet::default(),
+            excluded_functions: FxHashMap::default(),
+            clone_types: FxHashSet::default(),
+            std_traits: FxHashSet::default(),
+            constructor_candidates: Vec::new(),
+            constructors: FxHashMap::default(),
+            iterator_types: Vec::new(),
+            focus_apis: None,
+            api_ids: FxHashMap::default(),
+            corpus_info: corpus::CorpusInfo::default(),
+            seed,
+            rng: RefCell::new(StdRng::seed_from_u64(seed)),
             cache,
         }
     }
 
+    /// 命令行指定的种子，优先于环境变量和配置文件
+    pub(crate) fn set_seed(&mut self, seed: u64) {
+        println!("random seed is set to {}", seed);
+        self.seed = seed;
+        self.rng = RefCell::new(StdRng::seed_from_u64(seed));
+    }
+
     /// 向api_graph中投入function，包括method和bare function，支持泛型
     pub(crate) fn add_api_function(&mut self, mut api_fun: ApiFunction) {
         /*if api_fun._is_generic_function() {
             let generic_function = GenericFunction::from(api_fun);
             // self.generic_functions.push(generic_function);
         } else*/
+        //只作为回调使用的泛型参数，改写成可以合成的闭包
+        callback::_rewrite_callback_generics(&mut api_fun);
         //泛型函数不会单独考虑
         if api_fun.contains_unsupported_fuzzable_type(self.cache, &self.full_name_map) {
             self.functions_with_unsupported_fuzzable_types.insert(api_fun.full_name.clone());
+            self.exclude_function(&api_fun.full_name, "contains unsupported fuzzable type");
         } else {
             // FIXME:新加入泛型
             //既然支持了泛型函数，就要初始化generic_substitution
@@ -152,6 +227,180 @@ pub(crate) fn add_api_function(&mut self, mut api_fun: ApiFunction) {
         }
     }
 
+    /// 记录API的标识，在add_api_function之前调用，被过滤掉的API也要记录
+    pub(crate) fn add_api_id(&mut self, full_name: &str, api_id: ApiId) {
+        self.api_ids.insert(full_name.to_string(), api_id);
+    }
+
+    /// 记录一个可以直接构造的结构体或者枚举
+    pub(crate) fn add_constructor_candidate(&mut self, constructor: Constructor) {
+        self.constructor_candidates.push(constructor);
+    }
+
+    /// 为字段都能产生的公开类型合成构造函数，加入api_functions
+    /// 一个类型能构造之后又可以作为其他类型的字段，所以先迭代到不动点，再决定枚举保留哪些variant
+    pub(crate) fn add_constructors(&mut self) {
+        let invisible_mods = self.mod_visibility.get_invisible_mods();
+        let mut candidates: Vec<Constructor> = self
+            .constructor_candidates
+            .drain(..)
+            .filter(|constructor| {
+                !invisible_mods
+                    .iter()
+                    .any(|invisible_mod| constructor.type_path.starts_with(invisible_mod.as_str()))
+            })
+            .collect();
+
+        let mut output_types: Vec<clean::Type> =
+            self.api_functions.iter().filter_map(|api_fun| api_fun.output.clone()).collect();
+        let mut accepted = Vec::new();
+        loop {
+            let mut rest = Vec::new();
+            let accepted_num = accepted.len();
+            for constructor in candidates {
+                let constructible = constructor.variants.iter().any(|variant| {
+                    variant.fields.iter().all(|field| {
+                        constructor::_is_producible_type(
+                            field,
+                            &output_types,
+                            self.cache,
+                            &self.full_name_map,
+                        )
+                    })
+                });
+                if constructible {
+                    output_types.push(constructor.type_.clone());
+                    accepted.push(constructor);
+                } else {
+                    rest.push(constructor);
+                }
+            }
+            candidates = rest;
+            if accepted.len() == accepted_num {
+                break;
+            }
+        }
+
+        for mut constructor in accepted {
+            let cache = self.cache;
+            let full_name_map = &self.full_name_map;
+            constructor.retain_variants(|field| {
+                constructor::_is_producible_type(field, &output_types, cache, full_name_map)
+            });
+            let fuzzable =
+                |field: &clean::Type| api_util::is_fuzzable_type(field, cache, full_name_map, None);
+            for variant_constructor in constructor.split_variants(fuzzable) {
+                let api_fun = variant_constructor.to_api_function();
+                let full_name = api_fun.full_name.clone();
+                let function_num = self.api_functions.len();
+                self.add_api_function(api_fun);
+                if self.api_functions.len() > function_num {
+                    self.constructors.insert(full_name, variant_constructor);
+                }
+            }
+        }
+        println!("synthesized {} constructors", self.constructors.len());
+    }
+
+    /// 记录一个实现了Iterator的类型
+    pub(crate) fn add_iterator_type(&mut self, type_: &clean::Type, item: Option<clean::Type>) {
+        self._iterator_info_mut(type_).item = item;
+    }
+
+    /// 记录一个实现了DoubleEndedIterator的类型
+    pub(crate) fn set_double_ended_iterator(&mut self, type_: &clean::Type) {
+        self._iterator_info_mut(type_).double_ended = true;
+    }
+
+    fn _iterator_info_mut(&mut self, type_: &clean::Type) -> &mut IteratorInfo {
+        let position = self.iterator_types.iter().position(|info| info.type_ == *type_);
+        let index = match position {
+            Some(index) => index,
+            None => {
+                self.iterator_types.push(IteratorInfo::new(type_));
+                self.iterator_types.len() - 1
+            }
+        };
+        &mut self.iterator_types[index]
+    }
+
+    /// 为每种迭代器加入取元素和驱动的辅助函数
+    /// 除了Cache中找到的迭代器类型，还包括返回`impl Iterator<..>`的函数的返回值
+    pub(crate) fn add_iterator_functions(&mut self) {
+        for func_index in 0..self.api_functions.len() {
+            if let Some(output) = self._local_type(func_index) {
+                if let Some(info) = IteratorInfo::from_impl_trait(&output) {
+                    if !self.iterator_types.iter().any(|exist| exist.type_ == info.type_) {
+                        self.iterator_types.push(info);
+                    }
+                }
+            }
+        }
+
+        let invisible_mods = self.mod_visibility.get_invisible_mods();
+        let mut iterator_types = Vec::new();
+        let mut used_names = FxHashSet::default();
+        for (index, info) in self.iterator_types.iter().enumerate() {
+            let type_name = info
+                .type_
+                .def_id(self.cache)
+                .and_then(|def_id| self.full_name_map._get_full_name(def_id));
+            if let Some(type_name) = type_name {
+                if invisible_mods
+                    .iter()
+                    .any(|invisible_mod| type_name.starts_with(invisible_mod.as_str()))
+                {
+                    continue;
+                }
+            }
+            //`impl Iterator`没有名字；同一个类型的不同实例化名字相同，加上序号区分
+            let mut type_name = match type_name {
+                Some(type_name) => type_name.clone(),
+                None => format!("impl_Iterator_{}", index),
+            };
+            if !used_names.insert(type_name.clone()) {
+                type_name = format!("{}_{}", type_name, index);
+                used_names.insert(type_name.clone());
+            }
+            iterator_types.push((type_name, info.clone()));
+        }
+
+        let function_num = self.api_functions.len();
+        for (type_name, info) in &iterator_types {
+            for api_fun in info.to_api_functions(type_name) {
+                self.add_api_function(api_fun);
+            }
+        }
+        println!(
+            "add {} iterator helper functions for {} iterator types",
+            self.api_functions.len() - function_num,
+            iterator_types.len()
+        );
+    }
+
+    /// 差分测试时只保留另一个版本里也有、并且签名相同的API
+    pub(crate) fn intersect_with_other_version(&mut self) {
+        let other_signatures = match differential::other_version_signatures() {
+            Some(other_signatures) => other_signatures,
+            None => return,
+        };
+        let function_num = self.api_functions.len();
+        let cache = self.cache;
+        let full_name_map = &self.full_name_map;
+        let (kept, removed): (Vec<_>, Vec<_>) = self.api_functions.drain(..).partition(|api_fun| {
+            other_signatures.contains(&api_fun._pretty_print(cache, full_name_map))
+        });
+        self.api_functions = kept;
+        for api_fun in removed {
+            self.exclude_function(&api_fun.full_name, "signature differs in the other version");
+        }
+        println!(
+            "差分测试：两个版本共有{}个API，去掉了{}个",
+            self.api_functions.len(),
+            function_num - self.api_functions.len()
+        );
+    }
+
     /// 遍历到某个mod的时候，添加mod的可见性，为过滤出可见的api做准备
     pub(crate) fn add_mod_visibility(&mut self, mod_name: &String, visibility: &Visibility) {
         self.mod_visibility.add_one_mod(mod_name, visibility);
@@ -173,10 +422,12 @@ pub(crate) fn filter_functions(&mut self, support_generic: bool) {
         // 不支持泛型，就把泛型过滤出来
         if !support_generic {
             let mut new_api_function = Vec::new();
+            let mut excluded = Vec::new();
             for func in &self.api_functions {
                 //if func._generics.params.len() == 0 {
-                if !func._is_generic_function()
-                    && !func.full_name.contains("from_static")
+                if func._is_generic_function() {
+                    excluded.push((func.full_name.clone(), "generic function"));
+                } else if !func.full_name.contains("from_static")
                     && !func.full_name.contains("with_capacity")
                     && !func.full_name.contains("TimeDelta")
                     && !func.full_name.contains("from_raw_parts_mut")
@@ -188,9 +439,14 @@ pub(crate) fn filter_functions(&mut self, support_generic: bool) {
                     && !func.full_name.contains("scancode")
                 {
                     new_api_function.push(func.clone());
+                } else {
+                    excluded.push((func.full_name.clone(), "in the hard-coded deny list"));
                 }
             }
             self.api_functions = new_api_function;
+            for (full_name, reason) in excluded {
+                self.exclude_function(&full_name, reason);
+            }
         }
         println!("filtered api functions contain {} apis", self.api_functions.len());
     }
@@ -201,11 +457,14 @@ pub(crate) fn filter_functions_defined_on_prelude_type(&mut self) {
         if prelude_types.len() <= 0 {
             return;
         }
-        self.api_functions = self
+        let (kept, removed): (Vec<_>, Vec<_>) = self
             .api_functions
             .drain(..)
-            .filter(|api_function| api_function.is_not_defined_on_prelude_type(&prelude_types))
-            .collect();
+            .partition(|api_function| api_function.is_not_defined_on_prelude_type(&prelude_types));
+        self.api_functions = kept;
+        for api_function in removed {
+            self.exclude_function(&api_function.full_name, "defined on prelude type");
+        }
     }
 
     /// 过滤api，根据可见性进行过滤，不是pub就过滤掉
@@ -218,201 +477,297 @@ pub(crate) fn filter_api_functions_by_mod_visibility(&mut self) {
         let invisible_mods = self.mod_visibility.get_invisible_mods();
 
         let mut new_api_functions = Vec::new();
+        let mut excluded = Vec::new();
 
         //遍历api_graph中的所有的api
         for api_func in &self.api_functions {
             let api_func_name = &api_func.full_name;
             let trait_full_path = &api_func._trait_full_path;
-            let mut invisible_flag = false;
+            let mut invisible_reason = None;
             for invisible_mod in &invisible_mods {
                 // 两种情况下api不可见：
                 // 1. crate::m1::m2::api中的某个mod不可见
                 // 2. api实现了某个trait，同时trait不可见
                 if api_func_name.as_str().starts_with(invisible_mod.as_str()) {
-                    invisible_flag = true;
+                    invisible_reason = Some(format!("mod {} is not visible", invisible_mod));
                     break;
                 }
                 if api_func_name.as_str().ends_with("lossy_normalization")
                     || api_func_name.as_str().ends_with(":TokenizerBuilder::new")
                 {
-                    invisible_flag = true;
+                    invisible_reason = Some("in the hard-coded deny list".to_string());
                     break;
                 }
 
                 if let Some(trait_full_path) = trait_full_path {
                     if trait_full_path.as_str().starts_with(invisible_mod) {
-                        invisible_flag = true;
+                        invisible_reason =
+                            Some(format!("trait {} is not visible", trait_full_path));
                         break;
                     }
                 }
             }
+            if invisible_reason.is_none() && !api_func.visibility.is_public() {
+                invisible_reason = Some("not public".to_string());
+            }
 
             // parent所在mod可见
-            if !invisible_flag && api_func.visibility.is_public() {
-                new_api_functions.push(api_func.clone());
+            match invisible_reason {
+                None => new_api_functions.push(api_func.clone()),
+                Some(reason) => excluded.push((api_func_name.clone(), reason)),
             }
         }
         self.api_functions = new_api_functions;
+        for (full_name, reason) in excluded {
+            self.exclude_function(&full_name, reason.as_str());
+        }
     }
 
-    pub(crate) fn set_full_name_map(&mut self, full_name_map: &FullNameMap) {
-        self.full_name_map = full_name_map.clone();
+    /// [low, high)
+    pub(crate) fn gen_range(&self, low: usize, high: usize) -> usize {
+        self.rng.borrow_mut().gen_range(low, high)
     }
 
-    ///找到所有可能的依赖关系，存在api_dependencies中，供后续使用
-    pub(crate) fn find_all_dependencies(&mut self, support_generic: bool) {
-        println!("find_dependencies");
-        self.api_dependencies.clear();
+    /// 记录被过滤掉的API，同名的API只记第一个原因
+    pub(crate) fn exclude_function(&mut self, full_name: &String, reason: &str) {
+        self.excluded_functions.entry(full_name.clone()).or_insert(reason.to_string());
+    }
 
-        // 两个api_function之间的dependency
-        // 其中i和j分别是first_fun和second_fun在api_graph的index
-        for (i, first_fun) in self.api_functions.iter().enumerate() {
-            if first_fun._is_end_function(self.cache, &self.full_name_map, support_generic) {
-                //如果第一个函数是终止节点，就不寻找这样的依赖
-                continue;
-            }
+    pub(crate) fn set_full_name_map(&mut self, full_name_map: &FullNameMap) {
+        self.full_name_map = full_name_map.clone();
+    }
 
-            if let Some(ty_) = &first_fun.output {
-                let mut output_type = ty_.clone();
+    pub(crate) fn add_clone_type(&mut self, def_id: DefId) {
+        self.clone_types.insert(def_id);
+    }
 
-                //FIXME: 因为很多new或者什么的返回值是Some(T)或者Ok(T)
-                //在这里对unwrap做特殊处理！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！
-                //如果output_type是Option或者Result，那么就先提取出来，下面_same_type就减少了unwrap那一步，之后生成的部分就不会多出来一个unwrap
-                //后面在生成function call字符串的时候，特殊考虑一下，如果output_type就直接.unwrap()就好了
-                if prelude_type::_prelude_type_need_special_dealing(
-                    &output_type,
-                    self.cache,
-                    &self.full_name_map,
-                ) {
-                    //如果是option或者result，先转化prelude_type，然后让output变成里面包装的东西
-                    let prelude_type =
-                        PreludeType::from_type(&output_type, self.cache, &self.full_name_map);
-                    output_type = prelude_type._get_final_type();
-                }
+    pub(crate) fn add_std_trait(&mut self, def_id: DefId, std_trait: StdTrait) {
+        self.std_traits.insert((def_id, std_trait));
+    }
 
-                //！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！！
+    pub(crate) fn has_std_trait(&self, def_id: DefId, std_trait: StdTrait) -> bool {
+        self.std_traits.contains(&(def_id, std_trait))
+    }
 
-                for (j, second_fun) in self.api_functions.iter().enumerate() {
-                    //FIXME:是否要把i=j的情况去掉？
-                    if second_fun._is_start_function(
-                        self.cache,
-                        &self.full_name_map,
-                        support_generic,
-                    ) {
-                        //如果第二个节点是开始节点，那么直接跳过
-                        continue;
-                    }
-                    /*println!(
-                        "\nThe first function {} is: {}",
-                        i,
-                        first_fun._pretty_print(self.cache, &self.full_name_map)
-                    );
-                    println!(
-                        "The second function {} is: {}",
-                        j,
-                        second_fun._pretty_print(self.cache, &self.full_name_map)
-                    );*/
-                    //FIXME:写一个替换函数，在这里就把type给替换掉。
-
-                    // 下面开始正题
-                    // 对于second_fun的每个参数，看看first_fun的返回值是否对应得上
-                    for (k, input_type) in second_fun.inputs.iter().enumerate() {
-                        let mut input_type = input_type.clone();
-                        //为了添加泛型支持，在这里先替换
-                        /*println!(
-                            "替换前output: {}",
-                            api_util::_type_name(&output_type, self.cache, &self.full_name_map)
-                                .as_str()
-                        );
-                        println!(
-                            "替换前input: {}",
-                            api_util::_type_name(&input_type, self.cache, &self.full_name_map)
-                                .as_str()
-                        );*/
-                        if support_generic {
-                            output_type = match api_util::substitute_type(
-                                output_type.clone(),
-                                &first_fun.generic_substitutions,
-                            ) {
-                                Some(substi) => substi,
-                                None => {
-                                    continue;
+    /// 判断一个类型是否可以clone，泛型参数也要求都可以clone
+    pub(crate) fn is_clone_type(&self, ty: &clean::Type) -> bool {
+        match ty {
+            clean::Type::Primitive(primitive_type) => match primitive_type {
+                clean::PrimitiveType::Str | clean::PrimitiveType::Slice => false,
+                _ => true,
+            },
+            clean::Type::Tuple(types) => types.iter().all(|ty_| self.is_clone_type(ty_)),
+            clean::Type::Array(inner_type, _) => self.is_clone_type(inner_type),
+            clean::Type::Path { path } => {
+                let def_id = match ty.def_id(self.cache) {
+                    Some(def_id) => def_id,
+                    None => return false,
+                };
+                //Option，Result，String都是Clone的，剩下的看有没有impl Clone
+                let is_prelude = match self.full_name_map._get_full_name(def_id) {
+                    Some(full_name) => prelude_type::is_preluded_type(full_name),
+                    None => false,
+                };
+                if !is_prelude && !self.clone_types.contains(&def_id) {
+                    return false;
+                }
+                for segment in &path.segments {
+                    if let clean::GenericArgs::AngleBracketed { args, .. } = &segment.args {
+                        for arg in args.iter() {
+                            if let clean::GenericArg::Type(inner_type) = arg {
+                                if !self.is_clone_type(inner_type) {
+                                    return false;
                                 }
-                            };
-                            input_type = match api_util::substitute_type(
-                                input_type.clone(),
-                                &second_fun.generic_substitutions,
-                            ) {
-                                Some(substi) => substi,
-                                None => {
-                                    continue;
-                                }
-                            };
-                        } else {
-                            /*println!(
-                                "找：{}",
-                                api_util::_type_name(&output_type, self.cache, &self.full_name_map)
-                            );*/
-                            if (!prelude_type::_prelude_type_need_special_dealing(
-                                &output_type,
-                                self.cache,
-                                &self.full_name_map,
-                            ) && api_util::_is_generic_type(&output_type))
-                                || (!prelude_type::_prelude_type_need_special_dealing(
-                                    &input_type,
-                                    self.cache,
-                                    &self.full_name_map,
-                                ) && api_util::_is_generic_type(&input_type))
-                            {
-                                /*println!(
-                                    "找到了泛型，不支持：{}",
-                                    api_util::_type_name(
-                                        &output_type,
-                                        self.cache,
-                                        &self.full_name_map
-                                    )
-                                );*/
-                                continue;
-                            }
-                        }
-                        /*println!(
-                            "替换后output: {}",
-                            api_util::_type_name(&output_type, self.cache, &self.full_name_map)
-                                .as_str()
-                        );
-                        println!(
-                            "替换后input: {}",
-                            api_util::_type_name(&input_type, self.cache, &self.full_name_map)
-                                .as_str()
-                        );*/
-                        let call_type = api_util::_same_type(
-                            &output_type,
-                            &input_type,
-                            true,
-                            self.cache,
-                            &self.full_name_map,
-                        );
-                        match &call_type {
-                            CallType::_NotCompatible => {
-                                //如果无法转换，那就算了
-                                continue;
-                            }
-                            _ => {
-                                //println!("ok, it's ok!!!");
-                                //如果可以转换的话，那就存入依赖列表里
-                                let one_dependency = ApiDependency {
-                                    output_fun: (ApiType::BareFunction, i),
-                                    input_fun: (ApiType::BareFunction, j),
-                                    input_param_index: k,
-                                    call_type: call_type.clone(),
-                                };
-                                self.api_dependencies.push(one_dependency);
                             }
                         }
                     }
                 }
+                true
+            }
+            //引用调用clone会得到被引用的值，这里不考虑
+            _ => false,
+        }
+    }
+
+    /// 某个函数的返回值被move之前是否可以（并且需要）先clone
+    pub(crate) fn can_clone_local(&self, func_index: usize) -> bool {
+        match self._local_type(func_index) {
+            Some(ty) => !api_util::_copy_type(&ty) && self.is_clone_type(&ty),
+            None => false,
+        }
+    }
+
+    /// 序列里某个函数返回值最终保存在局部变量里的类型，Option和Result会先被解开
+    pub(crate) fn _local_type(&self, func_index: usize) -> Option<clean::Type> {
+        let output_type = self.api_functions[func_index].output.as_ref()?;
+        if prelude_type::_prelude_type_need_special_dealing(
+            output_type,
+            self.cache,
+            &self.full_name_map,
+        ) {
+            let prelude_type = PreludeType::from_type(output_type, self.cache, &self.full_name_map);
+            Some(prelude_type._get_final_type())
+        } else {
+            Some(output_type.clone())
+        }
+    }
+
+    /// 不支持泛型的时候，剥掉Option和Result之后仍然是泛型的类型不考虑
+    fn _is_unsupported_generic(&self, ty: &clean::Type, support_generic: bool) -> bool {
+        !support_generic
+            && !prelude_type::_prelude_type_need_special_dealing(
+                ty,
+                self.cache,
+                &self.full_name_map,
+            )
+            && api_util::_is_generic_type(ty)
+    }
+
+    /// 第i个函数作为生产者时的返回值类型，终止节点和没有返回值的函数返回None
+    fn _producer_output_type(&self, i: usize, support_generic: bool) -> Option<clean::Type> {
+        let first_fun = &self.api_functions[i];
+        if first_fun._is_end_function(self.cache, &self.full_name_map, support_generic) {
+            //如果第一个函数是终止节点，就不寻找这样的依赖
+            return None;
+        }
+        let mut output_type = first_fun.output.clone()?;
+
+        //FIXME: 因为很多new或者什么的返回值是Some(T)或者Ok(T)
+        //如果output_type是Option或者Result，那么就先提取出来，下面_same_type就减少了unwrap那一步，之后生成的部分就不会多出来一个unwrap
+        //后面在生成function call字符串的时候，特殊考虑一下，如果output_type就直接.unwrap()就好了
+        if prelude_type::_prelude_type_need_special_dealing(
+            &output_type,
+            self.cache,
+            &self.full_name_map,
+        ) {
+            //如果是option或者result，先转化prelude_type，然后让output变成里面包装的东西
+            let prelude_type =
+                PreludeType::from_type(&output_type, self.cache, &self.full_name_map);
+            output_type = prelude_type._get_final_type();
+        }
+
+        if support_generic {
+            api_util::substitute_type(output_type, &first_fun.generic_substitutions)
+        } else if self._is_unsupported_generic(&output_type, support_generic) {
+            None
+        } else {
+            Some(output_type)
+        }
+    }
+
+    /// 第j个函数的第k个参数作为消费者时的类型，开始节点返回None
+    fn _consumer_input_type(
+        &self,
+        j: usize,
+        k: usize,
+        support_generic: bool,
+    ) -> Option<clean::Type> {
+        let second_fun = &self.api_functions[j];
+        if second_fun._is_start_function(self.cache, &self.full_name_map, support_generic) {
+            //如果第二个节点是开始节点，那么直接跳过
+            return None;
+        }
+        let input_type = second_fun.inputs[k].clone();
+        //为了添加泛型支持，在这里先替换
+        if support_generic {
+            api_util::substitute_type(input_type, &second_fun.generic_substitutions)
+        } else if self._is_unsupported_generic(&input_type, support_generic) {
+            None
+        } else {
+            Some(input_type)
+        }
+    }
+
+    /// 第i个函数的返回值能否作为第j个函数的第k个参数
+    fn _dependency_between(
+        &self,
+        i: usize,
+        output_type: &clean::Type,
+        j: usize,
+        k: usize,
+        support_generic: bool,
+    ) -> Option<ApiDependency> {
+        let input_type = self._consumer_input_type(j, k, support_generic)?;
+        let call_type =
+            api_util::_same_type(output_type, &input_type, true, self.cache, &self.full_name_map);
+        match &call_type {
+            //如果无法转换，那就算了
+            CallType::_NotCompatible => None,
+            _ => Some(ApiDependency {
+                output_fun: (ApiType::BareFunction, i),
+                input_fun: (ApiType::BareFunction, j),
+                input_param_index: k,
+                call_type,
+            }),
+        }
+    }
+
+    ///找到所有可能的依赖关系，存在api_dependencies中，供后续使用
+    pub(crate) fn find_all_dependencies(&mut self, support_generic: bool) {
+        println!("find_dependencies");
+        self.api_dependencies.clear();
+
+        // 1. 把所有消费者的参数类型归一化，按类型key建立索引
+        let phase_start = Instant::now();
+        let mut consumer_index = ConsumerIndex::default();
+        for (j, second_fun) in self.api_functions.iter().enumerate() {
+            for k in 0..second_fun.inputs.len() {
+                if let Some(input_type) = self._consumer_input_type(j, k, support_generic) {
+                    consumer_index.add_consumer(&input_type, j, k, self.cache, &self.full_name_map);
+                }
+            }
+        }
+        println!(
+            "find_dependencies: 索引了{}种参数类型，用时{:?}",
+            consumer_index.interner.len(),
+            phase_start.elapsed()
+        );
+
+        // 2. 对每个生产者的返回值，只和索引中可能兼容的参数比较
+        // 其中i和j分别是first_fun和second_fun在api_graph的index
+        let phase_start = Instant::now();
+        let mut compared_num = 0;
+        let mut dependencies = Vec::new();
+        for i in 0..self.api_functions.len() {
+            let output_type = match self._producer_output_type(i, support_generic) {
+                Some(output_type) => output_type,
+                None => continue,
+            };
+            // 对于可能兼容的每个参数，看看first_fun的返回值是否对应得上
+            for (j, k) in
+                consumer_index.candidate_consumers(&output_type, self.cache, &self.full_name_map)
+            {
+                compared_num += 1;
+                dependencies.extend(self._dependency_between(
+                    i,
+                    &output_type,
+                    j,
+                    k,
+                    support_generic,
+                ));
             }
         }
+        self.api_dependencies = dependencies;
+        println!(
+            "find_dependencies: 比较了{}对返回值和参数，用时{:?}",
+            compared_num,
+            phase_start.elapsed()
+        );
+
+        if _is_check_dependencies_mode() {
+            let indexed: FxHashSet<&ApiDependency> = self.api_dependencies.iter().collect();
+            let scanned = self._find_all_dependencies_by_scan(support_generic);
+            let scanned: FxHashSet<&ApiDependency> = scanned.iter().collect();
+            assert!(
+                indexed == scanned,
+                "find_dependencies: 索引找到{}条依赖，两两比较找到{}条，只在索引中的有{:?}，只在两两比较中的有{:?}",
+                indexed.len(),
+                scanned.len(),
+                indexed.difference(&scanned).collect_vec(),
+                scanned.difference(&indexed).collect_vec()
+            );
+            println!("find_dependencies: 和两两比较的结果一致");
+        }
 
         println!(
             "find_dependencies finished! Num of dependencies is {}.",
@@ -420,6 +775,29 @@ pub(crate) fn find_all_dependencies(&mut self, support_generic: bool) {
         );
     }
 
+    /// 原来的做法：每个返回值和每个参数都比较一次，只用来检查索引的结果
+    fn _find_all_dependencies_by_scan(&self, support_generic: bool) -> Vec<ApiDependency> {
+        let mut dependencies = Vec::new();
+        for i in 0..self.api_functions.len() {
+            let output_type = match self._producer_output_type(i, support_generic) {
+                Some(output_type) => output_type,
+                None => continue,
+            };
+            for j in 0..self.api_functions.len() {
+                for k in 0..self.api_functions[j].inputs.len() {
+                    dependencies.extend(self._dependency_between(
+                        i,
+                        &output_type,
+                        j,
+                        k,
+                        support_generic,
+                    ));
+                }
+            }
+        }
+        dependencies
+    }
+
     pub(crate) fn _default_generate_sequences(&mut self, lib_name: &str) {
         //BFS + backward search
         self.generate_all_possoble_sequences(
@@ -510,6 +888,55 @@ pub(crate) fn generate_all_possoble_sequences(
                 self.fudge(lib_name);
             }
         }
+        self.retain_focused_sequences();
+    }
+
+    /// 序列是否包含了要关注的API
+    pub(crate) fn is_focused_sequence(&self, sequence: &ApiSequence) -> bool {
+        match &self.focus_apis {
+            None => true,
+            Some(focus_apis) => sequence.functions.iter().any(|api_call| {
+                focus_apis.contains(&self.api_functions[api_call.func.1].full_name)
+            }),
+        }
+    }
+
+    /// 关注的API，以及能直接或间接为它们产生参数的API，没有关注范围时返回None
+    pub(crate) fn focus_support_functions(&self) -> Option<FxHashSet<usize>> {
+        let focus_apis = self.focus_apis.as_ref()?;
+        let mut support: FxHashSet<usize> = (0..self.api_functions.len())
+            .filter(|index| focus_apis.contains(&self.api_functions[*index].full_name))
+            .collect();
+        let mut stack = support.iter().cloned().collect_vec();
+        while let Some(index) = stack.pop() {
+            for dependency in &self.api_dependencies {
+                if dependency.input_fun.1 == index && support.insert(dependency.output_fun.1) {
+                    stack.push(dependency.output_fun.1);
+                }
+            }
+        }
+        Some(support)
+    }
+
+    /// 遍历时可以加到序列里的函数，有关注范围时只用关注的API和能为它们产生参数的API，
+    /// 这样生成的预算不会花在和关注范围无关的序列上
+    fn _traverse_candidates(&self) -> Vec<usize> {
+        match self.focus_support_functions() {
+            Some(support) => support.into_iter().sorted().collect_vec(),
+            None => (0..self.api_functions.len()).collect_vec(),
+        }
+    }
+
+    /// 去掉不包含要关注的API的序列
+    pub(crate) fn retain_focused_sequences(&mut self) {
+        if self.focus_apis.is_none() {
+            return;
+        }
+        let sequence_num = self.api_sequences.len();
+        let sequences = std::mem::take(&mut self.api_sequences);
+        self.api_sequences =
+            sequences.into_iter().filter(|sequence| self.is_focused_sequence(sequence)).collect();
+        println!("只保留关注的API：{}个序列中保留了{}个", sequence_num, self.api_sequences.len());
     }
 
     pub(crate) fn reset_visited(&mut self) {
@@ -563,7 +990,7 @@ pub(crate) fn bfs(&mut self, max_len: usize, stop_at_end_function: bool, fast_mo
             return;
         }
 
-        let api_function_num = self.api_functions.len();
+        let candidates = self._traverse_candidates();
 
         //无需加入长度为1的，从空序列开始即可，加入一个长度为0的序列作为初始
         let api_sequence = ApiSequence::new();
@@ -585,7 +1012,7 @@ pub(crate) fn bfs(&mut self, max_len: usize, stop_at_end_function: bool, fast_mo
             for sequence in &tmp_sequences {
                 //长度为len的序列，去匹配每一个函数，如果可以加入的话，就生成一个新的序列
                 let api_type = ApiType::BareFunction;
-                for api_func_index in 0..api_function_num {
+                for &api_func_index in &candidates {
                     //bfs fast, 访问过的函数不再访问
                     if fast_mode && self.api_functions_visited[api_func_index] {
                         continue;
@@ -623,7 +1050,7 @@ pub(crate) fn _try_deep_bfs(&mut self, max_sequence_number: usize) {
             return;
         }
 
-        let api_function_num = self.api_functions.len();
+        let candidates = self._traverse_candidates();
 
         //无需加入长度为1的，从空序列开始即可，加入一个长度为0的序列作为初始
         let api_sequence = ApiSequence::new();
@@ -653,7 +1080,7 @@ pub(crate) fn _try_deep_bfs(&mut self, max_sequence_number: usize) {
             for sequence in &tmp_sequences {
                 //长度为len的序列，去匹配每一个函数，如果可以加入的话，就生成一个新的序列
                 let api_type = ApiType::BareFunction;
-                for api_func_index in 0..api_function_num {
+                for &api_func_index in &candidates {
                     if let Some(new_sequence) =
                         self.is_fun_satisfied(&api_type, api_func_index, sequence)
                     {
@@ -704,14 +1131,24 @@ pub(crate) fn random_walk(
         self.api_sequences.push(api_sequence);
 
         //start random work
-        let function_len = self.api_functions.len();
-        let mut rng = thread_rng();
+        let candidates = self._traverse_candidates();
+        if candidates.is_empty() {
+            return;
+        }
 
         let mut seq_num = 0;
+        //关注的API不一定能出现在序列里，尝试次数也要有上限
+        let focused = self.focus_apis.is_some();
+        let mut attempts = 0;
         // max_size是api序列的最大数量
         loop {
+            if focused && attempts >= max_size * MAX_FOCUS_ATTEMPTS_FACTOR {
+                println!("尝试了{}次，只生成了{}个包含关注的API的序列", attempts, seq_num);
+                break;
+            }
+            attempts += 1;
             let current_sequence_len = self.api_sequences.len();
-            let chosen_sequence_index = rng.gen_range(0, current_sequence_len);
+            let chosen_sequence_index = self.gen_range(0, current_sequence_len);
             let chosen_sequence = &self.api_sequences[chosen_sequence_index];
             //如果需要在终止节点处停止
             if stop_at_end_function && self.is_sequence_ended(&chosen_sequence, false) {
@@ -720,7 +1157,7 @@ pub(crate) fn random_walk(
 
             //如果深度没有很深，就继续加
 
-            let chosen_fun_index = rng.gen_range(0, function_len);
+            let chosen_fun_index = candidates[self.gen_range(0, candidates.len())];
             //let chosen_fun = &self.api_functions[chosen_fun_index];
             let fun_type = ApiType::BareFunction;
             if let Some(new_sequence) =
@@ -730,7 +1167,8 @@ pub(crate) fn random_walk(
 
                 self.api_functions_visited[chosen_fun_index] = true;
 
-                if new_sequence.len() >= max_len {
+                //只有包含关注的API的序列才计入预算
+                if new_sequence.len() >= max_len && self.is_focused_sequence(&new_sequence) {
                     //println!("api_functions {}", new_sequence.len());
                     seq_num += 1;
                     if seq_num > max_size {
@@ -829,20 +1267,22 @@ pub(crate) fn fudge(&mut self, lib_name: &str) {
         self.api_sequences.push(api_sequence);
 
         //start random work
-        let function_len = self.api_functions.len();
-        let mut rng = thread_rng();
+        let candidates = self._traverse_candidates();
+        if candidates.is_empty() {
+            return;
+        }
 
         let mut seq_num = 0;
         // max_size是api序列的最大数量
         for _ in 0..10000000 {
             let current_sequence_len = self.api_sequences.len();
-            let chosen_sequence_index = rng.gen_range(0, current_sequence_len);
+            let chosen_sequence_index = self.gen_range(0, current_sequence_len);
             let chosen_sequence = &self.api_sequences[chosen_sequence_index];
             //如果需要在终止节点处停止
 
             //如果深度没有很深，就继续加
 
-            let chosen_fun_index = rng.gen_range(0, function_len);
+            let chosen_fun_index = candidates[self.gen_range(0, candidates.len())];
             if !apis_existing_in_corpus_map
                 .contains_key(&self.api_functions[chosen_fun_index].full_name)
             {
@@ -857,7 +1297,8 @@ pub(crate) fn fudge(&mut self, lib_name: &str) {
 
                 self.api_functions_visited[chosen_fun_index] = true;
 
-                if new_sequence.len() >= max_len {
+                //只有包含关注的API的序列才计入预算
+                if new_sequence.len() >= max_len && self.is_focused_sequence(&new_sequence) {
                     //println!("api_functions {}", new_sequence.len());
                     seq_num += 1;
                     if seq_num > 50 {
@@ -872,132 +1313,15 @@ pub(crate) fn fudge(&mut self, lib_name: &str) {
 
     pub(crate) fn my_method(
         &mut self,
-        lib_name: &str,
+        _lib_name: &str,
         max_num: usize,
         max_len: usize,
         support_generic: bool,
     ) {
-        use std::fs::File;
-        use std::io::{BufRead, BufReader};
-
-        let mut depinfo = FxHashMap::default();
-        let mut orderinfo = FxHashMap::default();
-        let mut funcinfo = FxHashMap::default();
-
         self.reset_visited();
 
-        //依赖信息
-        {
-            let depinfo_file_path =
-                format!("/home/yxz/workspace/fuzz/experiment_root/{}/depinfo.txt", lib_name);
-            match File::open(depinfo_file_path) {
-                Ok(file) => {
-                    let reader = BufReader::new(file);
-                    for line in reader.lines() {
-                        let parts = line
-                            .unwrap()
-                            .split("  |  ")
-                            .into_iter()
-                            .map(|x| x.to_string())
-                            .collect_vec();
-                        let cnt = parts[1].parse::<usize>().unwrap();
-                        let (func1, func2) = parts[2]
-                            .split("   ")
-                            .into_iter()
-                            .map(|x| x.to_string())
-                            .collect_tuple()
-                            .unwrap();
-
-                        //把 (func2, cnt) 存入 func1对应的后续列表中
-
-                        if depinfo.contains_key(&func1) {
-                            let inner_map: &mut FxHashMap<String, usize> =
-                                depinfo.get_mut(&func1).unwrap();
-                            inner_map
-                                .entry(func2.clone())
-                                .and_modify(|value| *value += cnt)
-                                .or_insert(cnt);
-                        } else {
-                            let mut inner_map = FxHashMap::default();
-                            inner_map.insert(func2.clone(), cnt);
-                            depinfo.insert(func1.clone(), inner_map);
-                        }
-                    }
-                }
-                _ => {}
-            }
-        }
-
-        //解析顺序信息
-        {
-            let orderinfo_file_path =
-                format!("/home/yxz/workspace/fuzz/experiment_root/{}/orderinfo.txt", lib_name);
-            match File::open(orderinfo_file_path) {
-                Ok(file) => {
-                    let reader = BufReader::new(file);
-                    for line in reader.lines() {
-                        let parts = line
-                            .unwrap()
-                            .split("  |  ")
-                            .into_iter()
-                            .map(|x| x.to_string())
-                            .collect_vec();
-                        let cnt = parts[1].parse::<usize>().unwrap();
-                        let (func1, func2) = parts[2]
-                            .split("   ")
-                            .into_iter()
-                            .map(|x| x.to_string())
-                            .collect_tuple()
-                            .unwrap();
-
-                        //把 (func2, cnt) 存入 func1对应的后续列表中
-
-                        if orderinfo.contains_key(&func1) {
-                            let inner_map: &mut FxHashMap<String, usize> =
-                                orderinfo.get_mut(&func1).unwrap();
-                            inner_map
-                                .entry(func2.clone())
-                                .and_modify(|value| *value += cnt)
-                                .or_insert(cnt);
-                        } else {
-                            let mut inner_map = FxHashMap::default();
-                            inner_map.insert(func2.clone(), cnt);
-                            orderinfo.insert(func1.clone(), inner_map);
-                        }
-                    }
-                }
-                _ => {}
-            }
-        }
-
-        //解析函数频率信息（暂时没用）
-        {
-            let funcinfo_file_path =
-                format!("/home/yxz/workspace/fuzz/experiment_root/{}/funcinfo.txt", lib_name);
-            match File::open(funcinfo_file_path) {
-                Ok(file) => {
-                    let reader = BufReader::new(file);
-                    for line in reader.lines() {
-                        let parts = line
-                            .unwrap()
-                            .split("  |  ")
-                            .into_iter()
-                            .map(|x| x.to_string())
-                            .collect_vec();
-                        let cnt = parts[1].parse::<usize>().unwrap();
-                        let func = &parts[2].clone();
-
-                        //把 (func, cnt)存入funcindo中
-
-                        funcinfo
-                            .entry(func.clone())
-                            .and_modify(|value| *value += cnt)
-                            .or_insert(cnt);
-                    }
-                }
-                _ => {}
-            }
-        }
+        //依赖信息、顺序信息和函数频率信息（暂时没用），已经按corpus的种类加权
+        let corpus::CorpusInfo { depinfo, orderinfo, funcinfo, .. } = self.corpus_info.clone();
 
         let print = true;
         //打印各种信息
@@ -1058,10 +1382,10 @@ pub(crate) fn my_method(
                                 //println!("Yes, frequence{}", *frequence);
                                 *frequence
                             } else {
-                                0
+                                0.0
                             }
                         } else {
-                            0
+                            0.0
                         };
 
                         //println!("{}, {}", order_pair_occur_freq, *freq);
@@ -1091,7 +1415,19 @@ pub(crate) fn my_method(
             }
         }
 
-        let _start_functions = extract_start_function(self, support_generic);
+        let mut _start_functions = extract_start_function(self, support_generic);
+        //有关注范围的时候，只从能为关注的API产生参数的函数开始
+        let focus_support = self.focus_support_functions();
+        if let Some(focus_support) = &focus_support {
+            let focused_start_functions = _start_functions
+                .iter()
+                .filter(|index| focus_support.contains(index))
+                .cloned()
+                .collect_vec();
+            if !focused_start_functions.is_empty() {
+                _start_functions = focused_start_functions;
+            }
+        }
         println!("这里有{}个start function", _start_functions.len());
         for start in &_start_functions {
             println!(
@@ -1104,11 +1440,19 @@ pub(crate) fn my_method(
 
         let mut start_index_polling = 0;
 
+        //只有包含关注的API的序列才计入max_num
+        let mut attempts = 0;
+
         //FIXME: 在这里编写逻辑
         loop {
             if sequences.len() >= max_num {
                 break;
             }
+            if focus_support.is_some() && attempts >= max_num * MAX_FOCUS_ATTEMPTS_FACTOR {
+                println!("尝试了{}次，只生成了{}个包含关注的API的序列", attempts, sequences.len());
+                break;
+            }
+            attempts += 1;
 
             //初始化当前sequence
             let mut sequence = ApiSequence::new();
@@ -1146,14 +1490,15 @@ pub(crate) fn my_method(
                 if sequence.len() >= max_len {
                     break;
                 }
-                let rand = rand_num(0, sequence.len() + 3);
+                let rand = rand_num(&self.rng, 0, sequence.len() + 3);
                 //println!("rand = {}", rand);
                 if rand == 0 || need_new {
                     //有1/(len+5)的概率接触到new
                     //获得随机start函数在全局的index
                     //println!("选择start");
                     assert!(_start_functions.len() > 0);
-                    let start_idx = _start_functions[rand_num(0, _start_functions.len())];
+                    let start_idx =
+                        _start_functions[rand_num(&self.rng, 0, _start_functions.len())];
                     sequence =
                         match self.is_fun_satisfied(&ApiType::BareFunction, start_idx, &sequence) {
                             Some(seq) => {
@@ -1180,8 +1525,8 @@ pub(crate) fn my_method(
                     }
 
                     //随机找到序列中一个可获得的返回值
-                    let selected_function_index =
-                        available_function_indexs[rand_num(0, available_function_indexs.len())];
+                    let selected_function_index = available_function_indexs
+                        [rand_num(&self.rng, 0, available_function_indexs.len())];
                     /*println!(
                         "长度为{}, 被选择的生产者是{}",
                         available_function_indexs.len(),
@@ -1225,8 +1570,8 @@ pub(crate) fn my_method(
                     let mut succ_index = 0;
 
                     for _ in 0..3 {
-                        //let select_immutable = _select_immutable_or_not(sequence.len(), max_len);
-                        match _random_select(&normalized_weights) {
+                        //let select_immutable = _select_immutable_or_not(&self.rng, sequence.len(), max_len);
+                        match _random_select(&self.rng, &normalized_weights) {
                             Some(i) => {
                                 //先存着
                                 succ_index = i;
@@ -1259,7 +1604,9 @@ pub(crate) fn my_method(
                 }
             }
 
-            sequences.push(sequence);
+            if self.is_focused_sequence(&sequence) {
+                sequences.push(sequence);
+            }
         }
 
         for index in covered_function.iter() {
@@ -1278,12 +1625,12 @@ pub(crate) fn my_method(
         /// 归一化函数
         /// 导致每个差距都在20以内
         #[allow(dead_code)]
-        fn normalize_weights(weights: &Vec<usize>) -> Vec<f32> {
+        fn normalize_weights(weights: &Vec<f64>) -> Vec<f64> {
             if weights.len() == 0 {
                 return Vec::new();
             }
             // 使用对数函数进行调整
-            let mut weights = weights.iter().map(|x| *x as f32).collect_vec();
+            let mut weights = weights.clone();
 
             for weight in weights.iter_mut() {
                 *weight = (*weight + 2.0).ln();
@@ -1304,8 +1651,8 @@ fn normalize_weights(weights: &Vec<usize>) -> Vec<f32> {
         /// 构造函数后继表
         fn construct_function_succ_table(
             graph: &mut ApiGraph<'_>,
-            depinfo: &FxHashMap<String, FxHashMap<String, usize>>,
-        ) -> FxHashMap<usize, Vec<(usize, usize, ApiDependency)>> {
+            depinfo: &FxHashMap<String, FxHashMap<String, f64>>,
+        ) -> FxHashMap<usize, Vec<(usize, f64, ApiDependency)>> {
             let mut function_succ_tables = FxHashMap::default();
             //对每个API初始化它们的表
             for i in 0..graph.api_functions.len() {
@@ -1330,10 +1677,10 @@ fn construct_function_succ_table(
                         let freq = inner_map.get(input_func_name).unwrap();
                         output_func_table.push((input_func_index, *freq, api_dependency.clone()));
                     } else {
-                        output_func_table.push((input_func_index, 0, api_dependency.clone()));
+                        output_func_table.push((input_func_index, 0.0, api_dependency.clone()));
                     }
                 } else {
-                    output_func_table.push((input_func_index, 0, api_dependency.clone()));
+                    output_func_table.push((input_func_index, 0.0, api_dependency.clone()));
                 }
             }
             /*
@@ -1372,12 +1719,11 @@ fn extract_start_function(api_graph: &ApiGraph<'_>, support_generic: bool) -> Ve
         }
 
         /// [min, max)
-        fn rand_num(min: usize, max: usize) -> usize {
+        fn rand_num(rng: &RefCell<StdRng>, min: usize, max: usize) -> usize {
             if min >= max {
                 return 0;
             }
-            let mut rng = rand::thread_rng();
-            let random_number = rng.gen_range(min, max);
+            let random_number = rng.borrow_mut().gen_range(min, max);
 
             //println!("随机数: {}", random_number);
             random_number
@@ -1387,7 +1733,7 @@ fn _get_succ_indexs(
             //api_graph: &ApiGraph<'_>,
             sequence: &ApiSequence,
             indexs_in_sequence: &Vec<usize>,
-            _function_succ_tables_map: &FxHashMap<usize, Vec<(usize, usize, ApiDependency)>>,
+            _function_succ_tables_map: &FxHashMap<usize, Vec<(usize, f64, ApiDependency)>>,
         ) -> Vec<usize> {
             let mut res = FxHashSet::default();
             let mut filtered_indexs = Vec::new();
@@ -1410,7 +1756,7 @@ fn _get_succ_indexs(
         fn _get_available_function_indexs(
             _api_graph: &ApiGraph<'_>,
             sequence: &ApiSequence,
-            succ_tables: &FxHashMap<usize, Vec<(usize, f32)>>,
+            succ_tables: &FxHashMap<usize, Vec<(usize, f64)>>,
         ) -> Vec<usize> {
             let mut res = Vec::new();
             //println!();
@@ -1435,17 +1781,18 @@ fn _get_available_function_indexs(
             return res;
         }
 
-        fn _random_select(probabilities: &Vec<f32>) -> Option<usize> {
+        fn _random_select(rng: &RefCell<StdRng>, probabilities: &Vec<f64>) -> Option<usize> {
             use rand::prelude::SliceRandom;
-            let mut rng = thread_rng();
             let weighted_indices: Vec<usize> = (0..probabilities.len()).collect();
-            let dist = weighted_indices.choose_weighted(&mut rng, |&i| probabilities[i]).ok()?;
+            let dist = weighted_indices
+                .choose_weighted(&mut *rng.borrow_mut(), |&i| probabilities[i])
+                .ok()?;
             Some(*dist)
         }
-        fn _select_immutable_or_not(i: usize, max_len: usize) -> bool {
+        fn _select_immutable_or_not(rng: &RefCell<StdRng>, i: usize, max_len: usize) -> bool {
             //选择不可变引用，随着1/4概率增大到5/8
             let p_of_immutable_ref = ((max_len / 6 + i) as f32) / ((i + max_len) as f32);
-            let rand_from_0_to_99 = rand_num(0, 100);
+            let rand_from_0_to_99 = rand_num(rng, 0, 100);
 
             rand_from_0_to_99 < (p_of_immutable_ref as usize) * 100
         }
@@ -1549,6 +1896,10 @@ pub(crate) fn _try_to_cover_unvisited_nodes(&mut self) {
                         .map(|index| self.api_sequences[index].clone())
                         .collect();
                     let merged_sequence = ApiSequence::_merge_sequences(&dependent_sequences);
+                    if let Err(reason) = borrow_check::check_sequence(self, &merged_sequence) {
+                        println!("合并之后的序列没有通过借用检查: {}", reason);
+                        continue;
+                    }
                     let input_type = ApiType::BareFunction;
                     if let Some(generated_sequence) =
                         self.is_fun_satisfied(&input_type, *unvisited_node, &merged_sequence)
@@ -1643,7 +1994,7 @@ pub(crate) fn _naive_choose_sequence(&self, max_sequence_size: usize) -> Vec<Api
             }
         }
 
-        let mut rng = thread_rng();
+        let mut rng = self.rng.borrow_mut();
         for _ in 0..max_sequence_size {
             if to_cover_nodes.len() == 0 {
                 println!("all {} nodes need to be covered is covered", to_cover_nodes_number);
@@ -1685,7 +2036,7 @@ pub(crate) fn _random_choose(&self, max_size: usize) -> Vec<ApiSequence> {
             sequence_indexes.push(i);
         }
 
-        let mut rng = thread_rng();
+        let mut rng = self.rng.borrow_mut();
         for _ in 0..max_size {
             let rest_sequences_number = sequence_indexes.len();
             if rest_sequences_number <= 0 {
@@ -2169,7 +2520,8 @@ pub(crate) fn is_fun_satisfied(
                             ) {
                                 // 理论上这里泛型依赖也会出现
 
-                                let dependency_ = self.api_dependencies[dependency_index].clone();
+                                let mut dependency_ =
+                                    self.api_dependencies[dependency_index].clone();
                                 //将覆盖到的边加入到新的sequence中去
                                 new_sequence._add_dependency(dependency_index);
                                 //找到了依赖，当前参数是可以被满足的，设置flag并退出循环
@@ -2189,8 +2541,29 @@ pub(crate) fn is_fun_satisfied(
                                     )
                                 );*/
 
+                                //如果会发生move，但返回值可以clone，就先clone一份再传进去
+                                //原来的值不会被move，后面的调用还可以继续使用
+                                if api_util::_move_condition(current_ty, &dependency_.call_type)
+                                    && self.can_clone_local(index)
+                                    && dependency_
+                                        .call_type
+                                        ._insert_clone_call_type()
+                                        ._contains_clone_call_type()
+                                {
+                                    if _multi_mut.contains(&function_index) {
+                                        dependency_flag = false;
+                                        continue;
+                                    }
+                                    dependency_.call_type =
+                                        dependency_.call_type._insert_clone_call_type();
+                                    new_sequence._insert_clone(new_sequence.len(), i);
+                                    _immutable_borrow.insert(function_index);
+                                }
                                 //如果满足move发生的条件
-                                if api_util::_move_condition(current_ty, &dependency_.call_type) {
+                                else if api_util::_move_condition(
+                                    current_ty,
+                                    &dependency_.call_type,
+                                ) {
                                     /*println!(
                                         "！！！！！！！！！！！！！！！！！！！！移动，{}, {}",
                                         api_util::_type_name(
@@ -2367,6 +2740,11 @@ pub(crate) fn is_fun_satisfied(
                     //如果新生成的序列包含多维可变的参数，就不把这个序列加进去
                     return None;
                 }
+                //最后用借用检查器再确认一遍，上面的规则会漏掉借用跨越多个调用的情况
+                if let Err(_reason) = borrow_check::check_sequence(self, &new_sequence) {
+                    //println!("借用检查没有通过: {}", _reason);
+                    return None;
+                }
                 return Some(new_sequence);
             }
             ApiType::GenericFunction => None,
@@ -2761,6 +3139,95 @@ pub(crate) fn check_dependency(
         return None;
     }
 
+    /// 最小化序列：删掉返回值没有被使用、并且同一个函数在后面还会再出现的调用
+    /// 每删一个都要重新过一遍借用检查，没通过就保留原来的
+    pub(crate) fn _minimize_sequence(&self, api_sequence: &ApiSequence) -> ApiSequence {
+        let mut res = self._remove_redundant_clones(api_sequence);
+        let mut index = 0;
+        while index + 1 < res.len() {
+            let dead_code = res._dead_code(self);
+            let func_index = res.functions[index].func.1;
+            let duplicated =
+                res.functions[index + 1..].iter().any(|api_call| api_call.func.1 == func_index);
+            if dead_code[index] && duplicated {
+                let minimized = res._remove_api_call(index);
+                if borrow_check::check_sequence(self, &minimized).is_ok() {
+                    res = minimized;
+                    continue;
+                }
+            }
+            index = index + 1;
+        }
+        res
+    }
+
+    /// 去掉不必要的clone：从后往前，把clone换成move之后仍然能通过借用检查，就不需要这个clone
+    pub(crate) fn _remove_redundant_clones(&self, api_sequence: &ApiSequence) -> ApiSequence {
+        let mut res = api_sequence.clone();
+        let mut cloned = api_sequence._cloned.iter().cloned().collect_vec();
+        cloned.sort();
+        for (call_index, param_index) in cloned.into_iter().rev() {
+            let without_clone = res._remove_clone(call_index, param_index);
+            if borrow_check::check_sequence(self, &without_clone).is_ok() {
+                res = without_clone;
+            }
+        }
+        res
+    }
+
+    /// 对序列中返回迭代器的调用，在末尾追加驱动函数，步数由一个u8的输入决定
+    /// 追加之后借用检查没有通过的就不追加
+    pub(crate) fn _append_iterator_steps(&self, api_sequence: &ApiSequence) -> ApiSequence {
+        let mut res = api_sequence.clone();
+        for call_index in 0..api_sequence.len() {
+            let func_index = api_sequence.functions[call_index].func.1;
+            let dependency = self.api_dependencies.iter().find(|dependency| {
+                dependency.output_fun.1 == func_index
+                    && dependency.input_param_index == 0
+                    && iterator::is_drive_function(
+                        &self.api_functions[dependency.input_fun.1].full_name,
+                    )
+            });
+            let dependency = match dependency {
+                Some(dependency) => dependency,
+                None => continue,
+            };
+            let drive_index = dependency.input_fun.1;
+            let drive_function = &self.api_functions[drive_index];
+
+            let mut new_sequence = res.clone();
+            let mut api_call = ApiCall::_new(drive_index);
+            api_call._add_param(
+                ParamType::_FunctionReturn,
+                call_index,
+                dependency.call_type.clone(),
+            );
+            let fuzzable_index = new_sequence.fuzzable_params.len();
+            new_sequence.fuzzable_params.push(FuzzableType::Primitive(clean::PrimitiveType::U8));
+            api_call._add_param(ParamType::_FuzzableType, fuzzable_index, CallType::_DirectCall);
+            if api_util::_need_mut_tag(&dependency.call_type) {
+                new_sequence._insert_function_mut_tag(call_index);
+            }
+            new_sequence._add_fn(api_call);
+
+            match borrow_check::check_sequence(self, &new_sequence) {
+                Ok(()) => res = new_sequence,
+                Err(reason) => println!("没有追加{}: {}", drive_function.full_name, reason),
+            }
+        }
+        res
+    }
+
+    /// mock waker模式下，给包含async调用的序列加一个u8的输入，决定最多poll几次
+    pub(crate) fn _append_poll_budget(&self, api_sequence: &ApiSequence) -> ApiSequence {
+        let mut res = api_sequence.clone();
+        if res._poll_budget.is_none() && async_util::is_mock_waker_mode() && res._is_async(self) {
+            res._poll_budget = Some(res.fuzzable_params.len());
+            res.fuzzable_params.push(FuzzableType::Primitive(clean::PrimitiveType::U8));
+        }
+        res
+    }
+
     //判断一个调用序列是否已经到达终止端点
     fn is_sequence_ended(&self, api_sequence: &ApiSequence, support_generic: bool) -> bool {
         let functions = &api_sequence.functions;
@@ -2788,3 +3255,96 @@ fn is_sequence_ended(&self, api_sequence: &ApiSequence, support_generic: bool) -
         }
     }
 }
+
+/// corpus中提取出来的信息文件的路径
+fn _is_check_dependencies_mode() -> bool {
+    match std::env::var(CHECK_DEPENDENCIES_ENV) {
+        Ok(value) => value != "0" && !value.is_empty(),
+        Err(_) => false,
+    }
+}
+
+pub(crate) fn _corpus_info_path(lib_name: &str, file_name: &str) -> String {
+    format!("/home/yxz/workspace/fuzz/experiment_root/{}/{}", lib_name, file_name)
+}
+
+/// 读取depinfo.txt或者orderinfo.txt，func1 -> (func2 -> 次数)，文件不存在时返回空表
+pub(crate) fn _read_pair_info(path: &str) -> FxHashMap<String, FxHashMap<String, usize>> {
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+
+    let mut pair_info: FxHashMap<String, FxHashMap<String, usize>> = FxHashMap::default();
+    if let Ok(file) = File::open(path) {
+        let reader = BufReader::new(file);
+        for line in reader.lines() {
+            let parts =
+                line.unwrap().split("  |  ").into_iter().map(|x| x.to_string()).collect_vec();
+            let cnt = parts[1].parse::<usize>().unwrap();
+            let (func1, func2) =
+                parts[2].split("   ").into_iter().map(|x| x.to_string()).collect_tuple().unwrap();
+
+            //把 (func2, cnt) 存入 func1对应的后续列表中
+            pair_info
+                .entry(func1)
+                .or_default()
+                .entry(func2)
+                .and_modify(|value| *value += cnt)
+                .or_insert(cnt);
+        }
+    }
+    pair_info
+}
+
+/// 读取funcinfo.txt，func -> 次数，文件不存在时返回空表
+pub(crate) fn _read_funcinfo(path: &str) -> FxHashMap<String, usize> {
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+
+    let mut funcinfo = FxHashMap::default();
+    if let Ok(file) = File::open(path) {
+        let reader = BufReader::new(file);
+        for line in reader.lines() {
+            let parts =
+                line.unwrap().split("  |  ").into_iter().map(|x| x.to_string()).collect_vec();
+            let cnt = parts[1].parse::<usize>().unwrap();
+
+            //把 (func, cnt)存入funcinfo中
+            funcinfo.entry(parts[2].clone()).and_modify(|value| *value += cnt).or_insert(cnt);
+        }
+    }
+    funcinfo
+}
+
+/// 依次从`FRIES_SEED`和`FRIES_CONFIG`中的`seed`读取随机数种子，都没有设置的时候随机选一个
+fn _generation_seed() -> u64 {
+    let seed = match std::env::var(SEED_ENV) {
+        Ok(seed) => Some(seed),
+        Err(_) => _config_value("seed"),
+    };
+    match seed {
+        Some(seed) => match seed.parse::<u64>() {
+            Ok(seed) => seed,
+            Err(_) => {
+                println!("{}不是合法的随机数种子，随机选一个", seed);
+                rand::random::<u64>()
+            }
+        },
+        None => rand::random::<u64>(),
+    }
+}
+
+/// 读取配置文件中的一项，没有配置文件或者没有这一项时返回None
+fn _config_value(key: &str) -> Option<String> {
+    let path = std::env::var(CONFIG_ENV).ok()?;
+    let content = match std::fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(e) => {
+            println!("无法读取配置文件{}: {}", path, e);
+            return None;
+        }
+    };
+    content.lines().find_map(|line| {
+        let (name, value) = line.split_once('=')?;
+        if name.trim() == key { Some(value.trim().to_string()) } else { None }
+    })
+}
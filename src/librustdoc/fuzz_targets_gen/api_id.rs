@@ -0,0 +1,144 @@
+//! 在corpus模式和库模式之间识别同一个API
+//! corpus模式的名字来自`tcx.def_path_str`，比如`<url::Url as std::str::FromStr>::from_str`，也可能是重导出的路径；
+//! 库模式的名字来自rustdoc的FullNameMap，两边按字符串比较经常对不上
+//! ApiId由两部分组成：
+//! 1. DefPathHash：由定义的位置决定，和重导出、打印方式无关。其中包含待测库的StableCrateId，
+//!    两边用相同的`-C metadata`编译待测库时才相等
+//! 2. 规范化的路径：去掉泛型参数，`<T as Trait>::f`写成`T::f`。DefPathHash对不上，
+//!    或者没有DefPathHash（json前端、旧的文本文件、合成的API）时用它
+
+use crate::fuzz_targets_gen::api_graph::ApiGraph;
+use rustc_data_structures::fx::{FxHashMap, FxHashSet};
+use rustc_hir::def_id::DefId;
+use rustc_middle::ty::TyCtxt;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+pub(crate) struct ApiId {
+    /// 十六进制的DefPathHash
+    pub(crate) def_path_hash: Option<String>,
+    pub(crate) path: String,
+}
+
+impl ApiId {
+    pub(crate) fn from_def_id(tcx: TyCtxt<'_>, def_id: DefId) -> Self {
+        ApiId {
+            def_path_hash: Some(tcx.def_path_hash(def_id).0.to_hex()),
+            path: normalize_path(&tcx.def_path_str(def_id)),
+        }
+    }
+
+    pub(crate) fn from_path(path: &str) -> Self {
+        ApiId { def_path_hash: None, path: normalize_path(path) }
+    }
+}
+
+/// 找到和`start`处的`<`配对的`>`，`->`里的`>`不算
+fn _matching_angle(chars: &[char], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    for index in start..chars.len() {
+        match chars[index] {
+            '<' => depth += 1,
+            '>' if index > 0 && chars[index - 1] == '-' => {}
+            '>' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 去掉所有尖括号里的泛型参数，包括前面的`::`
+fn _strip_generic_args(path: &str) -> String {
+    let chars: Vec<char> = path.chars().collect();
+    let mut res = String::new();
+    let mut index = 0;
+    while index < chars.len() {
+        if chars[index] == '<' {
+            if res.ends_with("::") {
+                res.truncate(res.len() - 2);
+            }
+            match _matching_angle(&chars, index) {
+                Some(end) => index = end + 1,
+                None => break,
+            }
+        } else {
+            res.push(chars[index]);
+            index += 1;
+        }
+    }
+    res
+}
+
+/// 规范化的路径：`<&mut url::Url as std::str::FromStr>::from_str::<T>` -> `url::Url::from_str`
+pub(crate) fn normalize_path(path: &str) -> String {
+    let path = path.trim();
+    let chars: Vec<char> = path.chars().collect();
+    if chars.first() == Some(&'<') {
+        if let Some(end) = _matching_angle(&chars, 0) {
+            let qualified: String = chars[1..end].iter().collect();
+            let rest: String = chars[end + 1..].iter().collect();
+            //`<T as Trait>`只保留T，`<T>`本身就是T
+            let self_ty = match qualified.find(" as ") {
+                Some(pos) => qualified[..pos].to_string(),
+                None => qualified,
+            };
+            let self_ty = self_ty.trim_start_matches('&').trim_start_matches("mut ").trim();
+            return _strip_generic_args(&format!("{}{}", normalize_path(self_ty), rest));
+        }
+    }
+    _strip_generic_args(path)
+}
+
+/// 把corpus中的名字对应到库模式的full_name
+pub(crate) struct ApiResolver {
+    by_hash: FxHashMap<String, String>,
+    by_name: FxHashSet<String>,
+    by_path: FxHashMap<String, String>,
+}
+
+impl ApiResolver {
+    /// 被过滤掉的API也会被识别出来，只是生成时用不到，这样报告里只剩真正对不上的名字
+    pub(crate) fn new(api_graph: &ApiGraph<'_>) -> Self {
+        let mut by_hash = FxHashMap::default();
+        let mut by_path = FxHashMap::default();
+        for (full_name, api_id) in &api_graph.api_ids {
+            if let Some(hash) = &api_id.def_path_hash {
+                by_hash.insert(hash.clone(), full_name.clone());
+            }
+            by_path.entry(api_id.path.clone()).or_insert(full_name.clone());
+        }
+        let by_name: FxHashSet<String> = api_graph
+            .api_functions
+            .iter()
+            .map(|api_fun| api_fun.full_name.clone())
+            .chain(api_graph.excluded_functions.keys().cloned())
+            .chain(api_graph.api_ids.keys().cloned())
+            .collect();
+        for full_name in &by_name {
+            by_path.entry(normalize_path(full_name)).or_insert(full_name.clone());
+        }
+        ApiResolver { by_hash, by_name, by_path }
+    }
+
+    /// 依次按DefPathHash、原来的名字、规范化的路径查找
+    pub(crate) fn resolve(&self, name: &str, api_id: Option<&ApiId>) -> Option<String> {
+        if let Some(hash) = api_id.and_then(|api_id| api_id.def_path_hash.as_ref()) {
+            if let Some(full_name) = self.by_hash.get(hash) {
+                return Some(full_name.clone());
+            }
+        }
+        if self.by_name.contains(name) {
+            return Some(name.to_string());
+        }
+        let path = match api_id {
+            Some(api_id) => api_id.path.clone(),
+            None => normalize_path(name),
+        };
+        self.by_path.get(&path).cloned()
+    }
+}
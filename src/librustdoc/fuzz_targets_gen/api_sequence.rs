@@ -1,10 +1,19 @@
+use crate::clean;
 use crate::fuzz_targets_gen::afl_util::{self, _AflHelpers};
 use crate::fuzz_targets_gen::api_graph::{ApiGraph, ApiType};
 use crate::fuzz_targets_gen::api_util;
+use crate::fuzz_targets_gen::async_util;
+use crate::fuzz_targets_gen::borrow_check;
 use crate::fuzz_targets_gen::call_type::CallType;
+use crate::fuzz_targets_gen::differential;
+use crate::fuzz_targets_gen::error_path;
 use crate::fuzz_targets_gen::fuzz_type::FuzzableType;
+use crate::fuzz_targets_gen::inverse_pair::{self, InversePair};
+use crate::fuzz_targets_gen::iterator;
+use crate::fuzz_targets_gen::oracle::{self, TraitLaws};
 use crate::fuzz_targets_gen::prelude_type;
 use crate::fuzz_targets_gen::replay_util;
+use itertools::Itertools;
 use rustc_data_structures::fx::{FxHashMap, FxHashSet};
 
 use super::prelude_type::PreludeType;
@@ -240,6 +249,9 @@ pub(crate) struct ApiSequence {
     pub(crate) _covered_dependencies: FxHashSet<usize>, //表示用到了哪些dependency,即边覆盖率
 
     pub(crate) careful_pairs: FxHashMap<usize, Vec<usize>>,
+    pub(crate) _cloned: FxHashSet<(usize, usize)>, //表示哪些(调用，参数)插入了clone，最小化的时候可以去掉
+    pub(crate) _poll_budget: Option<usize>,        //mock waker模式下决定最多poll几次的fuzzable变量
+    pub(crate) _inverse_pair: Option<InversePair>, //需要检查B(A(x)) == x的一对调用
 }
 
 impl ApiSequence {
@@ -255,6 +267,9 @@ pub(crate) fn new() -> Self {
         let _function_mut_tag = FxHashSet::default();
         let _covered_dependencies = FxHashSet::default();
         let careful_pairs = FxHashMap::default();
+        let _cloned = FxHashSet::default();
+        let _poll_budget = None;
+        let _inverse_pair = None;
         ApiSequence {
             functions,
             fuzzable_params,
@@ -267,6 +282,9 @@ pub(crate) fn new() -> Self {
             _function_mut_tag,
             _covered_dependencies,
             careful_pairs,
+            _cloned,
+            _poll_budget,
+            _inverse_pair,
         }
     }
 
@@ -295,6 +313,13 @@ pub(crate) fn len(&self) -> usize {
         self.functions.len()
     }
 
+    /// 序列中是否有async函数的调用
+    pub(crate) fn _is_async(&self, _api_graph: &ApiGraph<'_>) -> bool {
+        self.functions
+            .iter()
+            .any(|api_call| _api_graph.api_functions[api_call.func.1]._async_tag._is_async())
+    }
+
     pub(crate) fn _has_no_fuzzables(&self) -> bool {
         if self.fuzzable_params.len() <= 0 {
             return true;
@@ -354,6 +379,10 @@ pub(crate) fn _merge_another_sequence(&self, other: &ApiSequence) -> Self {
         for function_mut_tag in other_sequence._function_mut_tag {
             res._function_mut_tag.insert(function_mut_tag + first_func_number);
         }
+        //clone
+        for (call_index, param_index) in other_sequence._cloned {
+            res._cloned.insert((call_index + first_func_number, param_index));
+        }
         res
     }
 
@@ -371,6 +400,101 @@ pub(crate) fn _merge_sequences(sequences: &Vec<ApiSequence>) -> Self {
         basic_sequence
     }
 
+    /// 删除序列中第index个调用，要求它的返回值没有被后面的调用使用
+    /// 后面调用的下标、fuzzable参数的下标以及各种标记都要跟着调整
+    pub(crate) fn _remove_api_call(&self, index: usize) -> Self {
+        let removed_call = &self.functions[index];
+        //被删除的调用所用到的fuzzable参数
+        let removed_fuzzables: FxHashSet<usize> = removed_call
+            .params
+            .iter()
+            .filter(|(param_type, ..)| *param_type == ParamType::_FuzzableType)
+            .map(|(_, fuzzable_index, _)| *fuzzable_index)
+            .collect();
+        let shift_function = |x: usize| if x > index { x - 1 } else { x };
+        let shift_fuzzable =
+            |x: usize| x - removed_fuzzables.iter().filter(|removed| **removed < x).count();
+
+        let mut res = self.clone();
+        res.functions.clear();
+        for (i, api_call) in self.functions.iter().enumerate() {
+            if i == index {
+                continue;
+            }
+            let mut new_call = ApiCall { func: api_call.func.clone(), params: Vec::new() };
+            for (param_type, param_index, call_type) in &api_call.params {
+                let new_index = match param_type {
+                    ParamType::_FunctionReturn => shift_function(*param_index),
+                    ParamType::_FuzzableType => shift_fuzzable(*param_index),
+                };
+                new_call._add_param(param_type.clone(), new_index, call_type.clone());
+            }
+            res.functions.push(new_call);
+        }
+
+        res.fuzzable_params = self
+            .fuzzable_params
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !removed_fuzzables.contains(i))
+            .map(|(_, fuzzable)| fuzzable.clone())
+            .collect();
+        res._fuzzable_mut_tag = self
+            ._fuzzable_mut_tag
+            .iter()
+            .filter(|x| !removed_fuzzables.contains(*x))
+            .map(|x| shift_fuzzable(*x))
+            .collect();
+        res._moved =
+            self._moved.iter().filter(|x| **x != index).map(|x| shift_function(*x)).collect();
+        res._mut_borrow =
+            self._mut_borrow.iter().filter(|x| **x != index).map(|x| shift_function(*x)).collect();
+        res._borrow =
+            self._borrow.iter().filter(|x| **x != index).map(|x| shift_function(*x)).collect();
+        res._function_mut_tag = self
+            ._function_mut_tag
+            .iter()
+            .filter(|x| **x != index)
+            .map(|x| shift_function(*x))
+            .collect();
+        res.careful_pairs = self
+            .careful_pairs
+            .iter()
+            .filter(|(x, _)| **x != index)
+            .map(|(x, pairs)| {
+                let pairs = pairs
+                    .iter()
+                    .filter(|y| **y != index)
+                    .map(|y| shift_function(*y))
+                    .collect::<Vec<usize>>();
+                (shift_function(*x), pairs)
+            })
+            .collect();
+        res._cloned = self
+            ._cloned
+            .iter()
+            .filter(|(call_index, _)| *call_index != index)
+            .map(|(call_index, param_index)| (shift_function(*call_index), *param_index))
+            .collect();
+        res._poll_budget =
+            self._poll_budget.filter(|x| !removed_fuzzables.contains(x)).map(|x| shift_fuzzable(x));
+        res._inverse_pair = self
+            ._inverse_pair
+            .as_ref()
+            .filter(|pair| {
+                pair.forward_call != index
+                    && pair.inverse_call != index
+                    && !removed_fuzzables.contains(&pair.input)
+            })
+            .map(|pair| InversePair {
+                input: shift_fuzzable(pair.input),
+                forward_call: shift_function(pair.forward_call),
+                inverse_call: shift_function(pair.inverse_call),
+                ..pair.clone()
+            });
+        res
+    }
+
     pub(crate) fn _contains_api_function(&self, index: usize) -> bool {
         for api_call in &self.functions {
             let (_, func_index) = api_call.func;
@@ -416,6 +540,21 @@ pub(crate) fn _insert_function_mut_tag(&mut self, index: usize) {
         self._function_mut_tag.insert(index);
     }
 
+    pub(crate) fn _insert_clone(&mut self, call_index: usize, param_index: usize) {
+        self._cloned.insert((call_index, param_index));
+    }
+
+    /// 去掉第call_index个调用的第param_index个参数上插入的clone，原来的值会被move
+    pub(crate) fn _remove_clone(&self, call_index: usize, param_index: usize) -> Self {
+        let mut res = self.clone();
+        let (param_type, index, call_type) = &self.functions[call_index].params[param_index];
+        res.functions[call_index].params[param_index] =
+            (param_type.clone(), *index, call_type._remove_clone_call_type());
+        res._cloned.remove(&(call_index, param_index));
+        res._moved.insert(*index);
+        res
+    }
+
     pub(crate) fn _is_function_need_mut_tag(&self, index: usize) -> bool {
         if self._function_mut_tag.contains(&index) { true } else { false }
     }
@@ -547,7 +686,8 @@ pub(crate) fn _to_replay_crash_file(
         res = res.replace("#[macro_use]\nextern crate afl;\n", "");
         res.push_str(replay_util::_read_crash_file_data());
         res.push('\n');
-        res.push_str(self._reproduce_main_function(test_index).as_str());
+        let record_input = self._oracle_laws(_api_graph).iter().any(|laws| laws.is_some());
+        res.push_str(self._reproduce_main_function(test_index, record_input).as_str());
         res
     }
 
@@ -598,6 +738,17 @@ pub(crate) fn _to_afl_except_main(
         res.push_str("#[macro_use]\n");
         res.push_str("extern crate afl;\n");
         res.push_str(format!("extern crate {};\n", _api_graph._crate_name).as_str());
+        //serde的往返检查用serde_json
+        if _api_graph._crate_name != "serde_json"
+            && self._oracle_laws(_api_graph).iter().flatten().any(|laws| laws.serde)
+        {
+            res.push_str("extern crate serde_json;\n");
+        }
+        let differential_mode = differential::is_differential_mode();
+        if differential_mode {
+            let other_crate = differential::other_version_crate_name(&_api_graph._crate_name);
+            res.push_str(format!("extern crate {};\n", other_crate).as_str());
+        }
 
         let prelude_helper_functions = self._prelude_helper_functions();
         if let Some(prelude_functions) = prelude_helper_functions {
@@ -608,11 +759,119 @@ pub(crate) fn _to_afl_except_main(
         if let Some(afl_functions) = afl_helper_functions {
             res.push_str(afl_functions.as_str());
         }
+        let constructor_macros = self._constructor_macros(_api_graph);
+        if let Some(constructor_macros) = constructor_macros {
+            res.push_str(constructor_macros.as_str());
+        }
+        let iterator_helper_functions = self._iterator_helper_functions(_api_graph);
+        if let Some(iterator_functions) = iterator_helper_functions {
+            res.push_str(iterator_functions.as_str());
+        }
+        if self._is_async(_api_graph) {
+            res.push_str(async_util::_executor_function(self._poll_budget.is_some()));
+            res.push('\n');
+        }
+        if self._oracle_laws(_api_graph).iter().any(|laws| laws.is_some()) {
+            let sequence = self.print_sequence(_api_graph, false);
+            res.push_str(oracle::_oracle_helper_functions(&sequence).as_str());
+            res.push('\n');
+        }
+        if self._inverse_pair.is_some() {
+            res.push_str(inverse_pair::_inverse_helper_function().as_str());
+            res.push('\n');
+        }
+        if differential_mode {
+            res.push_str(differential::_differential_helper_functions().as_str());
+            res.push('\n');
+        }
         res.push_str(self._to_well_written_function(_api_graph, test_index, 0).as_str());
         res.push('\n');
+        if differential_mode {
+            res.push_str(self._other_version_mod(_api_graph, test_index).as_str());
+            res.push('\n');
+        }
+        res
+    }
+
+    /// 差分测试时，在单独的mod里用另一个版本生成同样的测试函数
+    /// 构造宏和迭代器的辅助函数也要换成另一个版本的，其他辅助函数从外面引入
+    pub(crate) fn _other_version_mod(
+        &self,
+        _api_graph: &ApiGraph<'_>,
+        test_index: usize,
+    ) -> String {
+        let mut code = String::new();
+        if let Some(constructor_macros) = self._constructor_macros(_api_graph) {
+            code.push_str(constructor_macros.as_str());
+        }
+        if let Some(iterator_functions) = self._iterator_helper_functions(_api_graph) {
+            code.push_str(iterator_functions.as_str());
+        }
+        code.push_str(
+            self._to_well_written_function(_api_graph, test_index, 0)
+                .replace("fn test_function", "pub(super) fn test_function")
+                .as_str(),
+        );
+        let code = differential::_to_other_version(&code, &_api_graph._crate_name);
+
+        let indent = _generate_indent(4);
+        let mut res =
+            format!("mod {} {{\n{}use super::*;\n", differential::OTHER_VERSION_MOD, indent);
+        for line in code.lines() {
+            if !line.is_empty() {
+                res.push_str(indent.as_str());
+            }
+            res.push_str(line);
+            res.push('\n');
+        }
+        res.push_str("}\n");
         res
     }
 
+    /// 序列中用到的合成构造函数对应的宏定义
+    pub(crate) fn _constructor_macros(&self, _api_graph: &ApiGraph<'_>) -> Option<String> {
+        let mut used_constructors = Vec::new();
+        for api_call in &self.functions {
+            let full_name = &_api_graph.api_functions[api_call.func.1].full_name;
+            if !used_constructors.contains(full_name)
+                && _api_graph.constructors.contains_key(full_name)
+            {
+                used_constructors.push(full_name.clone());
+            }
+        }
+        if used_constructors.len() == 0 {
+            return None;
+        }
+        let mut res = String::new();
+        for full_name in &used_constructors {
+            res.push_str(_api_graph.constructors[full_name].to_macro_definition().as_str());
+            res.push('\n');
+        }
+        Some(res)
+    }
+
+    /// 序列中用到的迭代器辅助函数的定义
+    pub(crate) fn _iterator_helper_functions(&self, _api_graph: &ApiGraph<'_>) -> Option<String> {
+        let mut helper_codes = Vec::new();
+        for api_call in &self.functions {
+            let full_name = &_api_graph.api_functions[api_call.func.1].full_name;
+            if let Some(code) = iterator::_helper_function_code(full_name) {
+                if !helper_codes.contains(&code) {
+                    helper_codes.push(code);
+                }
+            }
+        }
+        if helper_codes.len() == 0 {
+            return None;
+        }
+        let mut res = String::new();
+        for code in helper_codes {
+            res.push_str(code);
+            res.push('\n');
+        }
+        Some(res)
+    }
+
     pub(crate) fn _prelude_helper_functions(&self) -> Option<String> {
         let mut prelude_helpers = FxHashSet::default();
         for api_call in &self.functions {
@@ -628,7 +887,8 @@ pub(crate) fn _prelude_helper_functions(&self) -> Option<String> {
             return None;
         }
         let mut res = String::new();
-        for helper in prelude_helpers {
+        //按固定的顺序输出，保证同样的种子生成的文件完全相同
+        for helper in prelude_helpers.into_iter().sorted() {
             res.push_str(helper._to_helper_function());
             res.push('\n');
         }
@@ -663,15 +923,16 @@ pub(crate) fn _afl_main_function(&self, test_index: usize) -> String {
         res
     }
 
-    pub(crate) fn _reproduce_main_function(&self, test_index: usize) -> String {
+    pub(crate) fn _reproduce_main_function(&self, test_index: usize, record_input: bool) -> String {
         format!(
             "fn main() {{
     let _content = _read_data();
     let data = &_content;
     println!(\"data = {{:?}}\", data);
     println!(\"data len = {{:?}}\", data.len());
-{}
+{}{}
 }}",
+            if record_input { replay_util::_record_oracle_input() } else { "" },
             self._afl_closure_body(0, test_index)
         )
     }
@@ -717,6 +978,7 @@ pub(crate) fn _afl_closure_body(&self, outer_indent: usize, test_index: usize) -
         let mut fixed_start_index = 0; //当前固定长度的变量开始分配的位置
         let mut dynamic_param_index = 0; //当前这是第几个动态长度的变量
 
+        let mut param_initial_lines = Vec::new();
         let fuzzable_param_number = self.fuzzable_params.len();
         for i in 0..fuzzable_param_number {
             let fuzzable_param = &self.fuzzable_params[i];
@@ -730,29 +992,43 @@ pub(crate) fn _afl_closure_body(&self, outer_indent: usize, test_index: usize) -
                 &dynamic_length_name.to_string(),
                 fuzzable_param,
             );
-            res.push_str(
-                format!(
-                    "{indent}{param_initial_line}\n",
-                    indent = indent,
-                    param_initial_line = param_initial_line
-                )
-                .as_str(),
-            );
+            param_initial_lines.push(param_initial_line);
             fixed_start_index = fixed_start_index + fuzzable_param._fixed_part_length();
             dynamic_param_index =
                 dynamic_param_index + fuzzable_param._dynamic_length_param_number();
         }
 
-        let mut test_function_call =
-            format!("{indent}test_function{test_index}(", indent = indent, test_index = test_index);
+        let mut test_function_call = format!("test_function{test_index}(", test_index = test_index);
         for i in 0..fuzzable_param_number {
             if i != 0 {
                 test_function_call.push_str(" ,");
             }
             test_function_call.push_str(format!("_param{}", i).as_str());
         }
-        test_function_call.push_str(");\n");
-        res.push_str(test_function_call.as_str());
+        test_function_call.push_str(");");
+
+        //差分测试的时候，两个版本各自初始化参数并调用，再比较记录下来的值
+        if differential::is_differential_mode() {
+            let inner_indent = _generate_indent(outer_indent + extra_indent + 4);
+            let versions = [
+                ("_current", test_function_call.clone()),
+                ("_other", format!("{}::{}", differential::OTHER_VERSION_MOD, test_function_call)),
+            ];
+            for (version, call) in &versions {
+                res.push_str(format!("{}let {} = _run_version(|| {{\n", indent, version).as_str());
+                for line in &param_initial_lines {
+                    res.push_str(format!("{}{}\n", inner_indent, line).as_str());
+                }
+                res.push_str(format!("{}{}\n", inner_indent, call).as_str());
+                res.push_str(format!("{}}});\n", indent).as_str());
+            }
+            res.push_str(format!("{}_compare_versions(_current, _other);\n", indent).as_str());
+        } else {
+            for line in &param_initial_lines {
+                res.push_str(format!("{}{}\n", indent, line).as_str());
+            }
+            res.push_str(format!("{}{}\n", indent, test_function_call).as_str());
+        }
 
         res
     }
@@ -784,14 +1060,24 @@ pub(crate) fn _to_well_written_function(
         //加入函数体开头的大括号
         res.push_str("{\n");
 
+        //有async调用的话，函数体放在async块里，交给执行器去驱动
+        let is_async = self._is_async(_api_graph);
+        let async_indent = _generate_indent(indent_size + 4);
+        let body_indent_size = if is_async { indent_size + 4 } else { indent_size };
+        if is_async {
+            let poll_budget = self._poll_budget.map(|index| format!("{}{}", param_prefix, index));
+            res.push_str(async_indent.as_str());
+            res.push_str(async_util::_executor_call_prefix(poll_budget).as_str());
+        }
+
         //加入函数体
         if self._unsafe_tag {
-            let unsafe_indent = _generate_indent(indent_size + 4);
+            let unsafe_indent = _generate_indent(body_indent_size + 4);
             res.push_str(unsafe_indent.as_str());
             res.push_str("unsafe {\n");
             let unsafe_function_body = self._generate_function_body_string(
                 _api_graph,
-                indent_size + 4,
+                body_indent_size + 4,
                 param_prefix,
                 local_param_prefix,
             );
@@ -801,13 +1087,18 @@ pub(crate) fn _to_well_written_function(
         } else {
             let function_body = self._generate_function_body_string(
                 _api_graph,
-                indent_size,
+                body_indent_size,
                 param_prefix,
                 local_param_prefix,
             );
             res.push_str(function_body.as_str());
         }
 
+        if is_async {
+            res.push_str(async_indent.as_str());
+            res.push_str("});\n");
+        }
+
         //加入函数体结尾的大括号
         let braket_indent = _generate_indent(indent_size);
         res.push_str(braket_indent.as_str());
@@ -897,7 +1188,26 @@ pub(crate) fn _generate_function_body_string(
         let mut res = String::new();
         let body_indent = _generate_indent(outer_indent + extra_indent);
 
-        let dead_code = self._dead_code(_api_graph);
+        let mut dead_code = self._dead_code(_api_graph);
+        let explore_error_path = error_path::is_error_path_mode();
+        let return_on_error = error_path::is_return_on_error();
+        //要检查trait约定的值，即使后面没有被用到也要保留下来
+        let oracle_laws = self._oracle_laws(_api_graph);
+        for (i, laws) in oracle_laws.iter().enumerate() {
+            if laws.is_some() {
+                dead_code[i] = false;
+            }
+        }
+        if let Some(pair) = &self._inverse_pair {
+            dead_code[pair.inverse_call] = false;
+        }
+        //差分测试时要记录下来的值
+        let observable = self._observable_values(_api_graph);
+        for (i, observable) in observable.iter().enumerate() {
+            if *observable {
+                dead_code[i] = false;
+            }
+        }
 
         //api_calls
         let api_calls_num = self.functions.len();
@@ -934,22 +1244,36 @@ pub(crate) fn _generate_function_body_string(
                     let mut former_param_name = param_name.clone();
                     let mut helper_index = 1;
                     let mut former_helper_line = String::new();
+                    //被解开的值的类型，用来找到Err分支里错误值的类型
+                    let mut value_type = match param_type {
+                        ParamType::_FunctionReturn => {
+                            let producer = &_api_graph.api_functions[self.functions[*index].func.1];
+                            _api_graph._local_type(self.functions[*index].func.1).map(|ty| {
+                                api_util::substitute_type(
+                                    ty.clone(),
+                                    &producer.generic_substitutions,
+                                )
+                                .unwrap_or(ty)
+                            })
+                        }
+                        ParamType::_FuzzableType => None,
+                    };
                     for k in 0..call_type_array_len - 1 {
                         let call_type = &call_type_array[k];
                         let helper_name = format!(
                             "{}{}_param{}_helper{}",
                             local_param_prefix, i, j, helper_index
                         );
-                        let helper_line = format!(
-                            "{}let mut {} = {};\n",
-                            body_indent,
-                            helper_name,
-                            call_type._to_call_string(
-                                &former_param_name,
-                                _api_graph.cache,
-                                full_name_map
-                            )
+                        let helper_value = self._helper_value(
+                            _api_graph,
+                            call_type,
+                            &former_param_name,
+                            value_type.as_ref(),
+                            &body_indent,
                         );
+                        value_type = _split_call_type_output(_api_graph, call_type, value_type);
+                        let helper_line =
+                            format!("{}let mut {} = {};\n", body_indent, helper_name, helper_value);
                         if helper_index > 1 {
                             if !api_util::_need_mut_tag(call_type) {
                                 former_helper_line = former_helper_line.replace("let mut ", "let ");
@@ -977,7 +1301,41 @@ pub(crate) fn _generate_function_body_string(
             //如果不是最后一个调用
             let api_function_index = api_call.func.1;
             let api_function = &_api_graph.api_functions[api_function_index];
-            if dead_code[i] || api_function._has_no_output() {
+            //返回值是Result或者Option的话，需要解开
+            let prelude_output = match &api_function.output {
+                Some(output_type)
+                    if prelude_type::_prelude_type_need_special_dealing(
+                        output_type,
+                        _api_graph.cache,
+                        &_api_graph.full_name_map,
+                    ) =>
+                {
+                    Some(PreludeType::from_type(
+                        output_type,
+                        _api_graph.cache,
+                        &_api_graph.full_name_map,
+                    ))
+                }
+                _ => None,
+            };
+            //探索错误路径的时候，Err分支里会使用错误值
+            let err_branch = match &prelude_output {
+                Some(PreludeType::PreludeResult { err_type, .. }) if explore_error_path => {
+                    let err_type = api_util::substitute_type(
+                        err_type.clone(),
+                        &api_function.generic_substitutions,
+                    )
+                    .unwrap_or(err_type.clone());
+                    Some(self._generate_err_branch(_api_graph, &err_type, &body_indent))
+                }
+                _ => None,
+            };
+
+            if dead_code[i] && err_branch.is_some() {
+                //返回值没有被使用，只需要处理Err
+                let (err_pattern, _) = err_branch.as_ref().unwrap();
+                res.push_str(format!("if let Err({}) = ", err_pattern).as_str());
+            } else if dead_code[i] || api_function._has_no_output() {
                 res.push_str("let _ = ");
             } else {
                 let mut_tag = if self._is_function_need_mut_tag(i) { "mut " } else { "" };
@@ -985,25 +1343,15 @@ pub(crate) fn _generate_function_body_string(
             }
 
             //对于Result和Option
-            if let Some(output_type) = &api_function.output {
-                if prelude_type::_prelude_type_need_special_dealing(
-                    &output_type,
-                    _api_graph.cache,
-                    &_api_graph.full_name_map,
-                ) && !dead_code[i]
-                {
-                    let prelude_type = PreludeType::from_type(
-                        output_type,
-                        _api_graph.cache,
-                        &_api_graph.full_name_map,
-                    );
-                    if prelude_type.is_option() {
+            if let Some(prelude) = &prelude_output {
+                if !dead_code[i] {
+                    if return_on_error {
+                        res.push_str("match ");
+                    } else if prelude.is_option() {
                         res.push_str("if let Some(x) = ");
-                    } else if prelude_type.is_result() {
+                    } else if prelude.is_result() {
                         res.push_str("if let Ok(x) = ");
                     }
-                } else {
-                    res.push_str("");
                 }
             }
 
@@ -1012,7 +1360,10 @@ pub(crate) fn _generate_function_body_string(
                 ApiType::BareFunction => {
                     let api_function_full_name =
                         &_api_graph.api_functions[*function_index].full_name;
-                    res.push_str(api_function_full_name.as_str());
+                    //迭代器的辅助函数在图中的名字各不相同，调用的都是同一个函数
+                    let call_name = iterator::_helper_function_name(api_function_full_name)
+                        .unwrap_or(api_function_full_name.as_str());
+                    res.push_str(call_name);
                 }
                 ApiType::GenericFunction => todo!(),
             }
@@ -1029,24 +1380,180 @@ pub(crate) fn _generate_function_body_string(
             }
 
             res.push_str(")");
-            if let Some(output_type) = &api_function.output {
-                //在这里添加，unwrap
-                if prelude_type::_prelude_type_need_special_dealing(
-                    &output_type,
-                    _api_graph.cache,
-                    &_api_graph.full_name_map,
-                ) && !dead_code[i]
-                {
+            if api_function._async_tag._is_async() {
+                res.push_str(".await");
+            }
+            //在这里添加，unwrap
+            match (&prelude_output, &err_branch) {
+                (Some(_), Some((_, err_statements))) if dead_code[i] => {
+                    res.push_str(format!(" {{\n{}{}}}\n", err_statements, body_indent).as_str());
+                }
+                (Some(prelude), _) if !dead_code[i] && return_on_error => {
+                    if prelude.is_option() {
+                        res.push_str(" {Some(x) => x, None => return,};\n");
+                    } else if let Some((err_pattern, err_statements)) = &err_branch {
+                        res.push_str(
+                            format!(
+                                " {{Ok(x) => x, Err({}) => {{\n{}{}    return;\n{}}}}};\n",
+                                err_pattern, err_statements, body_indent, body_indent
+                            )
+                            .as_str(),
+                        );
+                    } else {
+                        //只做差分测试，不探索错误路径，错误值不使用
+                        res.push_str(" {Ok(x) => x, Err(_) => return,};\n");
+                    }
+                }
+                (Some(_), _) if !dead_code[i] => {
                     res.push_str("{x} else {use std::process;process::exit(0);};\n");
-                } else {
-                    res.push_str(";\n");
                 }
-            } else {
-                res.push_str(";\n");
+                _ => res.push_str(";\n"),
+            }
+
+            //刚产生的值，检查它自己的约定
+            if let Some(laws) = &oracle_laws[i] {
+                let local_name = format!("{}{}", local_param_prefix, i);
+                for check in laws.unary_checks(&local_name) {
+                    res.push_str(format!("{}{}\n", body_indent, check).as_str());
+                }
+            }
+            if observable[i] {
+                let local_name = format!("{}{}", local_param_prefix, i);
+                let observe = differential::_observe_statement(&local_name);
+                res.push_str(format!("{}{}\n", body_indent, observe).as_str());
+            }
+            //互逆的一对调用，检查B(A(x)) == x
+            if let Some(pair) = self._inverse_pair.as_ref().filter(|pair| pair.inverse_call == i) {
+                let check = inverse_pair::_inverse_check(
+                    _api_graph,
+                    self,
+                    pair,
+                    param_prefix,
+                    local_param_prefix,
+                );
+                res.push_str(format!("{}{}\n", body_indent, check).as_str());
+            }
+        }
+
+        //最后检查同一类型的两个值之间的约定
+        let free_at_end = borrow_check::values_free_at_end(_api_graph, self);
+        for i in 0..api_calls_num {
+            for j in i + 1..api_calls_num {
+                let (left_laws, right_laws) = match (&oracle_laws[i], &oracle_laws[j]) {
+                    (Some(left_laws), Some(right_laws)) => (left_laws, right_laws),
+                    _ => continue,
+                };
+                if left_laws.type_ != right_laws.type_ || !free_at_end[i] || !free_at_end[j] {
+                    continue;
+                }
+                let left = format!("{}{}", local_param_prefix, i);
+                let right = format!("{}{}", local_param_prefix, j);
+                for check in left_laws.pair_checks(&left, &right) {
+                    res.push_str(format!("{}{}\n", body_indent, check).as_str());
+                }
             }
         }
         res
     }
+
+    /// 每个返回值可以检查的trait约定，没有打开trait oracle的时候都是None
+    pub(crate) fn _oracle_laws(&self, _api_graph: &ApiGraph<'_>) -> Vec<Option<TraitLaws>> {
+        let oracle_mode = oracle::is_oracle_mode();
+        self.functions
+            .iter()
+            .map(|api_call| {
+                if !oracle_mode {
+                    return None;
+                }
+                _api_graph
+                    ._local_type(api_call.func.1)
+                    .and_then(|ty| TraitLaws::from_type(_api_graph, &ty))
+            })
+            .collect()
+    }
+
+    /// 差分测试时每个返回值是否要记录下来
+    pub(crate) fn _observable_values(&self, _api_graph: &ApiGraph<'_>) -> Vec<bool> {
+        let differential_mode = differential::is_differential_mode();
+        self.functions
+            .iter()
+            .map(|api_call| {
+                differential_mode
+                    && _api_graph
+                        ._local_type(api_call.func.1)
+                        .map_or(false, |ty| differential::is_observable(_api_graph, &ty))
+            })
+            .collect()
+    }
+
+    /// 探索错误路径时Err分支的模式和语句，语句比当前多缩进一层
+    pub(crate) fn _generate_err_branch(
+        &self,
+        _api_graph: &ApiGraph<'_>,
+        err_type: &clean::Type,
+        indent: &String,
+    ) -> (String, String) {
+        let err_name = "_err".to_string();
+        let (statements, need_mut) =
+            error_path::_error_consumer_statements(_api_graph, err_type, &err_name);
+        let err_pattern = if need_mut { format!("mut {}", err_name) } else { err_name };
+        let mut err_statements = String::new();
+        for statement in &statements {
+            err_statements.push_str(format!("{}    {}\n", indent, statement).as_str());
+        }
+        (err_pattern, err_statements)
+    }
+
+    /// 参数的一段调用方式，探索错误路径时，解开Result的那一段在Err分支里使用错误值
+    fn _helper_value(
+        &self,
+        _api_graph: &ApiGraph<'_>,
+        call_type: &CallType,
+        param_name: &String,
+        value_type: Option<&clean::Type>,
+        indent: &String,
+    ) -> String {
+        let cache = _api_graph.cache;
+        let full_name_map = &_api_graph.full_name_map;
+        if let (CallType::_UnwrapResult(inner), Some(value_type)) = (call_type, value_type) {
+            let prelude = PreludeType::from_type(value_type, cache, full_name_map);
+            if let PreludeType::PreludeResult { err_type, .. } = prelude {
+                if error_path::is_error_path_mode() {
+                    let (err_pattern, err_statements) =
+                        self._generate_err_branch(_api_graph, &err_type, indent);
+                    if !err_statements.is_empty() {
+                        return format!(
+                            "match {} {{Ok(_t) => _t, Err({}) => {{\n{}{}    return;\n{}}}}}",
+                            inner._to_call_string(param_name, cache, full_name_map),
+                            err_pattern,
+                            err_statements,
+                            indent,
+                            indent
+                        );
+                    }
+                }
+            }
+        }
+        call_type._to_call_string(param_name, cache, full_name_map)
+    }
+}
+
+/// 参数的一段调用方式之后值的类型，只跟踪直接使用、clone和解开Option/Result，其余的返回None
+fn _split_call_type_output(
+    _api_graph: &ApiGraph<'_>,
+    call_type: &CallType,
+    value_type: Option<clean::Type>,
+) -> Option<clean::Type> {
+    let value_type = value_type?;
+    match call_type {
+        CallType::_DirectCall => Some(value_type),
+        CallType::_Clone(inner) if matches!(**inner, CallType::_DirectCall) => Some(value_type),
+        CallType::_UnwrapOption(..) | CallType::_UnwrapResult(..) => Some(
+            PreludeType::from_type(&value_type, _api_graph.cache, &_api_graph.full_name_map)
+                ._get_final_type(),
+        ),
+        _ => None,
+    }
 }
 
 pub(crate) fn _generate_indent(indent_size: usize) -> String {
@@ -10,6 +10,7 @@
 use crate::clean::{self, GenericArg, GenericArgs, PrimitiveType};
 use crate::formats::cache::Cache;
 use crate::fuzz_targets_gen::call_type::CallType;
+use crate::fuzz_targets_gen::callback::CallbackSignature;
 use crate::fuzz_targets_gen::fuzz_type::{self, FuzzableCallType};
 use crate::fuzz_targets_gen::impl_util::FullNameMap;
 use crate::fuzz_targets_gen::prelude_type::{self, PreludeType};
@@ -177,7 +178,10 @@ pub(crate) fn _is_end_type(
             if support_generic { true } else { false }
         }
         clean::Type::Primitive(_) => true,
-        clean::Type::BareFunction(_) => false,
+        //可以合成回调的函数指针和闭包
+        clean::Type::BareFunction(_) | clean::Type::ImplTrait(_) | clean::Type::DynTrait(_, _) => {
+            CallbackSignature::from_type(ty, cache, full_name_map).is_some()
+        }
         clean::Type::Tuple(inner) => {
             let mut flag = true;
             for inner_type in inner {
@@ -198,10 +202,7 @@ pub(crate) fn _is_end_type(
             let inner_type = &**type_;
             return _is_end_type(inner_type, cache, full_name_map, support_generic);
         }
-        clean::Type::QPath(_)
-        | clean::Type::Infer
-        | clean::Type::ImplTrait(_)
-        | clean::Type::DynTrait(_, _) => false,
+        clean::Type::QPath(_) | clean::Type::Infer => false,
     }
 }
 
@@ -0,0 +1,75 @@
+//! 支持async的API
+//! 序列中包含async函数时，测试函数的函数体被包在一个`async move`块里，每个async调用后面加上`.await`
+//! 这个块由测试文件里自带的执行器驱动，有两种模式：
+//! 1. 默认模式：`_block_on`，单线程，Pending的时候park当前线程，直到waker把它唤醒。
+//!    park有超时，poll的次数也有上限，超过上限认为future不会再完成，直接退出，不让fuzzer卡住
+//! 2. mock waker模式：`_poll_bounded`，waker什么也不做，最多poll输入决定的次数，
+//!    还是Pending就直接放弃，避免永远不会被唤醒的future卡住fuzzer
+//!    通过环境变量`FRIES_ASYNC_MOCK_WAKER`打开
+
+/// 设置了这个环境变量（并且不是0）的时候使用mock waker模式
+static MOCK_WAKER_ENV: &'static str = "FRIES_ASYNC_MOCK_WAKER";
+
+/// 是否使用mock waker模式
+pub(crate) fn is_mock_waker_mode() -> bool {
+    match std::env::var(MOCK_WAKER_ENV) {
+        Ok(value) => value != "0" && !value.is_empty(),
+        Err(_) => false,
+    }
+}
+
+/// 测试函数体外面包的一层，poll_budget是mock waker模式下决定poll次数的参数名
+pub(crate) fn _executor_call_prefix(poll_budget: Option<String>) -> String {
+    match poll_budget {
+        Some(param_name) => format!("_poll_bounded({}, async move {{\n", param_name),
+        None => "_block_on(async move {\n".to_string(),
+    }
+}
+
+/// 测试文件中执行器的定义
+pub(crate) fn _executor_function(mock_waker: bool) -> &'static str {
+    if mock_waker { _poll_bounded() } else { _block_on() }
+}
+
+fn _block_on() -> &'static str {
+    "fn _block_on<F: std::future::Future>(future: F) -> F::Output {
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake};
+    struct _ThreadWaker(std::thread::Thread);
+    impl Wake for _ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+    let waker = Arc::new(_ThreadWaker(std::thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    for _ in 0..1024 {
+        match future.as_mut().poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => std::thread::park_timeout(std::time::Duration::from_millis(10)),
+        }
+    }
+    use std::process;
+    process::exit(0);
+}\n"
+}
+
+fn _poll_bounded() -> &'static str {
+    "fn _poll_bounded<F: std::future::Future<Output = ()>>(budget: u8, future: F) {
+    use std::sync::Arc;
+    use std::task::{Context, Poll, Wake};
+    struct _NoopWaker;
+    impl Wake for _NoopWaker {
+        fn wake(self: Arc<Self>) {}
+    }
+    let waker = Arc::new(_NoopWaker).into();
+    let mut cx = Context::from_waker(&waker);
+    let mut future = Box::pin(future);
+    for _ in 0..=budget % 64 {
+        if let Poll::Ready(()) = future.as_mut().poll(&mut cx) {
+            return;
+        }
+    }
+}\n"
+}
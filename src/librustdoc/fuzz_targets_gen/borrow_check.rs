@@ -0,0 +1,386 @@
+//! 独立于生成算法的所有权和借用检查
+//! [`check_sequence`] 按顺序模拟一个ApiSequence里每个返回值的move、copy、借用和重借用
+//! 如果某个调用的返回值类型里带有引用或者生命周期，就认为它借用了这次调用中被借用的参数，
+//! 这个借用一直存活到持有者（以及持有者的持有者）的最后一次使用
+//! 不合法的时候返回[`BorrowError`]，可以直接打印出原因
+
+use std::fmt;
+
+use crate::clean::{self, GenericArg, GenericArgs, GenericBound, Term, TypeBindingKind};
+use crate::fuzz_targets_gen::api_function::ApiFunction;
+use crate::fuzz_targets_gen::api_graph::ApiGraph;
+use crate::fuzz_targets_gen::api_sequence::{ApiSequence, ParamType};
+use crate::fuzz_targets_gen::api_util;
+use crate::fuzz_targets_gen::call_type::CallType;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_span::symbol::kw;
+
+/// 一个返回值被某次调用使用的方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Access {
+    /// 按值传递，但类型是Copy的（包括不可变引用本身）
+    Copy,
+    /// 按值传递，所有权转移
+    Move,
+    /// 不可变借用
+    Borrow,
+    /// 可变借用，或者对可变引用的重借用
+    MutBorrow,
+    /// 先clone再传进去，只在调用的时候借用一下，不会留下借用
+    Clone,
+}
+
+/// 检查失败的原因，下标都是序列中api call的位置，也就是生成代码里的_localN
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum BorrowError {
+    UseBeforeDefinition { value: usize, used_at: usize },
+    UseAfterMove { value: usize, moved_at: usize, used_at: usize },
+    MoveWhileBorrowed { value: usize, holder: usize, used_at: usize },
+    MutBorrowWhileBorrowed { value: usize, holder: usize, used_at: usize },
+    UseWhileMutBorrowed { value: usize, holder: usize, used_at: usize },
+    ConflictInSameCall { value: usize, used_at: usize },
+    MissingMutTag { value: usize, used_at: usize },
+}
+
+impl fmt::Display for BorrowError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BorrowError::UseBeforeDefinition { value, used_at } => {
+                write!(f, "call {} uses _local{} before it is defined", used_at, value)
+            }
+            BorrowError::UseAfterMove { value, moved_at, used_at } => write!(
+                f,
+                "call {} uses _local{}, which was already moved by call {}",
+                used_at, value, moved_at
+            ),
+            BorrowError::MoveWhileBorrowed { value, holder, used_at } => write!(
+                f,
+                "call {} moves _local{} while _local{} still borrows from it",
+                used_at, value, holder
+            ),
+            BorrowError::MutBorrowWhileBorrowed { value, holder, used_at } => write!(
+                f,
+                "call {} mutably borrows _local{} while _local{} still borrows from it",
+                used_at, value, holder
+            ),
+            BorrowError::UseWhileMutBorrowed { value, holder, used_at } => write!(
+                f,
+                "call {} uses _local{} while _local{} still holds a mutable borrow of it",
+                used_at, value, holder
+            ),
+            BorrowError::ConflictInSameCall { value, used_at } => write!(
+                f,
+                "call {} uses _local{} more than once and at least one use is a move or a mutable borrow",
+                used_at, value
+            ),
+            BorrowError::MissingMutTag { value, used_at } => write!(
+                f,
+                "call {} mutably borrows _local{}, but it is not declared as mut",
+                used_at, value
+            ),
+        }
+    }
+}
+
+/// 一次借用：holder的返回值借用了lender的返回值
+#[derive(Debug, Clone, Copy)]
+struct Loan {
+    holder: usize,
+    kind: Access,
+}
+
+/// 检查一个序列是否满足所有权和借用规则
+pub(crate) fn check_sequence(
+    graph: &ApiGraph<'_>,
+    sequence: &ApiSequence,
+) -> Result<(), BorrowError> {
+    let call_num = sequence.functions.len();
+
+    // 每个返回值是否本身就是一个引用，以及返回值类型里是否带有借用
+    let mut is_reference_value = Vec::with_capacity(call_num);
+    let mut output_borrows = Vec::with_capacity(call_num);
+    for api_call in &sequence.functions {
+        let output = local_type(graph, api_call.func.1);
+        is_reference_value.push(match output {
+            Some(clean::Type::BorrowedRef { .. }) => true,
+            _ => false,
+        });
+        output_borrows.push(match output {
+            Some(ref ty) => _contains_borrow(ty),
+            None => false,
+        });
+    }
+
+    // 收集每个调用对之前返回值的访问方式，以及每个返回值最后一次被使用的位置
+    let mut accesses: Vec<Vec<(usize, Access)>> = Vec::with_capacity(call_num);
+    let mut last_use: Vec<Option<usize>> = vec![None; call_num];
+    for (call_index, api_call) in sequence.functions.iter().enumerate() {
+        let function = &graph.api_functions[api_call.func.1];
+        let mut call_accesses = Vec::new();
+        for (param_index, (param_type, value, call_type)) in api_call.params.iter().enumerate() {
+            if let ParamType::_FuzzableType = param_type {
+                continue;
+            }
+            if *value >= call_index {
+                return Err(BorrowError::UseBeforeDefinition {
+                    value: *value,
+                    used_at: call_index,
+                });
+            }
+            let output = local_type(graph, sequence.functions[*value].func.1);
+            let input = function.inputs.get(param_index).map(|ty| resolved_type(ty, function));
+            let access = access_kind(output.as_ref(), input.as_ref(), call_type);
+            last_use[*value] = Some(call_index);
+            call_accesses.push((*value, access));
+        }
+        accesses.push(call_accesses);
+    }
+
+    // 返回值带有借用的调用，会借用它这次借用（或者转手）的参数
+    let mut loans: FxHashMap<usize, Vec<Loan>> = FxHashMap::default();
+    for call_index in 0..call_num {
+        if !output_borrows[call_index] {
+            continue;
+        }
+        for (value, access) in &accesses[call_index] {
+            let kind = match access {
+                Access::Borrow | Access::MutBorrow => *access,
+                // 把一个带借用的值传进来，返回值会继续持有它背后的借用，clone出来的值生命周期也一样
+                Access::Copy | Access::Move | Access::Clone if output_borrows[*value] => {
+                    Access::Copy
+                }
+                Access::Copy | Access::Move | Access::Clone => continue,
+            };
+            loans.entry(*value).or_default().push(Loan { holder: call_index, kind });
+        }
+    }
+
+    // 借用的持有者还活着，被借用的值就还活着：从后往前传播最后使用的位置
+    let mut live_until = last_use.clone();
+    for lender in (0..call_num).rev() {
+        if let Some(lender_loans) = loans.get(&lender) {
+            for loan in lender_loans {
+                live_until[lender] = live_until[lender].max(live_until[loan.holder]);
+            }
+        }
+    }
+
+    // 按顺序模拟每次调用
+    let mut moved_at: Vec<Option<usize>> = vec![None; call_num];
+    for call_index in 0..call_num {
+        let call_accesses = &accesses[call_index];
+        for (i, (value, access)) in call_accesses.iter().enumerate() {
+            for (other_value, other_access) in &call_accesses[i + 1..] {
+                if value == other_value && (_is_exclusive(*access) || _is_exclusive(*other_access))
+                {
+                    return Err(BorrowError::ConflictInSameCall {
+                        value: *value,
+                        used_at: call_index,
+                    });
+                }
+            }
+        }
+
+        for (value, access) in call_accesses {
+            let value = *value;
+            if let Some(moved_at) = moved_at[value] {
+                return Err(BorrowError::UseAfterMove { value, moved_at, used_at: call_index });
+            }
+            if let Some(lender_loans) = loans.get(&value) {
+                for loan in lender_loans {
+                    let holder = loan.holder;
+                    // 持有者在当前调用之后才出现，或者已经不再使用
+                    if holder >= call_index
+                        || live_until[holder].map_or(true, |until| until < call_index)
+                    {
+                        continue;
+                    }
+                    match (*access, loan.kind) {
+                        (Access::Move, Access::Borrow | Access::MutBorrow) => {
+                            return Err(BorrowError::MoveWhileBorrowed {
+                                value,
+                                holder,
+                                used_at: call_index,
+                            });
+                        }
+                        (Access::MutBorrow, Access::Borrow | Access::MutBorrow) => {
+                            return Err(BorrowError::MutBorrowWhileBorrowed {
+                                value,
+                                holder,
+                                used_at: call_index,
+                            });
+                        }
+                        (Access::Borrow | Access::Copy | Access::Clone, Access::MutBorrow) => {
+                            return Err(BorrowError::UseWhileMutBorrowed {
+                                value,
+                                holder,
+                                used_at: call_index,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            if *access == Access::MutBorrow
+                && !is_reference_value[value]
+                && !sequence._is_function_need_mut_tag(value)
+            {
+                return Err(BorrowError::MissingMutTag { value, used_at: call_index });
+            }
+            if *access == Access::Move {
+                moved_at[value] = Some(call_index);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// 序列执行完之后，哪些返回值既没有被move，也没有被某个带借用的返回值借走
+/// 这些值可以在最后再被读一次
+pub(crate) fn values_free_at_end(graph: &ApiGraph<'_>, sequence: &ApiSequence) -> Vec<bool> {
+    let call_num = sequence.functions.len();
+    let mut free = vec![true; call_num];
+    for api_call in &sequence.functions {
+        let function = &graph.api_functions[api_call.func.1];
+        let output_borrows = match local_type(graph, api_call.func.1) {
+            Some(ref ty) => _contains_borrow(ty),
+            None => false,
+        };
+        for (param_index, (param_type, value, call_type)) in api_call.params.iter().enumerate() {
+            if let ParamType::_FuzzableType = param_type {
+                continue;
+            }
+            if *value >= call_num {
+                continue;
+            }
+            let output = local_type(graph, sequence.functions[*value].func.1);
+            let input = function.inputs.get(param_index).map(|ty| resolved_type(ty, function));
+            match access_kind(output.as_ref(), input.as_ref(), call_type) {
+                Access::Move => free[*value] = false,
+                Access::Borrow | Access::MutBorrow if output_borrows => free[*value] = false,
+                _ => {}
+            }
+        }
+    }
+    free
+}
+
+/// 判断一个返回值（类型为output）通过call_type传给类型为input的参数时，是怎样的访问
+pub(crate) fn access_kind(
+    output: Option<&clean::Type>,
+    input: Option<&clean::Type>,
+    call_type: &CallType,
+) -> Access {
+    if call_type._contains_clone_call_type() {
+        return Access::Clone;
+    }
+    if call_type._contains_move_call_type() {
+        match output {
+            Some(clean::Type::BorrowedRef { mutability, .. }) if mutability.is_mut() => {
+                return match call_type {
+                    // &mut T直接传给&mut T参数，是隐式的重借用
+                    CallType::_DirectCall => match input {
+                        Some(clean::Type::BorrowedRef { mutability, .. })
+                            if mutability.is_mut() =>
+                        {
+                            Access::MutBorrow
+                        }
+                        _ => Access::Move,
+                    },
+                    // 只有Copy类型才会解引用，相当于读一次
+                    CallType::_Deref(..) | CallType::_UnsafeDeref(..) => Access::Borrow,
+                    _ => Access::Move,
+                };
+            }
+            // 不可变引用本身是Copy的
+            Some(clean::Type::BorrowedRef { .. }) => return Access::Copy,
+            Some(output) if api_util::_copy_type(output) => return Access::Copy,
+            _ => return Access::Move,
+        }
+    }
+    match call_type {
+        CallType::_MutBorrowedRef(..) | CallType::_MutRawPointer(..) => Access::MutBorrow,
+        _ => match input {
+            Some(input) if api_util::_is_mutable_borrow_occurs(input, call_type) => {
+                Access::MutBorrow
+            }
+            _ => Access::Borrow,
+        },
+    }
+}
+
+fn _is_exclusive(access: Access) -> bool {
+    match access {
+        Access::Move | Access::MutBorrow => true,
+        Access::Copy | Access::Borrow | Access::Clone => false,
+    }
+}
+
+/// 局部变量里保存的类型（Option和Result已经被解开），并替换掉泛型参数
+fn local_type(graph: &ApiGraph<'_>, func_index: usize) -> Option<clean::Type> {
+    let function = &graph.api_functions[func_index];
+    graph._local_type(func_index).map(|ty| resolved_type(&ty, function))
+}
+
+fn resolved_type(ty: &clean::Type, function: &ApiFunction) -> clean::Type {
+    match api_util::substitute_type(ty.clone(), &function.generic_substitutions) {
+        Some(substi) => substi,
+        None => ty.clone(),
+    }
+}
+
+/// 类型里是否带有非'static的引用或者生命周期参数（裸指针不算）
+pub(crate) fn _contains_borrow(ty: &clean::Type) -> bool {
+    match ty {
+        clean::Type::BorrowedRef { lifetime, .. } => match lifetime {
+            Some(lifetime) => lifetime.0 != kw::StaticLifetime,
+            None => true,
+        },
+        clean::Type::Path { path } => {
+            for segment in &path.segments {
+                if let GenericArgs::AngleBracketed { args, .. } = &segment.args {
+                    for arg in args.iter() {
+                        match arg {
+                            GenericArg::Lifetime(lifetime) => {
+                                if lifetime.0 != kw::StaticLifetime {
+                                    return true;
+                                }
+                            }
+                            GenericArg::Type(inner_ty) => {
+                                if _contains_borrow(inner_ty) {
+                                    return true;
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+            }
+            false
+        }
+        clean::Type::Tuple(types) => types.iter().any(|ty_| _contains_borrow(ty_)),
+        clean::Type::Slice(type_) | clean::Type::Array(type_, ..) => _contains_borrow(type_),
+        // 返回的迭代器之类：`impl Iterator<Item = &'a T> + 'a`
+        clean::Type::ImplTrait(bounds) => bounds.iter().any(|bound| match bound {
+            GenericBound::Outlives(lifetime) => lifetime.0 != kw::StaticLifetime,
+            GenericBound::TraitBound(poly_trait, _) => {
+                poly_trait.trait_.segments.iter().any(|segment| match &segment.args {
+                    GenericArgs::AngleBracketed { args, bindings } => {
+                        args.iter().any(|arg| match arg {
+                            GenericArg::Lifetime(lifetime) => lifetime.0 != kw::StaticLifetime,
+                            GenericArg::Type(inner_ty) => _contains_borrow(inner_ty),
+                            _ => false,
+                        }) || bindings.iter().any(|binding| match &binding.kind {
+                            TypeBindingKind::Equality { term: Term::Type(inner_ty) } => {
+                                _contains_borrow(inner_ty)
+                            }
+                            _ => false,
+                        })
+                    }
+                    GenericArgs::Parenthesized { .. } => false,
+                })
+            }
+        }),
+        _ => false,
+    }
+}
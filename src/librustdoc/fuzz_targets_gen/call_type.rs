@@ -2,6 +2,7 @@
 use crate::formats::cache::Cache;
 use crate::fuzz_targets_gen::api_function::ApiUnsafety;
 use crate::fuzz_targets_gen::api_util::_type_name;
+use crate::fuzz_targets_gen::error_path;
 use crate::fuzz_targets_gen::impl_util::FullNameMap;
 
 #[derive(Clone, Debug, Eq, PartialEq, Hash)]
@@ -19,6 +20,7 @@ pub(crate) enum CallType {
     _ToResult(Box<CallType>),                     //产生一个result类型, never used
     _UnwrapOption(Box<CallType>),                 //获得option变量的值
     _ToOption(Box<CallType>),                     //产生一个option类型
+    _Clone(Box<CallType>),                        //先clone一份，原来的值不会被move
 }
 
 impl CallType {
@@ -81,12 +83,21 @@ pub(crate) fn _to_call_string(
             CallType::_UnwrapResult(inner_) => {
                 //FIXME: 暂时先unwrap，后面再想办法处理逻辑
                 let inner_call_string = inner_._to_call_string(variable_name, cache, full_name_map);
-                format!("_unwrap_result({})", inner_call_string)
+                //探索错误路径或者差分测试的时候不退出进程，直接从测试函数返回
+                if error_path::is_return_on_error() {
+                    format!("match {} {{Ok(_t) => _t, Err(_) => return}}", inner_call_string)
+                } else {
+                    format!("_unwrap_result({})", inner_call_string)
+                }
             }
             CallType::_UnwrapOption(inner_) => {
                 //FIXME: 暂时先unwrap,后面在想办法处理
                 let inner_call_string = inner_._to_call_string(variable_name, cache, full_name_map);
-                format!("_unwrap_option({})", inner_call_string)
+                if error_path::is_return_on_error() {
+                    format!("match {} {{Some(_t) => _t, None => return}}", inner_call_string)
+                } else {
+                    format!("_unwrap_option({})", inner_call_string)
+                }
             }
             CallType::_ToOption(inner_) => {
                 let inner_call_string = inner_._to_call_string(variable_name, cache, full_name_map);
@@ -96,6 +107,13 @@ pub(crate) fn _to_call_string(
                 let inner_call_string = inner_._to_call_string(variable_name, cache, full_name_map);
                 format!("Ok({})", inner_call_string)
             }
+            CallType::_Clone(inner_) => {
+                let inner_call_string = inner_._to_call_string(variable_name, cache, full_name_map);
+                match **inner_ {
+                    CallType::_DirectCall => format!("{}.clone()", inner_call_string),
+                    _ => format!("({}).clone()", inner_call_string),
+                }
+            }
         }
     }
 
@@ -123,6 +141,8 @@ pub(crate) fn _contains_move_call_type(&self) -> bool {
                     _ => call_type._contains_move_call_type(),
                 }
             }
+            //clone出来的是新的值，原来的值没有被move
+            CallType::_Clone(..) => false,
         }
     }
 
@@ -143,10 +163,51 @@ pub(crate) fn _contains_unwrap_call_type(&self) -> bool {
             | CallType::_UnsafeDeref(call_type)
             | CallType::_Deref(call_type)
             | CallType::_ToOption(call_type)
-            | CallType::_ToResult(call_type) => call_type._contains_move_call_type(),
+            | CallType::_ToResult(call_type)
+            | CallType::_Clone(call_type) => call_type._contains_move_call_type(),
+        }
+    }
+
+    /// 是否在某一步插入了clone
+    pub(crate) fn _contains_clone_call_type(&self) -> bool {
+        match self {
+            CallType::_NotCompatible | CallType::_DirectCall | CallType::_AsConvert(..) => false,
+            CallType::_Clone(..) => true,
+            CallType::_BorrowedRef(call_type)
+            | CallType::_MutBorrowedRef(call_type)
+            | CallType::_ConstRawPointer(call_type, _)
+            | CallType::_MutRawPointer(call_type, _)
+            | CallType::_UnsafeDeref(call_type)
+            | CallType::_Deref(call_type)
+            | CallType::_UnwrapOption(call_type)
+            | CallType::_UnwrapResult(call_type)
+            | CallType::_ToOption(call_type)
+            | CallType::_ToResult(call_type) => call_type._contains_clone_call_type(),
+        }
+    }
+
+    /// 在最里面的直接调用外面插入clone，也就是先clone原来的值，再做后面的转换
+    pub(crate) fn _insert_clone_call_type(&self) -> Self {
+        let mut call_type_array = self._call_type_to_array();
+        if let Some(CallType::_DirectCall) = call_type_array.last() {
+            let last_index = call_type_array.len() - 1;
+            call_type_array.insert(last_index, CallType::_Clone(Box::new(CallType::_DirectCall)));
+            CallType::_array_to_call_type(&call_type_array)
+        } else {
+            self.clone()
         }
     }
 
+    /// 去掉插入的clone
+    pub(crate) fn _remove_clone_call_type(&self) -> Self {
+        let mut call_type_array = self._call_type_to_array();
+        call_type_array.retain(|call_type| match call_type {
+            CallType::_Clone(..) => false,
+            _ => true,
+        });
+        CallType::_array_to_call_type(&call_type_array)
+    }
+
     pub(crate) fn _call_type_to_array(&self) -> Vec<CallType> {
         match self {
             CallType::_NotCompatible | CallType::_DirectCall | CallType::_AsConvert(..) => {
@@ -161,7 +222,8 @@ pub(crate) fn _call_type_to_array(&self) -> Vec<CallType> {
             | CallType::_UnsafeDeref(call_type)
             | CallType::_Deref(call_type)
             | CallType::_ToOption(call_type)
-            | CallType::_ToResult(call_type) => {
+            | CallType::_ToResult(call_type)
+            | CallType::_Clone(call_type) => {
                 let mut call_types = vec![self.clone()];
                 let mut inner_call_types = call_type._call_type_to_array();
                 call_types.append(&mut inner_call_types);
@@ -248,6 +310,7 @@ fn _inner_array_to_call_type(call_type_array: &Vec<CallType>, start: usize) -> S
             CallType::_ToOption(..) => CallType::_ToOption(Box::new(inner_type)),
             CallType::_UnwrapResult(..) => CallType::_UnwrapResult(Box::new(inner_type)),
             CallType::_ToResult(..) => CallType::_ToResult(Box::new(inner_type)),
+            CallType::_Clone(..) => CallType::_Clone(Box::new(inner_type)),
         }
     }
 }
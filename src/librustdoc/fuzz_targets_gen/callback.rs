@@ -0,0 +1,425 @@
+//! 为闭包和函数指针类型的参数合成回调
+//! 回调的返回值来自fuzz输入中一张固定长度的表，每次调用依次从表里取值，并打印一行日志，
+//! 这样回调里面发生的崩溃也可以直接复现
+//! 合成的闭包不捕获任何变量，在测试函数里以`fn(..) -> ..`的类型传递，
+//! 所以既可以传给函数指针，也可以传给`impl Fn(..)`、`F: FnMut(..)`和`&dyn Fn(..)`
+
+use crate::clean::{
+    self, GenericArg, GenericArgs, GenericBound, GenericParamDefKind, PrimitiveType,
+};
+use crate::formats::cache::Cache;
+use crate::fuzz_targets_gen::api_function::ApiFunction;
+use crate::fuzz_targets_gen::impl_util::FullNameMap;
+use rustc_span::Symbol;
+use rustc_target::spec::abi::Abi;
+
+/// 每个回调在输入中占用的字节数
+pub(crate) static CALLBACK_TABLE_LENGTH: usize = 16;
+
+/// 回调除了Fn系列的trait，还可以满足的约束（函数指针都实现了）
+static CALLBACK_MARKER_TRAITS: [&'static str; 6] =
+    ["Send", "Sync", "Copy", "Clone", "Sized", "Unpin"];
+
+/// 合成回调的签名
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct CallbackSignature {
+    /// 参数类型，已经写成测试文件中的形式，去掉了生命周期
+    pub(crate) inputs: Vec<String>,
+    /// 返回值，只支持可以从字节构造的基本类型，None表示`()`
+    pub(crate) output: Option<PrimitiveType>,
+}
+
+impl CallbackSignature {
+    /// 从函数指针、`impl Fn(..)`或者`dyn Fn(..)`中提取签名，不支持的返回None
+    pub(crate) fn from_type(
+        ty: &clean::Type,
+        cache: &Cache,
+        full_name_map: &FullNameMap,
+    ) -> Option<Self> {
+        match ty {
+            clean::Type::BareFunction(bare_function) => {
+                if bare_function.abi != Abi::Rust || bare_function.decl.c_variadic {
+                    return None;
+                }
+                let inputs: Vec<clean::Type> = bare_function
+                    .decl
+                    .inputs
+                    .values
+                    .iter()
+                    .map(|argument| argument.type_.clone())
+                    .collect();
+                _signature_from_parts(
+                    &inputs,
+                    bare_function.decl.output.as_return(),
+                    cache,
+                    full_name_map,
+                )
+            }
+            clean::Type::ImplTrait(bounds) => _signature_from_bounds(bounds, cache, full_name_map),
+            clean::Type::DynTrait(poly_traits, _) => {
+                let bounds: Vec<GenericBound> = poly_traits
+                    .iter()
+                    .map(|poly_trait| {
+                        GenericBound::TraitBound(
+                            poly_trait.clone(),
+                            rustc_hir::TraitBoundModifier::None,
+                        )
+                    })
+                    .collect();
+                _signature_from_bounds(&bounds, cache, full_name_map)
+            }
+            _ => None,
+        }
+    }
+
+    /// 测试函数中参数的类型
+    pub(crate) fn _to_type_string(&self) -> String {
+        let mut res = format!("fn({})", self.inputs.join(", "));
+        if let Some(output) = &self.output {
+            res.push_str(" -> ");
+            res.push_str(output.as_sym().as_str());
+        }
+        res
+    }
+
+    /// 初始化回调的表达式，table_index是表在输入中的起始位置，同时作为回调的编号
+    /// output_helper是从字节构造返回值的辅助函数名，返回值需要output_length个字节
+    pub(crate) fn _to_initial_rhs(
+        &self,
+        table_index: usize,
+        output_helper: &str,
+        output_length: usize,
+    ) -> String {
+        let params = self
+            .inputs
+            .iter()
+            .enumerate()
+            .map(|(i, input)| format!("_arg{}: {}", i, input))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let closure = match &self.output {
+            Some(output) => format!(
+                "|{params}| -> {output} {{let _bytes = _next_callback_bytes({table_index}, {output_length}); {output_helper}(&_bytes, 0)}}",
+                params = params,
+                output = output.as_sym(),
+                table_index = table_index,
+                output_length = output_length,
+                output_helper = output_helper
+            ),
+            None => format!(
+                "|{params}| {{_next_callback_bytes({table_index}, 0);}}",
+                params = params,
+                table_index = table_index
+            ),
+        };
+        format!(
+            "{{_set_callback_table(data, {table_index}); {closure}}}",
+            table_index = table_index,
+            closure = closure
+        )
+    }
+}
+
+/// 把只作为回调使用的泛型参数去掉，参数类型改写成`impl Fn(..)`，这样函数就不再是泛型函数
+/// 包括`F: Fn(..)`、`where F: FnMut(..)`以及参数位置的`impl Fn(..)`
+pub(crate) fn _rewrite_callback_generics(api_fun: &mut ApiFunction) {
+    let mut removed_params = Vec::new();
+    for param in api_fun._generics.params.iter() {
+        let param_bounds = match &param.kind {
+            GenericParamDefKind::Type { bounds, .. } => bounds,
+            _ => continue,
+        };
+        let mut bounds = param_bounds.clone();
+        for predicate in api_fun._generics.where_predicates.iter() {
+            if let clean::WherePredicate::BoundPredicate {
+                ty: clean::Type::Generic(name),
+                bounds: predicate_bounds,
+                ..
+            } = predicate
+            {
+                if *name == param.name {
+                    bounds.extend(predicate_bounds.iter().cloned());
+                }
+            }
+        }
+        if !bounds.iter().any(_is_fn_bound) {
+            continue;
+        }
+        // 参数位置的impl Fn(..)，参数本身已经是ImplTrait了
+        if param.is_synthetic_type_param() {
+            removed_params.push(param.name);
+            continue;
+        }
+        let callback_type = clean::Type::ImplTrait(bounds);
+        let new_inputs: Vec<clean::Type> = api_fun
+            .inputs
+            .iter()
+            .map(|input| _replace_generic(input, param.name, &callback_type))
+            .collect();
+        // 在其他位置出现的泛型参数无法替换
+        let still_used = new_inputs.iter().any(|input| _mentions_generic(input, param.name))
+            || api_fun
+                .output
+                .as_ref()
+                .map_or(false, |output| _mentions_generic(output, param.name));
+        if still_used {
+            continue;
+        }
+        api_fun.inputs = new_inputs;
+        removed_params.push(param.name);
+    }
+    if removed_params.is_empty() {
+        return;
+    }
+    api_fun._generics.params.retain(|param| !removed_params.contains(&param.name));
+    api_fun._generics.where_predicates.retain(|predicate| match predicate {
+        clean::WherePredicate::BoundPredicate { ty: clean::Type::Generic(name), .. } => {
+            !removed_params.contains(name)
+        }
+        _ => true,
+    });
+}
+
+/// 是否是带有签名的Fn、FnMut、FnOnce约束
+fn _is_fn_bound(bound: &GenericBound) -> bool {
+    _fn_bound_signature(bound).is_some()
+}
+
+fn _fn_bound_signature(bound: &GenericBound) -> Option<(&[clean::Type], Option<&clean::Type>)> {
+    if let GenericBound::TraitBound(poly_trait, _) = bound {
+        let segment = poly_trait.trait_.segments.last()?;
+        let name = segment.name.as_str();
+        if name != "Fn" && name != "FnMut" && name != "FnOnce" {
+            return None;
+        }
+        if let GenericArgs::Parenthesized { inputs, output } = &segment.args {
+            return Some((inputs, output.as_deref()));
+        }
+    }
+    None
+}
+
+fn _signature_from_bounds(
+    bounds: &[GenericBound],
+    cache: &Cache,
+    full_name_map: &FullNameMap,
+) -> Option<CallbackSignature> {
+    let mut signature = None;
+    for bound in bounds {
+        match bound {
+            GenericBound::Outlives(..) => {}
+            GenericBound::TraitBound(poly_trait, _) => {
+                if let Some((inputs, output)) = _fn_bound_signature(bound) {
+                    if signature.is_some() {
+                        return None;
+                    }
+                    signature = _signature_from_parts(inputs, output, cache, full_name_map);
+                    if signature.is_none() {
+                        return None;
+                    }
+                    continue;
+                }
+                let name = poly_trait.trait_.segments.last()?.name;
+                if !CALLBACK_MARKER_TRAITS.contains(&name.as_str()) {
+                    return None;
+                }
+            }
+        }
+    }
+    signature
+}
+
+fn _signature_from_parts(
+    inputs: &[clean::Type],
+    output: Option<&clean::Type>,
+    cache: &Cache,
+    full_name_map: &FullNameMap,
+) -> Option<CallbackSignature> {
+    let mut input_strings = Vec::new();
+    for input in inputs {
+        input_strings.push(_callback_arg_type(input, cache, full_name_map)?);
+    }
+    let output = match output {
+        None => None,
+        Some(clean::Type::Tuple(types)) if types.is_empty() => None,
+        Some(clean::Type::Primitive(primitive)) if _is_supported_output(primitive) => {
+            Some(primitive.clone())
+        }
+        Some(_) => return None,
+    };
+    Some(CallbackSignature { inputs: input_strings, output })
+}
+
+fn _is_supported_output(primitive: &PrimitiveType) -> bool {
+    match primitive {
+        PrimitiveType::I8
+        | PrimitiveType::U8
+        | PrimitiveType::I16
+        | PrimitiveType::U16
+        | PrimitiveType::I32
+        | PrimitiveType::U32
+        | PrimitiveType::I64
+        | PrimitiveType::U64
+        | PrimitiveType::I128
+        | PrimitiveType::U128
+        | PrimitiveType::Isize
+        | PrimitiveType::Usize
+        | PrimitiveType::F32
+        | PrimitiveType::F64
+        | PrimitiveType::Bool
+        | PrimitiveType::Char => true,
+        _ => false,
+    }
+}
+
+/// 闭包参数的类型写法，不写生命周期，使用完整路径
+fn _callback_arg_type(
+    ty: &clean::Type,
+    cache: &Cache,
+    full_name_map: &FullNameMap,
+) -> Option<String> {
+    match ty {
+        clean::Type::Primitive(primitive) => Some(primitive.as_sym().to_string()),
+        clean::Type::BorrowedRef { mutability, type_, .. } => {
+            let mut_str = if mutability.is_mut() { "mut " } else { "" };
+            Some(format!("&{}{}", mut_str, _callback_arg_type(type_, cache, full_name_map)?))
+        }
+        clean::Type::RawPointer(mutability, type_) => {
+            let mut_str = if mutability.is_mut() { "mut" } else { "const" };
+            Some(format!("*{} {}", mut_str, _callback_arg_type(type_, cache, full_name_map)?))
+        }
+        clean::Type::Slice(type_) => {
+            Some(format!("[{}]", _callback_arg_type(type_, cache, full_name_map)?))
+        }
+        clean::Type::Array(type_, length) => {
+            Some(format!("[{}; {}]", _callback_arg_type(type_, cache, full_name_map)?, length))
+        }
+        clean::Type::Tuple(types) => {
+            let mut inner_strings = Vec::new();
+            for inner_type in types {
+                inner_strings.push(_callback_arg_type(inner_type, cache, full_name_map)?);
+            }
+            if inner_strings.len() == 1 {
+                Some(format!("({},)", inner_strings[0]))
+            } else {
+                Some(format!("({})", inner_strings.join(", ")))
+            }
+        }
+        clean::Type::Path { path } => {
+            let last_segment = path.segments.last()?;
+            let name = match full_name_map._get_full_name(path.def_id()) {
+                Some(full_name) => full_name.clone(),
+                None => path
+                    .segments
+                    .iter()
+                    .map(|segment| segment.name.to_string())
+                    .collect::<Vec<_>>()
+                    .join("::"),
+            };
+            let mut type_args = Vec::new();
+            match &last_segment.args {
+                GenericArgs::AngleBracketed { args, bindings } => {
+                    if !bindings.is_empty() {
+                        return None;
+                    }
+                    for arg in args.iter() {
+                        match arg {
+                            GenericArg::Lifetime(..) => {}
+                            GenericArg::Type(inner_type) => type_args.push(_callback_arg_type(
+                                inner_type,
+                                cache,
+                                full_name_map,
+                            )?),
+                            GenericArg::Const(..) | GenericArg::Infer => return None,
+                        }
+                    }
+                }
+                GenericArgs::Parenthesized { .. } => return None,
+            }
+            if type_args.is_empty() {
+                Some(name)
+            } else {
+                Some(format!("{}<{}>", name, type_args.join(", ")))
+            }
+        }
+        _ => None,
+    }
+}
+
+/// 把参数类型中直接出现的（或者在引用后面的）泛型参数替换成回调类型
+fn _replace_generic(ty: &clean::Type, generic: Symbol, callback_type: &clean::Type) -> clean::Type {
+    match ty {
+        clean::Type::Generic(name) if *name == generic => callback_type.clone(),
+        clean::Type::BorrowedRef { lifetime, mutability, type_ } => clean::Type::BorrowedRef {
+            lifetime: lifetime.clone(),
+            mutability: *mutability,
+            type_: Box::new(_replace_generic(type_, generic, callback_type)),
+        },
+        _ => ty.clone(),
+    }
+}
+
+fn _mentions_generic(ty: &clean::Type, generic: Symbol) -> bool {
+    match ty {
+        clean::Type::Generic(name) => *name == generic,
+        clean::Type::BorrowedRef { type_, .. }
+        | clean::Type::RawPointer(_, type_)
+        | clean::Type::Slice(type_)
+        | clean::Type::Array(type_, _) => _mentions_generic(type_, generic),
+        clean::Type::Tuple(types) => types.iter().any(|inner| _mentions_generic(inner, generic)),
+        clean::Type::Path { path } => path.segments.iter().any(|segment| match &segment.args {
+            GenericArgs::AngleBracketed { args, .. } => args.iter().any(|arg| match arg {
+                GenericArg::Type(inner) => _mentions_generic(inner, generic),
+                _ => false,
+            }),
+            GenericArgs::Parenthesized { inputs, output } => {
+                inputs.iter().any(|inner| _mentions_generic(inner, generic))
+                    || output.as_ref().map_or(false, |inner| _mentions_generic(inner, generic))
+            }
+        }),
+        // QPath、impl Trait、dyn Trait里面可能用到，保守地认为用到了
+        clean::Type::QPath(..) | clean::Type::ImplTrait(..) | clean::Type::DynTrait(..) => true,
+        clean::Type::Primitive(..) | clean::Type::BareFunction(..) | clean::Type::Infer => false,
+    }
+}
+
+lazy_static! {
+    static ref CALLBACK_HELPERS: String = _generate_callback_helpers();
+}
+
+/// 测试文件中回调用到的辅助函数
+pub(crate) fn _callback_helpers() -> &'static str {
+    CALLBACK_HELPERS.as_str()
+}
+
+/// 表的长度用CALLBACK_TABLE_LENGTH生成，两边不会不一致
+fn _generate_callback_helpers() -> String {
+    format!(
+        "thread_local! {{
+    static _CALLBACK_TABLES: std::cell::RefCell<std::collections::HashMap<usize, (Vec<u8>, usize)>> =
+        std::cell::RefCell::new(std::collections::HashMap::new());
+}}
+
+fn _set_callback_table(data:&[u8], index:usize) {{
+    let table = data[index..index + {}].to_vec();
+    _CALLBACK_TABLES.with(|tables| {{
+        tables.borrow_mut().insert(index, (table, 0));
+    }});
+}}
+
+fn _next_callback_bytes(index:usize, length:usize)->Vec<u8> {{
+    _CALLBACK_TABLES.with(|tables| {{
+        let mut tables = tables.borrow_mut();
+        let (table, count) = tables.get_mut(&index).unwrap();
+        eprintln!(\"callback at input offset {{}} invoked, call #{{}}\", index, count);
+        let mut bytes = Vec::with_capacity(length);
+        for i in 0..length {{
+            bytes.push(table[(*count * length + i) % table.len()]);
+        }}
+        *count += 1;
+        bytes
+    }})
+}}\n",
+        CALLBACK_TABLE_LENGTH
+    )
+}
@@ -0,0 +1,126 @@
+//! 在写入文件之前，对生成的测试文件进行类型检查
+//! 用子进程rustc对待测库的rlib做检查（只到metadata，不做代码生成）
+//! 没有通过检查的序列会被丢弃，并且记录对应的诊断信息
+
+use std::fs;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// 通过环境变量指定rustc和依赖目录，和cargo afl build使用的要保持一致
+static RUSTC_ENV: &'static str = "FRIES_RUSTC";
+static DEPS_DIR_ENV: &'static str = "FRIES_DEPS_DIR";
+/// 每个诊断最多保留的字符数，防止日志太大
+static MAX_DIAGNOSTIC_LENGTH: usize = 4096;
+
+/// 一次检查的结果
+#[derive(Debug, Clone)]
+pub(crate) enum CheckResult {
+    /// 通过类型检查
+    Pass,
+    /// 没有通过，附带rustc的诊断信息
+    Fail(String),
+    /// 无法检查（找不到rustc或者依赖目录），此时不丢弃序列
+    Skipped(String),
+}
+
+#[derive(Debug)]
+pub(crate) struct CompileChecker {
+    rustc: String,
+    deps_dir: PathBuf,
+    work_dir: Option<tempfile::TempDir>,
+    /// 依赖目录不存在等情况下，整个检查被关闭
+    disabled_reason: Option<String>,
+}
+
+impl CompileChecker {
+    pub(crate) fn new(crate_name: &str) -> Self {
+        let rustc = std::env::var(RUSTC_ENV).unwrap_or("rustc".to_string());
+        let mut disabled_reason = None;
+        //依赖目录和待测库的构建方式有关，没有合理的默认值
+        let deps_dir = match std::env::var(DEPS_DIR_ENV) {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => {
+                disabled_reason = Some(format!(
+                    "{} is not set, it should point to the target/debug/deps directory of {}",
+                    DEPS_DIR_ENV, crate_name
+                ));
+                PathBuf::new()
+            }
+        };
+        if disabled_reason.is_none() && !deps_dir.is_dir() {
+            disabled_reason =
+                Some(format!("dependency directory {} does not exist", deps_dir.display()));
+        }
+        let work_dir = match tempfile::Builder::new().prefix("fries-check").tempdir() {
+            Ok(dir) => Some(dir),
+            Err(e) => {
+                disabled_reason = Some(format!("can not create temp dir: {}", e));
+                None
+            }
+        };
+        if let Some(ref reason) = disabled_reason {
+            println!("类型检查被关闭: {}", reason);
+        }
+        CompileChecker { rustc, deps_dir, work_dir, disabled_reason }
+    }
+
+    /// 对一个测试文件的内容做类型检查
+    pub(crate) fn check(&self, content: &str, index: usize) -> CheckResult {
+        if let Some(ref reason) = self.disabled_reason {
+            return CheckResult::Skipped(reason.clone());
+        }
+        let work_dir = self.work_dir.as_ref().unwrap().path();
+        let src_path = work_dir.join(format!("check{:0>5}.rs", index));
+        let out_path = work_dir.join(format!("check{:0>5}.rmeta", index));
+        if let Err(e) = fs::write(&src_path, content) {
+            return CheckResult::Skipped(format!("can not write {}: {}", src_path.display(), e));
+        }
+
+        let output = Command::new(&self.rustc)
+            .arg("--edition=2018")
+            .arg("--crate-type=bin")
+            .arg("--emit=metadata")
+            .arg("--error-format=short")
+            .arg("--cap-lints=allow")
+            //测试文件自己`extern crate`待测库、afl和serde_json，`dependency=`的目录不会用来找这些crate
+            .arg("-L")
+            .arg(&self.deps_dir)
+            .arg("-o")
+            .arg(&out_path)
+            .arg(&src_path)
+            .output();
+        let _ = fs::remove_file(&src_path);
+        let _ = fs::remove_file(&out_path);
+
+        match output {
+            Ok(output) if output.status.success() => CheckResult::Pass,
+            Ok(output) => {
+                let mut diagnostic = String::from_utf8_lossy(&output.stderr).to_string();
+                if diagnostic.len() > MAX_DIAGNOSTIC_LENGTH {
+                    let mut end = MAX_DIAGNOSTIC_LENGTH;
+                    while !diagnostic.is_char_boundary(end) {
+                        end -= 1;
+                    }
+                    diagnostic.truncate(end);
+                    diagnostic.push_str("\n...");
+                }
+                CheckResult::Fail(diagnostic)
+            }
+            Err(e) => CheckResult::Skipped(format!("can not run {}: {}", self.rustc, e)),
+        }
+    }
+}
+
+/// 把每个被丢弃序列的诊断信息写入日志文件
+pub(crate) fn write_diagnostics(path: &PathBuf, rejected: &Vec<(String, String)>) {
+    let content = rejected
+        .iter()
+        .enumerate()
+        .map(|(i, (sequence, diagnostic))| {
+            format!("rejected_{}:\n{}\n--- diagnostic ---\n{}\n\n", i, sequence, diagnostic)
+        })
+        .collect::<String>();
+    if let Err(e) = fs::write(path, content) {
+        println!("无法写入类型检查日志{}: {}", path.display(), e);
+    }
+}
@@ -0,0 +1,340 @@
+//! 为公开的结构体和枚举合成构造函数
+//! 字段全部公开的结构体可以直接用字段构造；枚举中字段都能直接fuzz的variant合成一个构造函数，先用一个u8选择variant，
+//! 再填充这个variant的字段；需要其他API产生字段的variant单独是一个构造函数，参数只有这个variant的字段
+//! 合成的构造函数在测试文件里是一个宏，比如`_construct_url_Host!(..)`、`_construct_url_Host_Domain!(..)`，
+//! 在API图中它就是一个普通的ApiFunction，参数是各个字段，返回值是这个类型
+
+use crate::clean;
+use crate::formats::cache::Cache;
+use crate::fuzz_targets_gen::api_function::{ApiAsyncness, ApiFunction, ApiUnsafety};
+use crate::fuzz_targets_gen::api_util;
+use crate::fuzz_targets_gen::call_type::CallType;
+use crate::fuzz_targets_gen::impl_util::FullNameMap;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_hir::def::{CtorKind, DefKind, Res};
+use rustc_hir::def_id::DefId;
+use rustc_middle::ty::{TyCtxt, Visibility};
+use rustc_span::Symbol;
+use serde::{Deserialize, Serialize};
+
+/// 选择器是一个u8，最多用到256个variant
+static MAX_VARIANT_NUM: usize = 256;
+/// 字段太多的类型不构造，参数太多很难被覆盖
+static MAX_FIELD_NUM: usize = 16;
+/// 宏名字的前缀
+static CONSTRUCTOR_PREFIX: &'static str = "_construct_";
+
+/// 一组字段的写法
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) enum FieldShape {
+    /// `T`
+    Unit,
+    /// `T(a, b)`
+    Tuple,
+    /// `T { x: a, y: b }`
+    Named(Vec<String>),
+}
+
+/// 结构体本身，或者枚举的一个variant
+#[derive(Debug, Clone)]
+pub(crate) struct ConstructorVariant {
+    /// 构造时使用的路径，结构体是`krate::m::T`，variant是`krate::m::E::V`
+    pub(crate) path: String,
+    pub(crate) shape: FieldShape,
+    pub(crate) fields: Vec<clean::Type>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) struct Constructor {
+    /// 类型的完整路径
+    pub(crate) type_path: String,
+    /// 构造出来的类型
+    pub(crate) type_: clean::Type,
+    pub(crate) is_enum: bool,
+    pub(crate) variants: Vec<ConstructorVariant>,
+}
+
+impl Constructor {
+    /// 从结构体或者枚举的定义中提取构造方式，不能直接构造的返回None
+    /// 暂时不支持带泛型（包括生命周期）的类型
+    pub(crate) fn from_item(
+        item: &clean::Item,
+        type_path: String,
+        tcx: TyCtxt<'_>,
+    ) -> Option<Constructor> {
+        if item.visibility(tcx) != Some(Visibility::Public) {
+            return None;
+        }
+        let did = item.item_id.as_def_id()?;
+        match *item.kind {
+            clean::StructItem(ref struct_) => {
+                if !struct_.generics.params.is_empty() || item.is_non_exhaustive() {
+                    return None;
+                }
+                let variant = _variant_from_fields(
+                    type_path.clone(),
+                    struct_.ctor_kind,
+                    &struct_.fields,
+                    tcx,
+                )?;
+                Some(Constructor {
+                    type_: _path_type(&type_path, DefKind::Struct, did),
+                    type_path,
+                    is_enum: false,
+                    variants: vec![variant],
+                })
+            }
+            clean::EnumItem(ref enum_) => {
+                if !enum_.generics.params.is_empty() {
+                    return None;
+                }
+                let mut variants = Vec::new();
+                for variant_item in enum_.variants() {
+                    if variant_item.is_non_exhaustive() || variants.len() >= MAX_VARIANT_NUM {
+                        continue;
+                    }
+                    let path = match variant_item.name {
+                        Some(name) => format!("{}::{}", type_path, name),
+                        None => continue,
+                    };
+                    let variant = match *variant_item.kind {
+                        clean::VariantItem(clean::Variant::CLike(..)) => Some(ConstructorVariant {
+                            path,
+                            shape: FieldShape::Unit,
+                            fields: vec![],
+                        }),
+                        clean::VariantItem(clean::Variant::Tuple(ref fields)) => {
+                            _variant_from_fields(path, Some(CtorKind::Fn), fields, tcx)
+                        }
+                        clean::VariantItem(clean::Variant::Struct(ref variant_struct)) => {
+                            _variant_from_fields(
+                                path,
+                                variant_struct.ctor_kind,
+                                &variant_struct.fields,
+                                tcx,
+                            )
+                        }
+                        _ => None,
+                    };
+                    if let Some(variant) = variant {
+                        variants.push(variant);
+                    }
+                }
+                if variants.is_empty() {
+                    return None;
+                }
+                Some(Constructor {
+                    type_: _path_type(&type_path, DefKind::Enum, did),
+                    type_path,
+                    is_enum: true,
+                    variants,
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// 只保留所有字段都能产生的variant
+    pub(crate) fn retain_variants<F>(&mut self, mut producible: F)
+    where
+        F: FnMut(&clean::Type) -> bool,
+    {
+        self.variants.retain(|variant| variant.fields.iter().all(|field| producible(field)));
+    }
+
+    /// 字段都能直接fuzz的variant留在一起，由输入的u8选择；
+    /// 其余的variant各自拆成一个构造函数，这样序列只需要产生用到的那个variant的字段
+    pub(crate) fn split_variants<F>(self, mut fuzzable: F) -> Vec<Constructor>
+    where
+        F: FnMut(&clean::Type) -> bool,
+    {
+        let Constructor { type_path, type_, is_enum, variants } = self;
+        let (selected, separate): (Vec<_>, Vec<_>) = variants
+            .into_iter()
+            .partition(|variant| !is_enum || variant.fields.iter().all(|field| fuzzable(field)));
+        let mut constructors = Vec::new();
+        if !selected.is_empty() {
+            constructors.push(Constructor {
+                type_path: type_path.clone(),
+                type_: type_.clone(),
+                is_enum,
+                variants: selected,
+            });
+        }
+        for variant in separate {
+            constructors.push(Constructor {
+                type_path: type_path.clone(),
+                type_: type_.clone(),
+                is_enum,
+                variants: vec![variant],
+            });
+        }
+        constructors
+    }
+
+    /// 是否需要一个u8选择variant
+    fn has_selector(&self) -> bool {
+        self.is_enum && self.variants.len() > 1
+    }
+
+    /// 宏的名字，不带`!`。结构体和带选择器的枚举是`_construct_krate_T`，单个variant是`_construct_krate_E_V`
+    pub(crate) fn macro_name(&self) -> String {
+        let path = if self.has_selector() { &self.type_path } else { &self.variants[0].path };
+        format!("{}{}", CONSTRUCTOR_PREFIX, path.replace("::", "_"))
+    }
+
+    /// 转换成API图中的函数。带选择器的枚举第一个参数是选择variant的u8，后面依次是各个variant的字段
+    pub(crate) fn to_api_function(&self) -> ApiFunction {
+        let mut inputs = Vec::new();
+        if self.has_selector() {
+            inputs.push(clean::Type::Primitive(clean::PrimitiveType::U8));
+        }
+        for variant in &self.variants {
+            inputs.extend(variant.fields.iter().cloned());
+        }
+        ApiFunction {
+            full_name: format!("{}!", self.macro_name()),
+            _generics: clean::Generics::default(),
+            generic_substitutions: FxHashMap::default(),
+            inputs,
+            output: Some(self.type_.clone()),
+            _trait_full_path: None,
+            _unsafe_tag: ApiUnsafety::Normal,
+            _async_tag: ApiAsyncness::NotAsync,
+            visibility: Visibility::Public,
+        }
+    }
+
+    /// 生成测试文件里的宏定义
+    pub(crate) fn to_macro_definition(&self) -> String {
+        let mut params = Vec::new();
+        if self.has_selector() {
+            params.push("$v:expr".to_string());
+        }
+        let field_num: usize = self.variants.iter().map(|variant| variant.fields.len()).sum();
+        for i in 0..field_num {
+            params.push(format!("$p{}:expr", i));
+        }
+
+        let mut body = String::new();
+        let mut next_param = 0;
+        if self.has_selector() {
+            body.push_str(
+                format!("        match ($v as usize) % {} {{\n", self.variants.len()).as_str(),
+            );
+            let variant_num = self.variants.len();
+            for (i, variant) in self.variants.iter().enumerate() {
+                let pattern = if i + 1 == variant_num { "_".to_string() } else { i.to_string() };
+                body.push_str(
+                    format!(
+                        "            {} => {},\n",
+                        pattern,
+                        _variant_expr(variant, &mut next_param)
+                    )
+                    .as_str(),
+                );
+            }
+            body.push_str("        }\n");
+        } else {
+            body.push_str(
+                format!("        {}\n", _variant_expr(&self.variants[0], &mut next_param)).as_str(),
+            );
+        }
+
+        format!(
+            "macro_rules! {} {{\n    ({}) => {{\n{}    }};\n}}\n",
+            self.macro_name(),
+            params.join(", "),
+            body
+        )
+    }
+}
+
+/// 判断一个字段的类型能不能产生：可以直接fuzz，或者可以由某个已有的API返回
+pub(crate) fn _is_producible_type(
+    ty: &clean::Type,
+    output_types: &Vec<clean::Type>,
+    cache: &Cache,
+    full_name_map: &FullNameMap,
+) -> bool {
+    if api_util::is_fuzzable_type(ty, cache, full_name_map, None) {
+        return true;
+    }
+    output_types.iter().any(|output_type| {
+        match api_util::_same_type(output_type, ty, true, cache, full_name_map) {
+            CallType::_NotCompatible => false,
+            _ => true,
+        }
+    })
+}
+
+fn _variant_from_fields(
+    path: String,
+    ctor_kind: Option<CtorKind>,
+    fields: &Vec<clean::Item>,
+    tcx: TyCtxt<'_>,
+) -> Option<ConstructorVariant> {
+    if fields.len() > MAX_FIELD_NUM {
+        return None;
+    }
+    let mut names = Vec::new();
+    let mut types = Vec::new();
+    for field in fields {
+        if field.is_stripped() {
+            return None;
+        }
+        // variant的字段继承枚举的可见性，返回None
+        match field.visibility(tcx) {
+            None | Some(Visibility::Public) => {}
+            _ => return None,
+        }
+        match *field.kind {
+            clean::StructFieldItem(ref ty) => types.push(ty.clone()),
+            _ => return None,
+        }
+        names.push(field.name.map(|name| name.to_string()).unwrap_or_default());
+    }
+    let shape = match ctor_kind {
+        None => FieldShape::Named(names),
+        Some(CtorKind::Fn) => FieldShape::Tuple,
+        Some(CtorKind::Const) => FieldShape::Unit,
+    };
+    Some(ConstructorVariant { path, shape, fields: types })
+}
+
+/// 构造variant的表达式，参数按顺序编号
+fn _variant_expr(variant: &ConstructorVariant, next_param: &mut usize) -> String {
+    let mut args = Vec::new();
+    for _ in &variant.fields {
+        args.push(format!("$p{}", *next_param));
+        *next_param += 1;
+    }
+    match variant.shape {
+        FieldShape::Unit => variant.path.clone(),
+        FieldShape::Tuple => format!("{}({})", variant.path, args.join(", ")),
+        FieldShape::Named(ref names) => {
+            let fields = names
+                .iter()
+                .zip(args.iter())
+                .map(|(name, arg)| format!("{}: {}", name, arg))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("{} {{ {} }}", variant.path, fields)
+        }
+    }
+}
+
+/// 类型本身，只有一段路径，并且没有泛型参数
+fn _path_type(type_path: &str, def_kind: DefKind, did: DefId) -> clean::Type {
+    let name = type_path.rsplit("::").next().unwrap_or(type_path);
+    let segment = clean::PathSegment {
+        name: Symbol::intern(name),
+        args: clean::GenericArgs::AngleBracketed {
+            args: Vec::new().into(),
+            bindings: Default::default(),
+        },
+    };
+    clean::Type::Path {
+        path: clean::Path { res: Res::Def(def_kind, did), segments: vec![segment].into() },
+    }
+}
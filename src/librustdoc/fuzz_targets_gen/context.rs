@@ -4,6 +4,7 @@
 
 use std::path::PathBuf;
 use std::rc::Rc;
+use std::time::Instant;
 
 use rustc_data_structures::fx::FxHashMap;
 //use rustc_data_structures::fx::{FxHashMap, FxHashSet};
@@ -18,10 +19,18 @@
 use crate::formats::cache::Cache;
 use crate::formats::item_type::ItemType;
 use crate::formats::FormatRenderer;
+use crate::fuzz_targets_gen::analysis_cache::{self, CacheKey};
+use crate::fuzz_targets_gen::api_diff;
 use crate::fuzz_targets_gen::api_graph::ApiGraph;
+use crate::fuzz_targets_gen::api_id::ApiId;
+use crate::fuzz_targets_gen::constructor::Constructor;
+use crate::fuzz_targets_gen::corpus;
+use crate::fuzz_targets_gen::explain;
 use crate::fuzz_targets_gen::extract_dep::extract_all_dependencies;
 use crate::fuzz_targets_gen::extract_info::ExtractInfo;
 use crate::fuzz_targets_gen::file_util::{self};
+use crate::fuzz_targets_gen::focus;
+use crate::fuzz_targets_gen::graph_export;
 use rustc_data_structures::fx::FxHashSet;
 
 lazy_static! {
@@ -147,6 +156,7 @@ fn init(
                 extract_info.print_dependencies_info(enable, experiment_root, tested_lib_name);
                 extract_info.print_order_info(enable, experiment_root, tested_lib_name);
                 extract_info.print_functions_info(enable, experiment_root, tested_lib_name);
+                extract_info.print_inverse_pair_info(enable, experiment_root, tested_lib_name);
             });
 
             println!(
@@ -154,7 +164,6 @@ fn init(
                 krate.name(tcx)
             );
         } else {
-            use std::time::Instant;
             let start = Instant::now();
             // 解析tested lib
             let kname = krate.name(tcx).to_string();
@@ -171,25 +180,37 @@ fn init(
 
             let support_generic = false;
 
-            // 新建一个API依赖图
-            let mut api_graph = ApiGraph::new(&krate.name(tcx).to_string(), cx.cache());
-            let mut full_name_map = impl_util::FullNameMap::new();
-
-            // 下面的代码块把method和bare function解析进入api_graph
-            {
-                impl_util::extract_impls_from_cache(
-                    cx.cache(),
-                    tcx,
-                    &mut full_name_map,
-                    &mut api_graph,
-                );
-                let _ret =
-                    cx.clone().add_bare_functions_into_api_graph(tcx, &krate, &mut api_graph);
-            }
-
-            api_graph.filter_functions(support_generic);
+            // 有缓存的时候直接恢复分析好的API图
+            let cache_key = CacheKey::new(tcx, &kname, cx.cache().crate_version.as_ref());
+            let cached_graph = analysis_cache::load_api_graph(&cache_key, support_generic);
+            let mut api_graph = match &cached_graph {
+                Some(cached_graph) => cached_graph.to_api_graph(),
+                None => {
+                    // 新建一个API依赖图
+                    let mut api_graph = ApiGraph::new(&kname, cx.cache());
+                    let mut full_name_map = impl_util::FullNameMap::new();
+
+                    // 下面的代码块把method和bare function解析进入api_graph
+                    {
+                        impl_util::extract_impls_from_cache(
+                            cx.cache(),
+                            tcx,
+                            &mut full_name_map,
+                            &mut api_graph,
+                        );
+                        let _ret = cx.clone().add_bare_functions_into_api_graph(
+                            tcx,
+                            &krate,
+                            &mut api_graph,
+                        );
+                    }
 
-            api_graph.find_all_dependencies(support_generic);
+                    _analyse_api_graph(&mut api_graph, support_generic);
+                    analysis_cache::save_api_graph(&api_graph, &cache_key, support_generic);
+                    api_graph
+                }
+            };
+            _after_analysis(&mut api_graph);
 
             println!("total functions in crate : {:?}", api_graph.api_functions.len());
 
@@ -206,41 +227,14 @@ fn init(
             let max_len = 15;
 
             if fries {
-                println!(
-                    "Fries Start!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!"
-                );
-                api_graph.api_sequences.clear();
-                //let generation_strategy = _Bfs;
-                let generation_strategy = _UseRealWorld;
-                //let generation_strategy = _RandomWalk;
-                api_graph.generate_all_possoble_sequences(
-                    generation_strategy,
+                _fries_generate(
+                    &mut api_graph,
                     krate.name(tcx).as_str().replace("_", "-").as_str(),
                     max_num,
                     max_len,
                     support_generic,
+                    start,
                 );
-                // 计算经过的时间
-                let duration = start.elapsed();
-                println!("代码执行时间: {:?}", duration);
-
-                if file_util::can_write_to_file(
-                    &api_graph._crate_name.replace("_", "-"),
-                    //&"unicode-segmentation".to_owned(),
-                    generation_strategy,
-                ) {
-                    println!("I will write test case into files");
-                    //whether to use random strategy
-                    let file_helper = file_util::FileHelper::new(
-                        &api_graph,
-                        generation_strategy,
-                        max_num,
-                        max_len,
-                    );
-                    file_helper.write_files();
-                }
-
-                println!("Fries! Finish to parse tested crate and generate test file.");
             }
 
             if fudge {
@@ -440,12 +434,15 @@ fn add_bare_functions_into_api_graph_util(
                         let decl = func.decl.clone();
                         let _generics = func.generics.clone();
 
-                        let clean::FnDecl { inputs, output, .. } = decl;
+                        let fn_header = item.fn_header(tcx).unwrap();
+                        let api_unsafety =
+                            api_function::ApiUnsafety::_get_unsafety_from_fnheader(&fn_header);
+                        let api_asyncness =
+                            api_function::ApiAsyncness::_get_asyncness_from_fnheader(&fn_header);
+                        let output = api_asyncness._return_type(&decl);
+                        let clean::FnDecl { inputs, .. } = decl;
                         let inputs = api_util::_extract_input_types(&inputs);
                         let output = api_util::_extract_output_type(&output);
-                        let api_unsafety = api_function::ApiUnsafety::_get_unsafety_from_fnheader(
-                            &item.fn_header(tcx).unwrap(),
-                        );
                         let api_fun = api_function::ApiFunction {
                             full_name,
                             _generics,
@@ -454,9 +451,15 @@ fn add_bare_functions_into_api_graph_util(
                             output,
                             _trait_full_path: None,
                             _unsafe_tag: api_unsafety,
+                            _async_tag: api_asyncness,
                             visibility: item.visibility(tcx).unwrap().expect_local(),
                         };
 
+                        if let Some(def_id) = item.item_id.as_def_id() {
+                            api_graph
+                                .add_api_id(&api_fun.full_name, ApiId::from_def_id(tcx, def_id));
+                        }
+
                         //let output_type = api_fun.output.clone().unwrap();
                         //println!("{:?}", output_type);
                         //let full_name_map = &api_dependency_graph.full_name_map;
@@ -469,7 +472,78 @@ fn add_bare_functions_into_api_graph_util(
                     _ => {}
                 }
             }
+            //item是结构体或者枚举，记录下来，之后尝试合成构造函数
+            else if item_type == ItemType::Struct || item_type == ItemType::Enum {
+                let full_name = self.full_path(&item);
+                if let Some(constructor) = Constructor::from_item(&item, full_name, tcx) {
+                    api_graph.add_constructor_candidate(constructor);
+                }
+            }
         }
         Ok(())
     }
 }
+
+/// 抽取完API之后共用的分析流程：过滤、合成函数和依赖分析，结果可以缓存
+/// 从rustdoc JSON抽取API时也走这里
+pub(crate) fn _analyse_api_graph(api_graph: &mut ApiGraph<'_>, support_generic: bool) {
+    api_graph.filter_functions(support_generic);
+
+    api_graph.add_constructors();
+    api_graph.add_iterator_functions();
+    api_graph.intersect_with_other_version();
+
+    api_graph.find_all_dependencies(support_generic);
+}
+
+/// 分析完或者从缓存恢复之后：导出API快照、和旧版本比较，以及关注范围
+pub(crate) fn _after_analysis(api_graph: &mut ApiGraph<'_>) {
+    api_diff::export_snapshot(api_graph);
+    api_diff::diff_with_old_version(api_graph);
+    focus::apply_focus_filters(api_graph);
+}
+
+/// fries的生成流程：生成序列，导出依赖图，然后写出测试文件
+pub(crate) fn _fries_generate(
+    api_graph: &mut ApiGraph<'_>,
+    lib_name: &str,
+    max_num: usize,
+    max_len: usize,
+    support_generic: bool,
+    start: Instant,
+) {
+    use crate::fuzz_targets_gen::api_graph::GraphTraverseAlgorithm::*;
+
+    println!("Fries Start!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!!");
+    api_graph.api_sequences.clear();
+    api_graph.corpus_info = corpus::load_corpus_info(api_graph, lib_name);
+    //let generation_strategy = _Bfs;
+    let generation_strategy = _UseRealWorld;
+    //let generation_strategy = _RandomWalk;
+    api_graph.generate_all_possoble_sequences(
+        generation_strategy,
+        lib_name,
+        max_num,
+        max_len,
+        support_generic,
+    );
+    graph_export::export_graph(api_graph, support_generic);
+    explain::save_graph(api_graph);
+    // 计算经过的时间
+    let duration = start.elapsed();
+    println!("代码执行时间: {:?}", duration);
+
+    if file_util::can_write_to_file(
+        &api_graph._crate_name.replace("_", "-"),
+        //&"unicode-segmentation".to_owned(),
+        generation_strategy,
+    ) {
+        println!("I will write test case into files");
+        //whether to use random strategy
+        let file_helper =
+            file_util::FileHelper::new(api_graph, generation_strategy, max_num, max_len);
+        file_helper.write_files();
+    }
+
+    println!("Fries! Finish to parse tested crate and generate test file.");
+}
@@ -0,0 +1,384 @@
+//! 结构化的corpus文件：corpus_driver为每个target写一个，生成序列时按target的种类加权读入
+//! 种类是lib、bin、example、test和bench，每个API对和函数都带着自己的种类
+//! 权重由`FRIES_CORPUS_WEIGHTS`指定，比如`test=0.2,bench=0`，没写的种类用默认值：
+//! lib、bin、example是1，test和bench是0.5，因为测试代码经常故意走错误路径
+//! 以前从`cargo doc`整理出来的depinfo.txt、orderinfo.txt和funcinfo.txt只有lib，按lib的权重一起读入
+//! 每个API对和函数在调用点上记下自己的ApiId，读入时用它把名字对应到库模式的full_name，对应不到的名字写进`FRIES_CORPUS_REPORT`指定的文件，
+//! 默认是corpus信息目录下的unmatched.txt
+
+use crate::fuzz_targets_gen::api_graph::{self, ApiGraph};
+use crate::fuzz_targets_gen::api_id::{ApiId, ApiResolver};
+use itertools::Itertools;
+use rustc_data_structures::fx::FxHashMap;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+static CORPUS_DIR_ENV: &'static str = "FRIES_CORPUS_DIR";
+static CORPUS_WEIGHTS_ENV: &'static str = "FRIES_CORPUS_WEIGHTS";
+static UNMATCHED_REPORT_ENV: &'static str = "FRIES_CORPUS_REPORT";
+
+/// corpus来自哪种target
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum CorpusKind {
+    Lib,
+    Bin,
+    Test,
+    Example,
+    Bench,
+}
+
+impl CorpusKind {
+    /// cargo不会告诉rustc target的种类，按源文件所在的目录和编译参数判断
+    /// 源文件相对于package根目录（`CARGO_MANIFEST_DIR`）的第一级目录是examples、benches、tests的
+    /// 是example、bench和test，其他用`--test`编译的是单元测试；package本身放在叫tests的目录里不受影响
+    pub(crate) fn from_target(src_path: &str, test: bool, crate_types: &Vec<String>) -> Self {
+        let top_dir = _top_dir_in_package(Path::new(src_path));
+        let in_dir = |dir: &str| top_dir.as_ref().map_or(false, |top_dir| top_dir == dir);
+        if in_dir("examples") {
+            CorpusKind::Example
+        } else if in_dir("benches") {
+            CorpusKind::Bench
+        } else if test || in_dir("tests") {
+            CorpusKind::Test
+        } else if crate_types.iter().any(|ty| ty == "bin") {
+            CorpusKind::Bin
+        } else {
+            CorpusKind::Lib
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lib" => Some(CorpusKind::Lib),
+            "bin" => Some(CorpusKind::Bin),
+            "test" => Some(CorpusKind::Test),
+            "example" => Some(CorpusKind::Example),
+            "bench" => Some(CorpusKind::Bench),
+            _ => None,
+        }
+    }
+
+    fn default_weight(&self) -> f64 {
+        match self {
+            CorpusKind::Lib | CorpusKind::Bin | CorpusKind::Example => 1.0,
+            CorpusKind::Test | CorpusKind::Bench => 0.5,
+        }
+    }
+}
+
+/// 源文件相对于package根目录的第一级目录，不在package里（或者不知道package在哪）时返回None
+/// cargo传给rustc的路径可能是相对于workspace根目录的，先按当前目录补全
+fn _top_dir_in_package(src_path: &Path) -> Option<String> {
+    let manifest_dir = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").ok()?);
+    let src_path = if src_path.is_relative() {
+        std::env::current_dir().ok()?.join(src_path)
+    } else {
+        src_path.to_path_buf()
+    };
+    let relative = src_path.strip_prefix(&manifest_dir).ok()?;
+    let mut components = relative.components();
+    let top_dir = components.next()?;
+    //直接放在package根目录下的文件没有目录
+    components.next()?;
+    Some(top_dir.as_os_str().to_string_lossy().to_string())
+}
+
+/// 被分析的target
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CorpusTarget {
+    /// cargo的package名
+    pub(crate) package: String,
+    pub(crate) crate_name: String,
+    pub(crate) src_path: String,
+    pub(crate) crate_types: Vec<String>,
+    /// 用`--test`编译的，包括lib和bin的单元测试、tests/下的集成测试和bench
+    pub(crate) test: bool,
+    pub(crate) kind: CorpusKind,
+}
+
+/// 两个API以及出现的次数，名字去掉泛型参数之后相同、但ApiId不同的API分开记
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CorpusPair {
+    pub(crate) first: String,
+    pub(crate) second: String,
+    /// 调用点上的标识，旧的corpus文件没有
+    #[serde(default)]
+    pub(crate) first_id: Option<ApiId>,
+    #[serde(default)]
+    pub(crate) second_id: Option<ApiId>,
+    pub(crate) count: usize,
+    pub(crate) kind: CorpusKind,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CorpusFunction {
+    pub(crate) name: String,
+    #[serde(default)]
+    pub(crate) id: Option<ApiId>,
+    pub(crate) count: usize,
+    pub(crate) kind: CorpusKind,
+}
+
+/// 一个target的corpus文件，内容和ExtractInfo输出的几个文本文件一样，名字已经去掉了泛型参数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct CorpusFile {
+    pub(crate) tested_crate: String,
+    pub(crate) target: CorpusTarget,
+    pub(crate) sequences: Vec<Vec<String>>,
+    pub(crate) dependencies: Vec<CorpusPair>,
+    pub(crate) orders: Vec<CorpusPair>,
+    pub(crate) functions: Vec<CorpusFunction>,
+    pub(crate) inverse_pairs: Vec<CorpusPair>,
+}
+
+impl CorpusFile {
+    pub(crate) fn file_name(&self) -> String {
+        format!(
+            "{}.{}{}.json",
+            self.target.package,
+            self.target.crate_name,
+            if self.target.test { ".test" } else { "" }
+        )
+    }
+}
+
+pub(crate) fn corpus_dir(tested_lib: &str) -> PathBuf {
+    match std::env::var(CORPUS_DIR_ENV) {
+        Ok(dir) => PathBuf::from(dir),
+        Err(_) => PathBuf::from(api_graph::_corpus_info_path(tested_lib, "corpus")),
+    }
+}
+
+/// 每种target的权重
+fn _kind_weights() -> FxHashMap<CorpusKind, f64> {
+    let mut weights: FxHashMap<CorpusKind, f64> = [
+        CorpusKind::Lib,
+        CorpusKind::Bin,
+        CorpusKind::Test,
+        CorpusKind::Example,
+        CorpusKind::Bench,
+    ]
+    .into_iter()
+    .map(|kind| (kind, kind.default_weight()))
+    .collect();
+    if let Ok(value) = std::env::var(CORPUS_WEIGHTS_ENV) {
+        for item in value.split(',').filter(|item| !item.trim().is_empty()) {
+            let parsed = item.split_once('=').and_then(|(kind, weight)| {
+                Some((CorpusKind::from_name(kind.trim())?, weight.trim().parse::<f64>().ok()?))
+            });
+            match parsed {
+                Some((kind, weight)) if weight >= 0.0 => {
+                    weights.insert(kind, weight);
+                }
+                _ => println!("{}中的{}不合法，忽略", CORPUS_WEIGHTS_ENV, item),
+            }
+        }
+    }
+    weights
+}
+
+/// 记录在manifest里的实际使用的权重，比如`lib=1,bin=1,test=0.5,example=1,bench=0.5`
+pub(crate) fn manifest_weights() -> String {
+    _kind_weights()
+        .into_iter()
+        .sorted_by_key(|(kind, _)| *kind)
+        .map(|(kind, weight)| format!("{:?}={}", kind, weight).to_lowercase())
+        .join(",")
+}
+
+/// 生成时用到的corpus信息：func1 -> (func2 -> 次数)，func -> 次数，次数已经按种类加权
+/// 加权之后的次数是小数，不取整，一直保留到选择后继的时候，权重小的pair也不会被丢掉
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CorpusInfo {
+    pub(crate) depinfo: FxHashMap<String, FxHashMap<String, f64>>,
+    pub(crate) orderinfo: FxHashMap<String, FxHashMap<String, f64>>,
+    pub(crate) funcinfo: FxHashMap<String, f64>,
+}
+
+/// 名字在累加之前先对应到full_name，对不上的名字记下来写进报告
+struct WeightedCorpus {
+    resolver: ApiResolver,
+    depinfo: FxHashMap<(String, String), f64>,
+    orderinfo: FxHashMap<(String, String), f64>,
+    funcinfo: FxHashMap<String, f64>,
+    /// 对不上的名字和出现的次数
+    unmatched: FxHashMap<String, usize>,
+}
+
+impl WeightedCorpus {
+    fn new(api_graph: &ApiGraph<'_>) -> Self {
+        WeightedCorpus {
+            resolver: ApiResolver::new(api_graph),
+            depinfo: FxHashMap::default(),
+            orderinfo: FxHashMap::default(),
+            funcinfo: FxHashMap::default(),
+            unmatched: FxHashMap::default(),
+        }
+    }
+
+    fn resolve(&mut self, name: &str, count: usize, api_id: Option<&ApiId>) -> Option<String> {
+        let full_name = self.resolver.resolve(name, api_id);
+        if full_name.is_none() {
+            *self.unmatched.entry(name.to_string()).or_default() += count;
+        }
+        full_name
+    }
+
+    fn resolve_pair(&mut self, pair: &CorpusPair) -> Option<(String, String)> {
+        let first = self.resolve(&pair.first, pair.count, pair.first_id.as_ref());
+        let second = self.resolve(&pair.second, pair.count, pair.second_id.as_ref());
+        Some((first?, second?))
+    }
+
+    fn add_legacy_pairs(
+        &mut self,
+        pairs: &FxHashMap<String, FxHashMap<String, usize>>,
+        weight: f64,
+        order: bool,
+    ) {
+        for (first, inner_map) in pairs {
+            for (second, count) in inner_map {
+                let first = self.resolve(first, *count, None);
+                let second = self.resolve(second, *count, None);
+                if let (Some(first), Some(second)) = (first, second) {
+                    let key = (first, second);
+                    let weighted = if order { &mut self.orderinfo } else { &mut self.depinfo };
+                    *weighted.entry(key).or_default() += *count as f64 * weight;
+                }
+            }
+        }
+    }
+}
+
+/// 权重是0的（比如对应种类的权重设成了0）去掉
+fn _nest_pairs(
+    weighted: FxHashMap<(String, String), f64>,
+) -> FxHashMap<String, FxHashMap<String, f64>> {
+    let mut pairs: FxHashMap<String, FxHashMap<String, f64>> = FxHashMap::default();
+    for ((first, second), count) in weighted {
+        if count > 0.0 {
+            pairs.entry(first).or_default().insert(second, count);
+        }
+    }
+    pairs
+}
+
+pub(crate) fn _read_corpus_files(lib_name: &str) -> Vec<CorpusFile> {
+    let dir = corpus_dir(lib_name);
+    let paths = match fs::read_dir(&dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().map_or(false, |ext| ext == "json"))
+            .sorted()
+            .collect_vec(),
+        Err(_) => return Vec::new(),
+    };
+    let mut corpus_files = Vec::new();
+    for path in paths {
+        let corpus_file =
+            fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|content| {
+                serde_json::from_str::<CorpusFile>(&content).map_err(|e| e.to_string())
+            });
+        match corpus_file {
+            Ok(corpus_file)
+                if corpus_file.tested_crate.replace("-", "_") == lib_name.replace("-", "_") =>
+            {
+                corpus_files.push(corpus_file)
+            }
+            Ok(_) => {}
+            Err(e) => println!("无法读取corpus文件{}: {}", path.display(), e),
+        }
+    }
+    corpus_files
+}
+
+/// 对不上的名字按出现次数从多到少写成`名字  |  次数`
+fn _write_unmatched_report(lib_name: &str, unmatched: &FxHashMap<String, usize>) {
+    if unmatched.is_empty() {
+        return;
+    }
+    let path = std::env::var(UNMATCHED_REPORT_ENV)
+        .unwrap_or(api_graph::_corpus_info_path(lib_name, "unmatched.txt"));
+    let content = unmatched
+        .iter()
+        .sorted_by(|(name1, count1), (name2, count2)| count2.cmp(count1).then(name1.cmp(name2)))
+        .map(|(name, count)| format!("{}  |  {}\n", name, count))
+        .join("");
+    match fs::write(&path, content) {
+        Ok(()) => println!("corpus中有{}个名字对应不到API，写入了{}", unmatched.len(), path),
+        Err(e) => {
+            println!("corpus中有{}个名字对应不到API，无法写入{}: {}", unmatched.len(), path, e)
+        }
+    }
+}
+
+/// 读入整理过的文本文件和corpus目录下的所有corpus文件，把名字对应到api_graph中的full_name，按种类加权
+pub(crate) fn load_corpus_info(api_graph: &ApiGraph<'_>, lib_name: &str) -> CorpusInfo {
+    let weights = _kind_weights();
+    let mut weighted = WeightedCorpus::new(api_graph);
+
+    let lib_weight = weights[&CorpusKind::Lib];
+    weighted.add_legacy_pairs(
+        &api_graph::_read_pair_info(&api_graph::_corpus_info_path(lib_name, "depinfo.txt")),
+        lib_weight,
+        false,
+    );
+    weighted.add_legacy_pairs(
+        &api_graph::_read_pair_info(&api_graph::_corpus_info_path(lib_name, "orderinfo.txt")),
+        lib_weight,
+        true,
+    );
+    for (name, count) in
+        api_graph::_read_funcinfo(&api_graph::_corpus_info_path(lib_name, "funcinfo.txt"))
+    {
+        if let Some(full_name) = weighted.resolve(&name, count, None) {
+            *weighted.funcinfo.entry(full_name).or_default() += count as f64 * lib_weight;
+        }
+    }
+
+    let corpus_files = _read_corpus_files(lib_name);
+    let mut kind_counts: FxHashMap<CorpusKind, usize> = FxHashMap::default();
+    for corpus_file in &corpus_files {
+        *kind_counts.entry(corpus_file.target.kind).or_default() += 1;
+        for pair in &corpus_file.dependencies {
+            if let Some(key) = weighted.resolve_pair(pair) {
+                *weighted.depinfo.entry(key).or_default() +=
+                    pair.count as f64 * weights[&pair.kind];
+            }
+        }
+        for pair in &corpus_file.orders {
+            if let Some(key) = weighted.resolve_pair(pair) {
+                *weighted.orderinfo.entry(key).or_default() +=
+                    pair.count as f64 * weights[&pair.kind];
+            }
+        }
+        for function in &corpus_file.functions {
+            if let Some(full_name) =
+                weighted.resolve(&function.name, function.count, function.id.as_ref())
+            {
+                *weighted.funcinfo.entry(full_name).or_default() +=
+                    function.count as f64 * weights[&function.kind];
+            }
+        }
+    }
+    if !corpus_files.is_empty() {
+        println!(
+            "读入了{}个corpus文件：{}",
+            corpus_files.len(),
+            kind_counts
+                .iter()
+                .sorted()
+                .map(|(kind, count)| format!("{:?}={} (weight {})", kind, count, weights[kind]))
+                .join(", ")
+        );
+    }
+    _write_unmatched_report(lib_name, &weighted.unmatched);
+
+    CorpusInfo {
+        depinfo: _nest_pairs(weighted.depinfo),
+        orderinfo: _nest_pairs(weighted.orderinfo),
+        funcinfo: weighted.funcinfo.into_iter().filter(|(_, count)| *count > 0.0).collect(),
+    }
+}
@@ -0,0 +1,185 @@
+//! 把corpus的解析做成rustc_driver的回调，作为`RUSTC_WORKSPACE_WRAPPER`使用，不再借用`cargo doc`
+//! `FRIES_TESTED_LIB=url RUSTC_WORKSPACE_WRAPPER=<fuzz工具链里的rustdoc> cargo check --all-targets`
+//! cargo对工作区里的每个target调用`rustdoc <rustc的路径> <参数>`，这里照常编译，分析完之后
+//! 为每个target（lib、bin、example、test、bench）写出一个结构化的corpus文件`<目录>/<package>.<crate>[.test].json`
+//! 目录由`FRIES_CORPUS_DIR`指定，默认是待测库的corpus信息目录下的`corpus`，文件格式见corpus模块
+//! 每个API对和函数同时记下调用点上的ApiId，库模式读入时用它对应到自己的full_name
+//! 没有设置`FRIES_TESTED_LIB`时只编译不分析，build script和待测库自己也不分析
+
+use crate::fuzz_targets_gen::api_id::ApiId;
+use crate::fuzz_targets_gen::corpus::{
+    self, CorpusFile, CorpusFunction, CorpusKind, CorpusPair, CorpusTarget,
+};
+use crate::fuzz_targets_gen::extract_dep::extract_all_dependencies;
+use crate::fuzz_targets_gen::extract_info::{self, ExtractInfo};
+use itertools::Itertools;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_driver::Compilation;
+use rustc_hir::def_id::{DefId, LOCAL_CRATE};
+use rustc_interface::{interface, Queries};
+use rustc_middle::ty::TyCtxt;
+use std::fs;
+use std::path::Path;
+
+static TESTED_LIB_ENV: &'static str = "FRIES_TESTED_LIB";
+
+/// 去掉泛型参数之后可能重名，ApiId也相同的话次数加在一起，按名字排序
+fn _corpus_pairs(
+    tcx: TyCtxt<'_>,
+    info: &FxHashMap<(String, String), usize>,
+    def_ids: &FxHashMap<String, DefId>,
+    kind: CorpusKind,
+) -> Vec<CorpusPair> {
+    let mut pairs: FxHashMap<(String, String, Option<ApiId>, Option<ApiId>), usize> =
+        FxHashMap::default();
+    for ((first, second), count) in info {
+        let key = (
+            extract_info::_get_function_name(first.clone()),
+            extract_info::_get_function_name(second.clone()),
+            _api_id(tcx, def_ids, first),
+            _api_id(tcx, def_ids, second),
+        );
+        *pairs.entry(key).or_default() += count;
+    }
+    pairs
+        .into_iter()
+        .sorted()
+        .map(|((first, second, first_id, second_id), count)| CorpusPair {
+            first,
+            second,
+            first_id,
+            second_id,
+            count,
+            kind,
+        })
+        .collect()
+}
+
+/// 调用点上的名字对应的标识
+fn _api_id(tcx: TyCtxt<'_>, def_ids: &FxHashMap<String, DefId>, name: &str) -> Option<ApiId> {
+    def_ids.get(name).map(|def_id| ApiId::from_def_id(tcx, *def_id))
+}
+
+impl CorpusFile {
+    fn from_extract_info(
+        tcx: TyCtxt<'_>,
+        tested_crate: &str,
+        target: CorpusTarget,
+        extract_info: &ExtractInfo,
+    ) -> Self {
+        let kind = target.kind;
+        let def_ids = &extract_info.def_ids;
+        let mut functions: FxHashMap<(String, Option<ApiId>), usize> = FxHashMap::default();
+        for (name, count) in &extract_info.function_info {
+            let key = (extract_info::_get_function_name(name.clone()), _api_id(tcx, def_ids, name));
+            *functions.entry(key).or_default() += count;
+        }
+        CorpusFile {
+            tested_crate: tested_crate.to_string(),
+            target,
+            sequences: extract_info
+                .all_sequences
+                .iter()
+                .map(|seq| {
+                    seq.iter().map(|name| extract_info::_get_function_name(name.clone())).collect()
+                })
+                .collect(),
+            dependencies: _corpus_pairs(tcx, &extract_info.dependencies_info, def_ids, kind),
+            orders: _corpus_pairs(tcx, &extract_info.order_info, def_ids, kind),
+            functions: functions
+                .into_iter()
+                .sorted()
+                .map(|((name, id), count)| CorpusFunction { name, id, count, kind })
+                .collect(),
+            inverse_pairs: _corpus_pairs(tcx, &extract_info.inverse_pair_info, def_ids, kind),
+        }
+    }
+}
+
+fn _current_target(tcx: TyCtxt<'_>, crate_name: &str) -> CorpusTarget {
+    let src_path = tcx
+        .sess
+        .local_crate_source_file
+        .as_ref()
+        .map_or(String::new(), |path| path.display().to_string());
+    let crate_types: Vec<String> = tcx.sess.crate_types().iter().map(|ty| ty.to_string()).collect();
+    let test = tcx.sess.opts.test;
+    CorpusTarget {
+        package: std::env::var("CARGO_PKG_NAME").unwrap_or(crate_name.to_string()),
+        crate_name: crate_name.to_string(),
+        kind: CorpusKind::from_target(&src_path, test, &crate_types),
+        src_path,
+        crate_types,
+        test,
+    }
+}
+
+/// 分析当前target，写出corpus文件
+fn _extract_corpus(tcx: TyCtxt<'_>, tested_lib: &str) {
+    let crate_name = tcx.crate_name(LOCAL_CRATE).to_string();
+    let tested_crate_name = tested_lib.replace("-", "_");
+    //待测库自己可能用到非pub的API
+    if crate_name == "build_script_build" || crate_name == tested_crate_name {
+        return;
+    }
+    let target = _current_target(tcx, &crate_name);
+    println!(
+        "\nStart to parse dependencies.\nThe name of the parsed crate is {}, kind: {:?}.",
+        crate_name, target.kind
+    );
+    let corpus_file = tcx.sess.time("build_call_graph", || {
+        let all_dependencies = extract_all_dependencies(tcx);
+        let extract_info =
+            ExtractInfo::new(tcx, crate_name.clone(), tested_crate_name, &all_dependencies, true);
+        CorpusFile::from_extract_info(tcx, tested_lib, target, &extract_info)
+    });
+
+    let path = corpus::corpus_dir(tested_lib).join(corpus_file.file_name());
+    let content = serde_json::to_string_pretty(&corpus_file).unwrap();
+    match fs::create_dir_all(path.parent().unwrap()).and_then(|_| fs::write(&path, content)) {
+        Ok(()) => println!("corpus写入了{}", path.display()),
+        Err(e) => println!("无法写入corpus {}: {}", path.display(), e),
+    }
+}
+
+struct CorpusCallbacks {
+    tested_lib: Option<String>,
+}
+
+impl rustc_driver::Callbacks for CorpusCallbacks {
+    fn after_analysis<'tcx>(
+        &mut self,
+        compiler: &interface::Compiler,
+        queries: &'tcx Queries<'tcx>,
+    ) -> Compilation {
+        compiler.session().abort_if_errors();
+        if let Some(tested_lib) = &self.tested_lib {
+            queries.global_ctxt().unwrap().peek_mut().enter(|tcx| {
+                _extract_corpus(tcx, tested_lib);
+            });
+        }
+        Compilation::Continue
+    }
+}
+
+/// cargo调用`RUSTC_WORKSPACE_WRAPPER`时，第一个参数是rustc的路径
+/// 要求它是存在的文件，并且名字就是rustc（windows上是rustc.exe），`rustdoc rustc.rs`这样的调用不算
+pub(crate) fn is_rustc_wrapper_invocation(args: &[String]) -> bool {
+    let path = match args.get(1) {
+        Some(arg) => Path::new(arg),
+        None => return false,
+    };
+    let is_rustc = path.file_stem().map_or(false, |stem| stem == "rustc")
+        && path.extension().map_or(true, |extension| extension == "exe");
+    is_rustc && path.is_file()
+}
+
+/// args是rustc的路径和编译参数，rustc的路径相当于argv[0]
+pub(crate) fn corpus_driver_main(args: &[String]) -> i32 {
+    rustc_driver::install_ice_hook();
+    rustc_driver::init_rustc_env_logger();
+    let mut callbacks = CorpusCallbacks { tested_lib: std::env::var(TESTED_LIB_ENV).ok() };
+    rustc_driver::catch_with_exit_code(|| {
+        rustc_driver::RunCompiler::new(args, &mut callbacks).run()
+    })
+}
@@ -0,0 +1,115 @@
+//! 对待测库的两个版本做差分测试
+//! 1. 先在一个版本上运行，设置`FRIES_EXPORT_API`导出API快照（见api_diff）
+//! 2. 在另一个版本上运行，设置`FRIES_DIFF_WITH`为上一步的快照，只保留路径和签名都相同的API
+//! 3. 生成的测试里同一个序列跑两遍，另一个版本在测试crate里的名字由`FRIES_DIFF_CRATE`指定，
+//!    默认是`<crate>_old`，需要在测试crate的依赖里重命名（本地registry或者path都可以）
+//! 每个实现了Debug的返回值都会被记录下来，两个版本记录的结果不同，或者只有一个版本panic，
+//! 都作为分歧单独报告；两个版本都panic的还是当作普通的panic
+//! afl的`fuzz!`会装一个遇到panic就abort的hook，每个版本运行时临时换掉它，只有一个版本panic时才能比较
+
+use crate::clean;
+use crate::fuzz_targets_gen::api_diff::ApiSnapshot;
+use crate::fuzz_targets_gen::api_graph::ApiGraph;
+use crate::fuzz_targets_gen::impl_util::StdTrait;
+use rustc_data_structures::fx::FxHashSet;
+
+static DIFF_WITH_ENV: &'static str = "FRIES_DIFF_WITH";
+static DIFF_CRATE_ENV: &'static str = "FRIES_DIFF_CRATE";
+/// 两个版本行为不一致时输出的前缀，用来和其他crash区分
+static DIVERGENCE_PREFIX: &'static str = "FRIES_VERSION_DIVERGENCE";
+/// 测试文件中放另一个版本测试函数的mod
+pub(crate) static OTHER_VERSION_MOD: &'static str = "_other_version";
+
+/// 是否进行差分测试
+pub(crate) fn is_differential_mode() -> bool {
+    std::env::var(DIFF_WITH_ENV).is_ok()
+}
+
+/// 另一个版本在测试crate里的名字
+pub(crate) fn other_version_crate_name(crate_name: &str) -> String {
+    std::env::var(DIFF_CRATE_ENV).unwrap_or(format!("{}_old", crate_name))
+}
+
+/// 另一个版本的API签名，没有打开差分测试时返回None
+pub(crate) fn other_version_signatures() -> Option<FxHashSet<String>> {
+    let path = std::env::var(DIFF_WITH_ENV).ok()?;
+    Some(ApiSnapshot::read(&path).map(|snapshot| snapshot.signatures()).unwrap_or_default())
+}
+
+/// 这个类型的值能不能通过Debug记录下来
+/// 只考虑基本类型，以及实现了Debug并且不带泛型参数的类型
+pub(crate) fn is_observable(api_graph: &ApiGraph<'_>, ty: &clean::Type) -> bool {
+    match ty {
+        clean::Type::Primitive(_) => true,
+        clean::Type::BorrowedRef { type_, .. } => is_observable(api_graph, type_),
+        clean::Type::Path { path } => {
+            let has_generic_args = path.segments.iter().any(|segment| match &segment.args {
+                clean::GenericArgs::AngleBracketed { args, bindings } => {
+                    !args.is_empty() || !bindings.is_empty()
+                }
+                clean::GenericArgs::Parenthesized { .. } => true,
+            });
+            !has_generic_args
+                && ty
+                    .def_id(api_graph.cache)
+                    .map_or(false, |def_id| api_graph.has_std_trait(def_id, StdTrait::Debug))
+        }
+        _ => false,
+    }
+}
+
+/// 记录一个值
+pub(crate) fn _observe_statement(value: &String) -> String {
+    format!("_observe_value(format!(\"{{:?}}\", {}));", value)
+}
+
+/// 把当前版本的代码改成调用另一个版本
+pub(crate) fn _to_other_version(code: &str, crate_name: &str) -> String {
+    use regex::Regex;
+    let re = Regex::new(format!(r"\b{}::", regex::escape(crate_name)).as_str()).unwrap();
+    re.replace_all(code, format!("{}::", other_version_crate_name(crate_name)).as_str()).to_string()
+}
+
+/// 测试文件中记录和比较两个版本用到的辅助函数
+pub(crate) fn _differential_helper_functions() -> String {
+    format!(
+        "thread_local! {{
+    static _OBSERVATIONS: std::cell::RefCell<Vec<String>> = std::cell::RefCell::new(Vec::new());
+}}
+
+fn _observe_value(observation: String) {{
+    _OBSERVATIONS.with(|observations| observations.borrow_mut().push(observation));
+}}
+
+fn _run_version<F: FnOnce()>(run: F) -> Result<Vec<String>, Box<dyn std::any::Any + Send>> {{
+    _OBSERVATIONS.with(|observations| observations.borrow_mut().clear());
+    // fuzz!装的hook遇到panic直接abort，运行期间换成只打印的hook，比较之前换回来
+    let hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(|info| eprintln!(\"{{}}\", info)));
+    let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(run));
+    std::panic::set_hook(hook);
+    let observations = _OBSERVATIONS.with(|observations| observations.take());
+    result.map(|_| observations)
+}}
+
+fn _compare_versions(
+    current: Result<Vec<String>, Box<dyn std::any::Any + Send>>,
+    other: Result<Vec<String>, Box<dyn std::any::Any + Send>>,
+) {{
+    let divergence = match (current, other) {{
+        (Ok(current), Ok(other)) => {{
+            if current == other {{
+                return;
+            }}
+            format!(\"observations differ\\ncurrent: {{:?}}\\nother: {{:?}}\", current, other)
+        }}
+        (Err(_), Err(payload)) => std::panic::resume_unwind(payload),
+        (Ok(current), Err(_)) => format!(\"only the other version panicked\\ncurrent: {{:?}}\", current),
+        (Err(_), Ok(other)) => format!(\"only the current version panicked\\nother: {{:?}}\", other),
+    }};
+    eprintln!(\"{prefix}: {{}}\", divergence);
+    std::process::abort();
+}}\n",
+        prefix = DIVERGENCE_PREFIX
+    )
+}
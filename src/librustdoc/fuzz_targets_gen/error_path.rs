@@ -0,0 +1,96 @@
+//! 探索错误路径
+//! 默认情况下，返回Err或者None的调用会直接`process::exit(0)`，错误值本身从来不会被使用
+//! 打开`FRIES_EXPLORE_ERROR_PATH`之后：
+//! 1. Ok/Some继续执行后面的序列，None直接return
+//! 2. Err的值交给图中以错误类型为参数的API，以及它实现的Debug、Display和Error，然后return；
+//!    作为参数时才解开的Result也一样，只是类型推不出来（比如经过了解引用）的时候不使用错误值
+//! 差分测试的时候同一个进程里还要跑另一个版本，所以Err和None也是return，但不使用错误值
+
+use crate::clean;
+use crate::fuzz_targets_gen::api_graph::ApiGraph;
+use crate::fuzz_targets_gen::api_util;
+use crate::fuzz_targets_gen::call_type::CallType;
+use crate::fuzz_targets_gen::differential;
+use crate::fuzz_targets_gen::impl_util::StdTrait;
+use crate::fuzz_targets_gen::iterator;
+
+/// 设置了这个环境变量（并且不是0）的时候探索错误路径
+static EXPLORE_ERROR_PATH_ENV: &'static str = "FRIES_EXPLORE_ERROR_PATH";
+/// 每个错误值最多交给几个借用它的API，再加上最多一个会move它的API
+static MAX_ERROR_CONSUMERS: usize = 4;
+
+/// 是否探索错误路径
+pub(crate) fn is_error_path_mode() -> bool {
+    match std::env::var(EXPLORE_ERROR_PATH_ENV) {
+        Ok(value) => value != "0" && !value.is_empty(),
+        Err(_) => false,
+    }
+}
+
+/// Err和None的时候是从测试函数返回，而不是退出进程
+pub(crate) fn is_return_on_error() -> bool {
+    is_error_path_mode() || differential::is_differential_mode()
+}
+
+/// Err分支里使用错误值的语句，以及错误值是否需要绑定成mut
+pub(crate) fn _error_consumer_statements(
+    api_graph: &ApiGraph<'_>,
+    err_type: &clean::Type,
+    err_name: &String,
+) -> (Vec<String>, bool) {
+    let cache = api_graph.cache;
+    let full_name_map = &api_graph.full_name_map;
+    let mut statements = Vec::new();
+    if let Some(def_id) = err_type.def_id(cache) {
+        if api_graph.has_std_trait(def_id, StdTrait::Debug) {
+            statements.push(format!("let _ = format!(\"{{:?}}\", {});", err_name));
+        }
+        if api_graph.has_std_trait(def_id, StdTrait::Display) {
+            statements.push(format!("let _ = format!(\"{{}}\", {});", err_name));
+        }
+        if api_graph.has_std_trait(def_id, StdTrait::Error) {
+            statements.push(format!("let _ = std::error::Error::source(&{});", err_name));
+        }
+    }
+
+    //图中只有一个参数，并且参数可以由错误值得到的API
+    let mut borrowing = Vec::new();
+    let mut moving = None;
+    for (index, api_fun) in api_graph.api_functions.iter().enumerate() {
+        if api_fun.inputs.len() != 1
+            || api_fun._unsafe_tag._is_unsafe()
+            || api_fun._async_tag._is_async()
+            || api_graph.constructors.contains_key(&api_fun.full_name)
+            || iterator::_helper_function_code(&api_fun.full_name).is_some()
+        {
+            continue;
+        }
+        let call_type =
+            api_util::_same_type(err_type, &api_fun.inputs[0], true, cache, full_name_map);
+        if let CallType::_NotCompatible = call_type {
+            continue;
+        }
+        if call_type._contains_unwrap_call_type() {
+            continue;
+        }
+        if call_type._contains_move_call_type() && !api_util::_copy_type(err_type) {
+            if moving.is_none() {
+                moving = Some((index, call_type));
+            }
+        } else if borrowing.len() < MAX_ERROR_CONSUMERS {
+            borrowing.push((index, call_type));
+        }
+    }
+
+    //会move错误值的API放在最后
+    let mut need_mut = false;
+    for (index, call_type) in borrowing.into_iter().chain(moving.into_iter()) {
+        if api_util::_need_mut_tag(&call_type) {
+            need_mut = true;
+        }
+        let param = call_type._to_call_string(err_name, cache, full_name_map);
+        statements
+            .push(format!("let _ = {}({});", api_graph.api_functions[index].full_name, param));
+    }
+    (statements, need_mut)
+}
@@ -0,0 +1,375 @@
+//! 保存API图，离线回答"为什么某个API没有出现在任何测试里"
+//! 1. 设置`FRIES_SAVE_GRAPH=<文件>`时，生成序列之后把过滤后的API图、被过滤的API和原因、
+//!    corpus中的权重以及生成的序列写成JSON
+//! 2. `rustdoc explain <文件> <查询> <参数>`读取保存的API图回答查询，不需要重新编译：
+//!    - `produces <类型>`：哪些API的返回值用到了这个类型
+//!    - `chain <API>`：能产生所有参数的最短调用链
+//!    - `why <API>`：API是否被过滤，为什么；没有被过滤的话，能不能产生参数，有没有被序列覆盖
+//!    - `sequences <API>`：包含这个API的序列
+//!    API可以写全路径，也可以只写路径的后几段，比如`Regex::new`
+
+use crate::fuzz_targets_gen::api_graph::ApiGraph;
+use crate::fuzz_targets_gen::api_util;
+use crate::fuzz_targets_gen::corpus;
+use itertools::Itertools;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+static SAVE_GRAPH_ENV: &'static str = "FRIES_SAVE_GRAPH";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredFunction {
+    full_name: String,
+    signature: String,
+    inputs: Vec<String>,
+    /// 参数是否可以直接由fuzzer的输入产生
+    fuzzable_inputs: Vec<bool>,
+    output: Option<String>,
+    visited: bool,
+    corpus_frequency: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredEdge {
+    output_fun: usize,
+    input_fun: usize,
+    input_param_index: usize,
+    call_type: String,
+    corpus_weight: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct StoredGraph {
+    crate_name: String,
+    functions: Vec<StoredFunction>,
+    edges: Vec<StoredEdge>,
+    /// (full_name, 原因)，按名字排序
+    excluded: Vec<(String, String)>,
+    /// 每个序列中的函数下标
+    sequences: Vec<Vec<usize>>,
+}
+
+impl StoredGraph {
+    fn from_graph(api_graph: &ApiGraph<'_>) -> Self {
+        let corpus::CorpusInfo { depinfo, funcinfo, .. } = &api_graph.corpus_info;
+        let cache = api_graph.cache;
+        let full_name_map = &api_graph.full_name_map;
+
+        let functions = api_graph
+            .api_functions
+            .iter()
+            .enumerate()
+            .map(|(index, api_fun)| StoredFunction {
+                full_name: api_fun.full_name.clone(),
+                signature: api_fun._pretty_print(cache, full_name_map),
+                inputs: api_fun
+                    .inputs
+                    .iter()
+                    .map(|ty| api_util::_type_name(ty, cache, full_name_map))
+                    .collect(),
+                fuzzable_inputs: api_fun
+                    .inputs
+                    .iter()
+                    .map(|ty| {
+                        api_util::is_fuzzable_type(
+                            ty,
+                            cache,
+                            full_name_map,
+                            Some(&api_fun.generic_substitutions),
+                        )
+                    })
+                    .collect(),
+                output: api_fun
+                    .output
+                    .as_ref()
+                    .map(|ty| api_util::_type_name(ty, cache, full_name_map)),
+                visited: api_graph.api_functions_visited.get(index).cloned().unwrap_or(false),
+                corpus_frequency: funcinfo.get(&api_fun.full_name).cloned().unwrap_or(0.0),
+            })
+            .collect();
+
+        let edges = api_graph
+            .api_dependencies
+            .iter()
+            .map(|dependency| {
+                let output_name = &api_graph.api_functions[dependency.output_fun.1].full_name;
+                let input_name = &api_graph.api_functions[dependency.input_fun.1].full_name;
+                StoredEdge {
+                    output_fun: dependency.output_fun.1,
+                    input_fun: dependency.input_fun.1,
+                    input_param_index: dependency.input_param_index,
+                    call_type: format!("{:?}", dependency.call_type),
+                    corpus_weight: depinfo
+                        .get(output_name)
+                        .and_then(|inner_map| inner_map.get(input_name))
+                        .cloned()
+                        .unwrap_or(0.0),
+                }
+            })
+            .collect();
+
+        let excluded = api_graph
+            .excluded_functions
+            .iter()
+            .map(|(full_name, reason)| (full_name.clone(), reason.clone()))
+            .sorted()
+            .collect();
+
+        let sequences = api_graph
+            .api_sequences
+            .iter()
+            .filter(|sequence| sequence.len() > 0)
+            .map(|sequence| sequence.functions.iter().map(|api_call| api_call.func.1).collect())
+            .collect();
+
+        StoredGraph {
+            crate_name: api_graph._crate_name.clone(),
+            functions,
+            edges,
+            excluded,
+            sequences,
+        }
+    }
+
+    /// 名字和查询的API一样，或者以`::查询`结尾
+    fn name_matches(full_name: &str, query: &str) -> bool {
+        full_name == query || full_name.ends_with(&format!("::{}", query))
+    }
+
+    fn find_functions(&self, query: &str) -> Vec<usize> {
+        (0..self.functions.len())
+            .filter(|index| StoredGraph::name_matches(&self.functions[*index].full_name, query))
+            .collect()
+    }
+
+    /// 产生每个函数需要的最少调用次数，以及每个参数选用的生产者，不能产生的函数是None
+    /// 参数可以fuzz的不需要调用，否则选代价最小的生产者，迭代到不动点
+    fn producible_costs(&self) -> (Vec<Option<usize>>, Vec<Vec<Option<usize>>>) {
+        let mut costs: Vec<Option<usize>> = vec![None; self.functions.len()];
+        let mut producers: Vec<Vec<Option<usize>>> =
+            self.functions.iter().map(|function| vec![None; function.inputs.len()]).collect();
+        loop {
+            let mut changed = false;
+            for (index, function) in self.functions.iter().enumerate() {
+                let mut cost = 1;
+                let mut chosen = vec![None; function.inputs.len()];
+                let mut satisfied = true;
+                for param_index in 0..function.inputs.len() {
+                    if function.fuzzable_inputs[param_index] {
+                        continue;
+                    }
+                    let best = self
+                        .edges
+                        .iter()
+                        .filter(|edge| {
+                            edge.input_fun == index && edge.input_param_index == param_index
+                        })
+                        .filter_map(|edge| costs[edge.output_fun].map(|c| (c, edge.output_fun)))
+                        .min();
+                    match best {
+                        Some((c, output_fun)) => {
+                            cost += c;
+                            chosen[param_index] = Some(output_fun);
+                        }
+                        None => {
+                            satisfied = false;
+                            break;
+                        }
+                    }
+                }
+                if satisfied && costs[index].map_or(true, |old| cost < old) {
+                    costs[index] = Some(cost);
+                    producers[index] = chosen;
+                    changed = true;
+                }
+            }
+            if !changed {
+                break;
+            }
+        }
+        (costs, producers)
+    }
+
+    /// 最短调用链，生产者在前
+    fn shortest_chain(&self, index: usize) -> Option<Vec<String>> {
+        let (costs, producers) = self.producible_costs();
+        costs[index]?;
+        let mut lines = Vec::new();
+        self._push_chain(index, &producers, &mut lines);
+        Some(lines)
+    }
+
+    fn _push_chain(
+        &self,
+        index: usize,
+        producers: &Vec<Vec<Option<usize>>>,
+        lines: &mut Vec<String>,
+    ) -> usize {
+        let function = &self.functions[index];
+        let mut params = Vec::new();
+        for (param_index, producer) in producers[index].iter().enumerate() {
+            match producer {
+                Some(producer) => {
+                    let call = self._push_chain(*producer, producers, lines);
+                    params.push(format!("_{}", call));
+                }
+                None => params.push(format!("<fuzz {}>", function.inputs[param_index])),
+            }
+        }
+        lines.push(format!("_{} = {}({})", lines.len(), function.full_name, params.join(", ")));
+        lines.len() - 1
+    }
+
+    fn produces(&self, type_name: &str) -> Vec<String> {
+        let re = match Regex::new(format!(r"\b{}\b", regex::escape(type_name)).as_str()) {
+            Ok(re) => re,
+            Err(_) => return Vec::new(),
+        };
+        self.functions
+            .iter()
+            .filter(|function| function.output.as_ref().map_or(false, |output| re.is_match(output)))
+            .map(|function| function.signature.clone())
+            .collect()
+    }
+
+    fn why(&self, query: &str) -> Vec<String> {
+        let mut res = self
+            .excluded
+            .iter()
+            .filter(|(full_name, _)| StoredGraph::name_matches(full_name, query))
+            .map(|(full_name, reason)| format!("{} was filtered: {}", full_name, reason))
+            .collect_vec();
+        let indexes = self.find_functions(query);
+        if indexes.is_empty() {
+            if res.is_empty() {
+                res.push(format!("{} is not in the api graph of {}", query, self.crate_name));
+            }
+            return res;
+        }
+        let (costs, _) = self.producible_costs();
+        for index in indexes {
+            let function = &self.functions[index];
+            let sequence_num =
+                self.sequences.iter().filter(|sequence| sequence.contains(&index)).count();
+            let mut reasons = Vec::new();
+            if costs[index].is_none() {
+                for param_index in 0..function.inputs.len() {
+                    let has_producer = self.edges.iter().any(|edge| {
+                        edge.input_fun == index && edge.input_param_index == param_index
+                    });
+                    if !function.fuzzable_inputs[param_index] && !has_producer {
+                        reasons.push(format!(
+                            "no api produces parameter #{} ({})",
+                            param_index, function.inputs[param_index]
+                        ));
+                    }
+                }
+                if reasons.is_empty() {
+                    reasons.push(
+                        "its parameters can only be produced by unproducible apis".to_string(),
+                    );
+                }
+            } else if sequence_num == 0 {
+                reasons.push("it is producible but no generated sequence reaches it".to_string());
+            }
+            res.push(format!(
+                "{}: in the graph, visited = {}, corpus frequency = {}, in {} sequences{}",
+                function.signature,
+                function.visited,
+                function.corpus_frequency,
+                sequence_num,
+                reasons.iter().map(|reason| format!("\n  - {}", reason)).collect::<String>()
+            ));
+        }
+        res
+    }
+
+    fn sequences_containing(&self, query: &str) -> Vec<String> {
+        let indexes = self.find_functions(query);
+        self.sequences
+            .iter()
+            .filter(|sequence| sequence.iter().any(|index| indexes.contains(index)))
+            .map(|sequence| {
+                sequence.iter().map(|index| self.functions[*index].full_name.as_str()).join(" -> ")
+            })
+            .collect()
+    }
+}
+
+/// 设置了`FRIES_SAVE_GRAPH`时，保存API图
+pub(crate) fn save_graph(api_graph: &ApiGraph<'_>) {
+    let path = match std::env::var(SAVE_GRAPH_ENV) {
+        Ok(path) => path,
+        Err(_) => return,
+    };
+    let graph = StoredGraph::from_graph(api_graph);
+    let content = serde_json::to_string(&graph).unwrap();
+    match fs::write(&path, content) {
+        Ok(()) => println!(
+            "保存了{}个API，{}个被过滤的API和{}个序列到{}",
+            graph.functions.len(),
+            graph.excluded.len(),
+            graph.sequences.len(),
+            path
+        ),
+        Err(e) => println!("无法保存API图到{}: {}", path, e),
+    }
+}
+
+/// `rustdoc explain <文件> <查询> <参数>`，返回进程的退出码
+pub(crate) fn explain_main(args: &[String]) -> i32 {
+    let usage = "usage: rustdoc explain <graph.json> (produces <type> | chain <api> | why <api> | sequences <api>)";
+    let (path, query, arg) = match args {
+        [path, query, arg] => (path, query.as_str(), arg.as_str()),
+        _ => {
+            eprintln!("{}", usage);
+            return 1;
+        }
+    };
+    let graph: StoredGraph = match fs::read_to_string(path)
+        .map_err(|e| e.to_string())
+        .and_then(|content| serde_json::from_str(&content).map_err(|e| e.to_string()))
+    {
+        Ok(graph) => graph,
+        Err(e) => {
+            eprintln!("can not read api graph {}: {}", path, e);
+            return 1;
+        }
+    };
+
+    let lines = match query {
+        "produces" => graph.produces(arg),
+        "chain" => {
+            let indexes = graph.find_functions(arg);
+            if indexes.is_empty() {
+                vec![format!("{} is not in the api graph, try `why`", arg)]
+            } else {
+                indexes
+                    .into_iter()
+                    .map(|index| match graph.shortest_chain(index) {
+                        Some(chain) => format!(
+                            "{}:\n{}",
+                            graph.functions[index].signature,
+                            chain.iter().map(|line| format!("  {}", line)).join("\n")
+                        ),
+                        None => format!("{}: not producible", graph.functions[index].signature),
+                    })
+                    .collect()
+            }
+        }
+        "why" => graph.why(arg),
+        "sequences" => graph.sequences_containing(arg),
+        _ => {
+            eprintln!("{}", usage);
+            return 1;
+        }
+    };
+    if lines.is_empty() {
+        println!("nothing found");
+    }
+    for line in lines {
+        println!("{}", line);
+    }
+    0
+}
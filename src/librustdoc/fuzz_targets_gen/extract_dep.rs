@@ -418,6 +418,55 @@ enum LocalCallType {
     LocalFunctionPtr(mir::Local),
 }
 
+/// 用调用点的泛型实参把被调用函数解析到具体的实现：trait方法解析到impl里的方法，
+/// 比如`FromStr::from_str`解析到`<Url as FromStr>::from_str`，闭包的`Fn::call`解析到闭包本身
+/// 解析不了的（caller本身是泛型、dyn Trait的虚调用、编译器生成的shim）保持原来的DefId
+fn resolve_callee<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    caller: DefId,
+    def_id: DefId,
+    substs: ty::SubstsRef<'tcx>,
+) -> DefId {
+    let param_env = tcx.param_env_reveal_all_normalized(caller);
+    match ty::Instance::resolve(tcx, param_env, def_id, substs) {
+        Ok(Some(ty::Instance { def: ty::InstanceDef::Item(item), .. })) => item.did,
+        _ => def_id,
+    }
+}
+
+/// 函数指针的来源只有一个函数项时（比如`let f: fn(&str) -> _ = Url::parse;`），当成直接调用这个函数
+/// 来源里有参数、其他调用的返回值或者多个函数项时，不知道实际调用的是哪个
+fn resolve_function_ptr<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    caller: DefId,
+    deps: &LocalDependencies<'tcx>,
+    callsites: &Vec<CallSite<'tcx>>,
+    ptr: mir::Local,
+) -> Option<DefId> {
+    let mut fn_items = Vec::new();
+    for dep in deps.dependencies(ptr) {
+        match dep {
+            DependencyType::Return | DependencyType::Argument(_) => return None,
+            DependencyType::Local(local) => {
+                if callsites.iter().any(|callsite| callsite.return_variable == Some(local)) {
+                    return None;
+                }
+            }
+            DependencyType::Constant(cst) => {
+                if let ty::FnDef(def_id, substs) = cst.literal.ty().kind() {
+                    if !fn_items.contains(&(*def_id, *substs)) {
+                        fn_items.push((*def_id, *substs));
+                    }
+                }
+            }
+        }
+    }
+    match fn_items[..] {
+        [(def_id, substs)] => Some(resolve_callee(tcx, caller, def_id, substs)),
+        _ => None,
+    }
+}
+
 /// 给定一个mir::Body，解析某个函数内部的函数调用
 fn extract_function_call<'tcx>(
     tcx: TyCtxt<'tcx>,
@@ -464,11 +513,16 @@ fn visit_terminator(&mut self, terminator: &Terminator<'tcx>, _location: mir::Lo
                     }
                     //直接调用的函数是一种常量
                     Constant(constant) => {
-                        if let ty::FnDef(def_id, _) = constant.literal.ty().kind() {
+                        if let ty::FnDef(def_id, substs) = constant.literal.ty().kind() {
                             let def_id = *def_id;
                             use def::DefKind::*;
                             match self.tcx.def_kind(def_id) {
-                                Fn | AssocFn => LocalCallType::DirectCall(def_id),
+                                Fn | AssocFn => LocalCallType::DirectCall(resolve_callee(
+                                    self.tcx,
+                                    self.caller.source.def_id(),
+                                    def_id,
+                                    *substs,
+                                )),
                                 _other => {
                                     //基本不会触发
                                     return;
@@ -584,9 +638,17 @@ pub fn extract_all_dependencies<'tcx>(tcx: TyCtxt<'tcx>) -> AllDependencies<'tcx
         // 参数
         let arguments = extract_arguments(&mir);
         //函数调用点
-        let callsites: Vec<CallSite<'_>> = extract_function_call(tcx, &mir);
+        let mut callsites: Vec<CallSite<'_>> = extract_function_call(tcx, &mir);
         //解析依赖并传播
         let deps = LocalDependencies::compute(&mir);
+        //来源已知的函数指针当成直接调用
+        for index in 0..callsites.len() {
+            if let LocalCallType::LocalFunctionPtr(ptr) = callsites[index].function {
+                if let Some(callee) = resolve_function_ptr(tcx, caller, &deps, &callsites, ptr) {
+                    callsites[index].function = LocalCallType::DirectCall(callee);
+                }
+            }
+        }
 
         // 局部变量的一些来源，也就是说对于 from: mir::Local，依赖于哪些：
         //  - caller的参数
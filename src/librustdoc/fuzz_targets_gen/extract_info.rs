@@ -2,15 +2,17 @@
 use std::io::Write;
 use std::path::PathBuf;
 
+use crate::fuzz_targets_gen::api_id;
 use crate::fuzz_targets_gen::extract_dep::AllDependencies;
 use crate::fuzz_targets_gen::extract_dep::{
-    extract_arguments, Argument, CalleeDependency, Function,
+    extract_arguments, Argument, CallSite, CalleeDependency, Function,
 };
 
 use rustc_data_structures::fx::{FxHashMap, FxHashSet};
 use rustc_hir::def_id::DefId;
 use rustc_middle::mir;
 use rustc_middle::ty::{self, Ty, TyCtxt};
+use rustc_span::symbol::sym;
 
 /// 解析序列
 pub struct ExtractInfo {
@@ -18,6 +20,10 @@ pub struct ExtractInfo {
     pub dependencies_info: FxHashMap<(String, String), usize>,
     pub order_info: FxHashMap<(String, String), usize>,
     pub function_info: FxHashMap<String, usize>,
+    /// (A, B)：A的返回值直接传给B，并且B的返回值类型和A的某个参数类型相同，可能是一对互逆的API
+    pub inverse_pair_info: FxHashMap<(String, String), usize>,
+    /// 上面每个名字在调用点上对应的DefId，名字就是这个DefId的def_path_str
+    pub def_ids: FxHashMap<String, DefId>,
 }
 
 impl ExtractInfo {
@@ -37,15 +43,23 @@ pub fn new<'tcx>(
             enable,
         );
 
-        let (dependencies_info, order_info, function_info) = Self::extract_info(
-            tcx,
-            current_crate_name.clone(),
-            test_crate_name.clone(),
-            all_dependencies,
-            enable,
-        );
+        let (dependencies_info, order_info, function_info, inverse_pair_info, def_ids) =
+            Self::extract_info(
+                tcx,
+                current_crate_name.clone(),
+                test_crate_name.clone(),
+                all_dependencies,
+                enable,
+            );
 
-        ExtractInfo { all_sequences, dependencies_info, order_info, function_info }
+        ExtractInfo {
+            all_sequences,
+            dependencies_info,
+            order_info,
+            function_info,
+            inverse_pair_info,
+            def_ids,
+        }
     }
 
     /// 进行一个深度优先搜索，然后生成遍历序列
@@ -105,7 +119,7 @@ pub fn extract_sequence<'tcx>(
                             Callee::DirectCall(def_id) => {
                                 (tcx.crate_name(def_id.krate).as_str().to_string(), def_id)
                             }
-                            Callee::LocalFunctionPtr(_) => continue, //跳过
+                            Callee::LocalFunctionPtr(_) => continue, //来源不明的函数指针，跳过
                         };
 
                         // 如果当前的callsite参数都是primitive type的话，把上一个序列终结，开始新序列
@@ -118,7 +132,7 @@ pub fn extract_sequence<'tcx>(
 
                         let callee_name = tcx.def_path_str(callee_def_id);
                         //if crate_name.starts_with(&test_crate_name) {
-                        if callee_name.starts_with(&test_crate_name) {
+                        if _is_tested_api(tcx, callee_def_id, &callee_name, &test_crate_name) {
                             // 如果是test crate的api，推入序列
 
                             func_seq.push(callee_name);
@@ -181,10 +195,18 @@ pub fn extract_info<'tcx>(
         FxHashMap<(String, String), usize>,
         FxHashMap<(String, String), usize>,
         FxHashMap<String, usize>,
+        FxHashMap<(String, String), usize>,
+        FxHashMap<String, DefId>,
     ) {
         //如果待测crate就是当前crate，那就返回，因为可能解析到非pub
         if current_crate_name == test_crate_name || !enable {
-            return (FxHashMap::default(), FxHashMap::default(), FxHashMap::default());
+            return (
+                FxHashMap::default(),
+                FxHashMap::default(),
+                FxHashMap::default(),
+                FxHashMap::default(),
+                FxHashMap::default(),
+            );
         }
 
         // 用于剪枝，访问过的API就不用访问了
@@ -194,6 +216,8 @@ pub fn extract_info<'tcx>(
         let mut pre_succ_map = FxHashMap::default();
         let mut order_map = FxHashMap::default();
         let mut function_map = FxHashMap::default();
+        let mut inverse_pair_map = FxHashMap::default();
+        let mut def_id_map = FxHashMap::default();
 
         //遍历每一个本地函数
         for (caller_def_id, function) in all_dependencies.functions.iter() {
@@ -232,10 +256,11 @@ pub fn extract_info<'tcx>(
                         Callee::DirectCall(def_id) => {
                             (tcx.crate_name(def_id.krate).as_str().to_string(), def_id)
                         }
-                        Callee::LocalFunctionPtr(_) => continue, //跳过
+                        Callee::LocalFunctionPtr(_) => continue, //来源不明的函数指针，跳过
                     };
                     let callee_name = tcx.def_path_str(*callee_def_id);
-                    if callee_name.starts_with(&test_crate_name) {
+                    if _is_tested_api(tcx, *callee_def_id, &callee_name, &test_crate_name) {
+                        def_id_map.insert(callee_name.clone(), *callee_def_id);
                         order_sequence.push(callee_name.clone());
                     }
                 }
@@ -246,18 +271,18 @@ pub fn extract_info<'tcx>(
                     }
                 }
                 //下面遍历每个callee，解析dependency_info
-                for CalleeDependency { callee, arg_sources, .. } in &callee_dependency {
+                for CalleeDependency { callee, arg_sources, callsite } in &callee_dependency {
                     //被调用函数对应的crate_name和DefId
                     let (_crate_name, callee_def_id) = match callee {
                         Callee::DirectCall(def_id) => {
                             (tcx.crate_name(def_id.krate).as_str().to_string(), def_id)
                         }
-                        Callee::LocalFunctionPtr(_) => continue, //跳过
+                        Callee::LocalFunctionPtr(_) => continue, //来源不明的函数指针，跳过
                     };
 
                     let callee_name = tcx.def_path_str(*callee_def_id);
                     //if crate_name.starts_with(&test_crate_name) {
-                    if callee_name.starts_with(&test_crate_name) {
+                    if _is_tested_api(tcx, *callee_def_id, &callee_name, &test_crate_name) {
                         //先加入function_info
                         if function_map.contains_key(&callee_name) {
                             function_map.insert(
@@ -276,10 +301,22 @@ pub fn extract_info<'tcx>(
                                         crate::fuzz_targets_gen::extract_dep::Source::ReturnVariable(pre_id) => {
                                             let pre_function_name = tcx.def_path_str(*pre_id);
                                             //只有前驱是tested_lib中的才行
-                                            if pre_function_name.starts_with(&test_crate_name)
+                                            if _is_tested_api(tcx, *pre_id, &pre_function_name, &test_crate_name)
                                             {
+                                                def_id_map.insert(pre_function_name.clone(), *pre_id);
                                                 let succ_function_name = callee_name.clone();
                                                 let tuple = (pre_function_name, succ_function_name);
+                                                //同一个函数里A的调用点，B的返回值和A的参数类型相同的话，可能是互逆的
+                                                let is_inverse = *pre_id != *callee_def_id
+                                                    && callee_dependency.iter().any(|dependency| match dependency.callee {
+                                                        Callee::DirectCall(def_id) if def_id == *pre_id => {
+                                                            _is_inverse_call(tcx, &dependency.callsite, callsite)
+                                                        }
+                                                        _ => false,
+                                                    });
+                                                if is_inverse {
+                                                    *inverse_pair_map.entry(tuple.clone()).or_insert(0) += 1;
+                                                }
                                                 //如果有就更新，没有就继续
                                                 if pre_succ_map.contains_key(&tuple) {
                                                     pre_succ_map.insert(tuple.clone(), pre_succ_map.get(&tuple).unwrap()+1);
@@ -307,7 +344,7 @@ pub fn extract_info<'tcx>(
             }
         }
 
-        (pre_succ_map, order_map, function_map)
+        (pre_succ_map, order_map, function_map, inverse_pair_map, def_id_map)
     }
 
     pub fn print_sequence(&self, enable: bool, dir_path: &str, _crate_name: &str) {
@@ -398,6 +435,40 @@ pub fn print_order_info(&self, enable: bool, dir_path: &str, _crate_name: &str)
         println!("\x1b[94mFinish printing\x1b[0m");
     }
 
+    /// 可能互逆的API对，需要人工确认之后才会用来生成测试
+    pub fn print_inverse_pair_info(&self, enable: bool, dir_path: &str, _crate_name: &str) {
+        if !enable {
+            return;
+        }
+
+        let dir_path = PathBuf::from(dir_path).join(_crate_name).join("inversepairinfo");
+
+        println!(
+            "\x1b[94mStart to print inverse pairs extracted from corpus, please confirm them manually.\x1b[0m"
+        );
+
+        let mut file =
+            OpenOptions::new().create(true).append(true).open(dir_path).expect("cannot open file");
+        for (idx, ((forward_func, inverse_func), num)) in self.inverse_pair_info.iter().enumerate()
+        {
+            let s = format!(
+                "pair_{}:   {}   {}   {}",
+                idx,
+                _get_function_name(forward_func.clone()),
+                _get_function_name(inverse_func.clone()),
+                num
+            );
+            println!("{}", s);
+            file.write_all(s.as_bytes()).expect("write failed");
+
+            //写入回车
+            println!("");
+            file.write_all("\n".as_bytes()).expect("write failed");
+        }
+
+        println!("\x1b[94mFinish printing\x1b[0m");
+    }
+
     pub fn print_functions_info(&self, enable: bool, dir_path: &str, _crate_name: &str) {
         if !enable {
             return;
@@ -521,7 +592,50 @@ fn get_function_signature<'tcx>(tcx: TyCtxt<'tcx>, def_id: DefId) -> ty::FnSig<'
     }
 }
 
-fn _get_function_name(name: String) -> String {
+/// B的返回值（去掉Result和Option）和A的某个参数（去掉引用）类型相同
+/// str和String、[T]和Vec<T>也算相同
+fn _is_inverse_call<'tcx>(
+    tcx: TyCtxt<'tcx>,
+    forward: &CallSite<'tcx>,
+    inverse: &CallSite<'tcx>,
+) -> bool {
+    let mut output = tcx.erase_regions(inverse.return_ty);
+    if let ty::Adt(adt_def, substs) = output.kind() {
+        if tcx.is_diagnostic_item(sym::Result, adt_def.did())
+            || tcx.is_diagnostic_item(sym::Option, adt_def.did())
+        {
+            output = substs.type_at(0);
+        }
+    }
+    let output = output.peel_refs();
+    forward.argument_tys.iter().any(|input| {
+        let input = tcx.erase_regions(*input).peel_refs();
+        if input == output {
+            return true;
+        }
+        match (input.kind(), output.kind()) {
+            (ty::Str, ty::Adt(adt_def, _)) => tcx.is_diagnostic_item(sym::String, adt_def.did()),
+            (ty::Slice(elem), ty::Adt(adt_def, substs)) => {
+                tcx.is_diagnostic_item(sym::Vec, adt_def.did()) && substs.type_at(0) == *elem
+            }
+            _ => false,
+        }
+    })
+}
+
+/// 被调用的函数是不是待测库的API
+/// trait方法解析到impl之后名字是`<url::Url as std::str::FromStr>::from_str`，按规范化的路径判断；
+/// 待测库为其他类型实现的trait，比如`<std::string::String as url::IntoUrl>::into_url`，按crate判断
+fn _is_tested_api(tcx: TyCtxt<'_>, def_id: DefId, name: &str, test_crate_name: &str) -> bool {
+    if tcx.crate_name(def_id.krate).as_str() == test_crate_name {
+        return true;
+    }
+    //按路径的第一段比较，`url_helper::xx`不算`url`的API
+    let path = api_id::normalize_path(name);
+    path == test_crate_name || path.starts_with(&format!("{}::", test_crate_name))
+}
+
+pub(crate) fn _get_function_name(name: String) -> String {
     // If no name can be found, return an empty string
 
     // 使用正则表达式匹配函数名字符串
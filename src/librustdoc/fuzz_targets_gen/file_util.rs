@@ -1,5 +1,10 @@
+use crate::fuzz_targets_gen::api_diff;
 use crate::fuzz_targets_gen::api_graph::ApiGraph;
 use crate::fuzz_targets_gen::api_graph::GraphTraverseAlgorithm::*;
+use crate::fuzz_targets_gen::compile_check::{self, CompileChecker};
+use crate::fuzz_targets_gen::corpus;
+use crate::fuzz_targets_gen::focus;
+use crate::fuzz_targets_gen::inverse_pair;
 use itertools::Itertools;
 use rustc_data_structures::fx::FxHashMap;
 use std::fs;
@@ -130,6 +135,9 @@ pub(crate) fn get_randwalk_crate_test_dir(lib_name: &str) -> String {
 static _REPRODUCE_FILE_DIR: &'static str = "replay_files";
 static _LIBFUZZER_DIR: &'static str = "libfuzzer_files";
 static MAX_TEST_FILE_NUMBER: usize = 300;
+static _COMPILE_CHECK_LOG: &'static str = "compile_check.log";
+/// 记录生成参数的文件，用同样的种子和参数可以重新生成完全相同的测试
+static _MANIFEST_FILE: &'static str = "manifest.txt";
 //static DEFAULT_RANDOM_FILE_NUMBER: usize = 100;
 
 pub(crate) fn can_write_to_file(crate_name: &String, strategy: GraphTraverseAlgorithm) -> bool {
@@ -171,6 +179,9 @@ pub(crate) struct FileHelper {
     pub(crate) test_dir: String,
     pub(crate) test_files: Vec<String>,
     pub(crate) reproduce_files: Vec<String>,
+    /// 没有通过类型检查的序列，以及对应的诊断信息
+    pub(crate) rejected_sequences: Vec<(String, String)>,
+    pub(crate) manifest: String,
     //pub(crate) libfuzzer_files: Vec<String>,
 }
 
@@ -202,6 +213,8 @@ pub(crate) fn new(
         let mut test_files = Vec::new();
         let mut reproduce_files = Vec::new();
         let mut libfuzzer_files = Vec::new();
+        let mut rejected_sequences = Vec::new();
+        let checker = CompileChecker::new(crate_name.as_str());
         //let chosen_sequences = api_graph._naive_choose_sequence(MAX_TEST_FILE_NUMBER);
         let _chosen_sequences = if strategy == _Fudge {
             //api_graph.api_sequences.clone()
@@ -219,8 +232,18 @@ pub(crate) fn new(
             api_graph._first_choose(max_size, max_len)
         };
 
+        //人工确认过的互逆API对，每一对单独生成一个测试
+        let inverse_pair_sequences =
+            inverse_pair::_inverse_pair_sequences(api_graph, crate_name.as_str());
+
         let mut sequence_map = FxHashMap::default();
-        for seq in _chosen_sequences {
+        for seq in _chosen_sequences.into_iter().chain(inverse_pair_sequences) {
+            if !api_graph.is_focused_sequence(&seq) {
+                continue;
+            }
+            let seq = api_graph._minimize_sequence(&seq);
+            let seq = api_graph._append_iterator_steps(&seq);
+            let seq = api_graph._append_poll_budget(&seq);
             let seq_str = seq.print_sequence(api_graph, true);
             //println!("{}", seq_str);
             sequence_map.insert(seq_str, seq);
@@ -236,6 +259,13 @@ pub(crate) fn new(
                 break;
             }
             let test_file = sequence._to_afl_test_file(api_graph, sequence_count);
+            // 类型检查没通过的序列直接丢弃，只记录诊断信息
+            if let compile_check::CheckResult::Fail(diagnostic) =
+                checker.check(&test_file, sequence_count)
+            {
+                rejected_sequences.push((sequence.print_sequence(api_graph, true), diagnostic));
+                continue;
+            }
             test_files.push(test_file);
             let reproduce_file = sequence._to_replay_crash_file(api_graph, sequence_count);
             reproduce_files.push(reproduce_file);
@@ -243,7 +273,34 @@ pub(crate) fn new(
             libfuzzer_files.push(libfuzzer_file);
             sequence_count = sequence_count + 1;
         }
-        FileHelper { crate_name, test_dir, test_files, reproduce_files }
+        println!("类型检查丢弃了 {} 个序列", rejected_sequences.len());
+        let mut manifest = format!(
+            "crate = {}\nseed = {}\nstrategy = {:?}\nmax_size = {}\nmax_len = {}\ntest_files = {}\nrejected_sequences = {}\n",
+            crate_name,
+            api_graph.seed,
+            strategy,
+            max_size,
+            max_len,
+            test_files.len(),
+            rejected_sequences.len()
+        );
+        //影响生成结果的其他设置
+        for (key, value) in
+            focus::manifest_entries().into_iter().chain(api_diff::manifest_entries())
+        {
+            manifest.push_str(format!("{} = {}\n", key, value).as_str());
+        }
+        let focus_apis = api_graph.focus_apis.as_ref().map_or(0, |focus_apis| focus_apis.len());
+        manifest.push_str(format!("focus_apis = {}\n", focus_apis).as_str());
+        manifest.push_str(format!("corpus_weights = {}\n", corpus::manifest_weights()).as_str());
+        FileHelper {
+            crate_name,
+            test_dir,
+            test_files,
+            reproduce_files,
+            rejected_sequences,
+            manifest,
+        }
     }
 
     pub(crate) fn write_files(&self) {
@@ -259,6 +316,14 @@ pub(crate) fn write_files(&self) {
         write_to_files(&self.crate_name, &test_file_path, &self.test_files, "test");
         //暂时用test file代替一下，后续改成真正的reproduce file
         write_to_files(&self.crate_name, &reproduce_file_path, &self.reproduce_files, "replay");
+        compile_check::write_diagnostics(
+            &test_path.join(_COMPILE_CHECK_LOG),
+            &self.rejected_sequences,
+        );
+        let manifest_path = test_path.join(_MANIFEST_FILE);
+        if let Err(e) = fs::write(&manifest_path, &self.manifest) {
+            println!("无法写入manifest {}: {}", manifest_path.display(), e);
+        }
     }
     /*
     pub(crate) fn write_libfuzzer_files(&self) {
@@ -0,0 +1,96 @@
+//! 只为一部分API生成测试
+//! `FRIES_INCLUDE`和`FRIES_EXCLUDE`是逗号分隔的过滤条件，每个条件可以是：
+//! 1. `path:<glob>`（不加前缀也是路径）：API的路径，比如`regex::bytes::*`，`*`匹配任意字符
+//! 2. `type:<类型名>`：参数或者返回值（包括self）用到了这个类型
+//! 3. `trait:<trait名>`：API是这个trait的方法，可以是trait的名字或者全路径
+//! 满足任意一个include条件（没有include时就是所有API），并且不满足任何exclude条件的API是要关注的
+//! 其他API仍然留在图里，用来产生关注的API需要的参数
+
+use crate::fuzz_targets_gen::api_function::ApiFunction;
+use crate::fuzz_targets_gen::api_graph::ApiGraph;
+use crate::fuzz_targets_gen::api_util;
+use regex::Regex;
+use rustc_data_structures::fx::FxHashSet;
+
+static INCLUDE_ENV: &'static str = "FRIES_INCLUDE";
+static EXCLUDE_ENV: &'static str = "FRIES_EXCLUDE";
+
+/// 记录在manifest里的过滤条件
+pub(crate) fn manifest_entries() -> Vec<(&'static str, String)> {
+    vec![
+        ("include", std::env::var(INCLUDE_ENV).unwrap_or_default()),
+        ("exclude", std::env::var(EXCLUDE_ENV).unwrap_or_default()),
+    ]
+}
+
+#[derive(Debug, Clone)]
+enum FocusFilter {
+    Path(Regex),
+    Type(Regex),
+    Trait(String),
+}
+
+impl FocusFilter {
+    fn parse(filter: &str) -> Option<Self> {
+        let filter = filter.trim();
+        if filter.is_empty() {
+            return None;
+        }
+        if let Some(type_name) = filter.strip_prefix("type:") {
+            let re = format!(r"\b{}\b", regex::escape(type_name.trim()));
+            return Regex::new(re.as_str()).ok().map(FocusFilter::Type);
+        }
+        if let Some(trait_name) = filter.strip_prefix("trait:") {
+            return Some(FocusFilter::Trait(trait_name.trim().to_string()));
+        }
+        let glob = filter.strip_prefix("path:").unwrap_or(filter).trim();
+        let re = format!("^{}$", regex::escape(glob).replace(r"\*", ".*"));
+        Regex::new(re.as_str()).ok().map(FocusFilter::Path)
+    }
+
+    fn matches(&self, api_graph: &ApiGraph<'_>, api_fun: &ApiFunction) -> bool {
+        match self {
+            FocusFilter::Path(re) => re.is_match(&api_fun.full_name),
+            FocusFilter::Type(re) => api_fun.inputs.iter().chain(api_fun.output.iter()).any(|ty| {
+                re.is_match(&api_util::_type_name(ty, api_graph.cache, &api_graph.full_name_map))
+            }),
+            FocusFilter::Trait(trait_name) => match &api_fun._trait_full_path {
+                Some(trait_path) => {
+                    trait_path == trait_name || trait_path.ends_with(&format!("::{}", trait_name))
+                }
+                None => false,
+            },
+        }
+    }
+}
+
+fn _filters_from_env(env: &str) -> Option<Vec<FocusFilter>> {
+    let value = std::env::var(env).ok()?;
+    Some(value.split(',').filter_map(FocusFilter::parse).collect())
+}
+
+/// 根据include和exclude得到要关注的API，和已有的关注范围（比如只关注改变了的API）取交集
+pub(crate) fn apply_focus_filters(api_graph: &mut ApiGraph<'_>) {
+    let include = _filters_from_env(INCLUDE_ENV);
+    let exclude = _filters_from_env(EXCLUDE_ENV);
+    if include.is_none() && exclude.is_none() {
+        return;
+    }
+    let include = include.unwrap_or_default();
+    let exclude = exclude.unwrap_or_default();
+
+    let mut focus_apis = FxHashSet::default();
+    for api_fun in &api_graph.api_functions {
+        let included =
+            include.is_empty() || include.iter().any(|filter| filter.matches(api_graph, api_fun));
+        let excluded = exclude.iter().any(|filter| filter.matches(api_graph, api_fun));
+        if included && !excluded {
+            focus_apis.insert(api_fun.full_name.clone());
+        }
+    }
+    if let Some(existing) = &api_graph.focus_apis {
+        focus_apis.retain(|full_name| existing.contains(full_name));
+    }
+    println!("关注的API有{}个", focus_apis.len());
+    api_graph.focus_apis = Some(focus_apis);
+}
@@ -1,6 +1,7 @@
 use crate::clean::{self, PrimitiveType};
 use crate::formats::cache::Cache;
 use crate::fuzz_targets_gen::call_type::CallType;
+use crate::fuzz_targets_gen::callback::{self, CallbackSignature};
 use crate::fuzz_targets_gen::impl_util::FullNameMap;
 use crate::fuzz_targets_gen::prelude_type::PreludeType;
 use rustc_data_structures::fx::FxHashMap;
@@ -20,6 +21,7 @@ pub(crate) enum FuzzableCallType {
     BorrowedRef(Box<FuzzableCallType>),
     MutBorrowedRef(Box<FuzzableCallType>),
     ToOption(Box<FuzzableCallType>),
+    Callback(CallbackSignature),
 }
 
 #[derive(Debug, Clone, Eq, PartialEq)]
@@ -29,6 +31,7 @@ pub(crate) enum FuzzableType {
     RefSlice(Box<FuzzableType>),
     RefStr,
     Tuple(Vec<Box<FuzzableType>>),
+    Callback(CallbackSignature),
 }
 
 impl FuzzableCallType {
@@ -132,6 +135,9 @@ pub(crate) fn generate_fuzzable_type_and_call_type(&self) -> (FuzzableType, Call
                 }
                 return (fuzzable_type, CallType::_ToOption(Box::new(inner_call_type)));
             }
+            FuzzableCallType::Callback(signature) => {
+                return (FuzzableType::Callback(signature.clone()), CallType::_DirectCall);
+            }
             FuzzableCallType::Array(_) | FuzzableCallType::Slice(_) => {
                 return (FuzzableType::NoFuzzable, CallType::_NotCompatible);
             } //_ => {
@@ -148,6 +154,7 @@ pub(crate) fn _is_fixed_length(&self) -> bool {
             FuzzableType::Primitive(_) => true,
             FuzzableType::RefSlice(_) => false,
             FuzzableType::RefStr => false,
+            FuzzableType::Callback(_) => true,
             FuzzableType::Tuple(inner_fuzzables) => {
                 for inner_fuzzable in inner_fuzzables {
                     if !inner_fuzzable._is_fixed_length() {
@@ -187,6 +194,7 @@ pub(crate) fn _min_length(&self) -> usize {
             }
             FuzzableType::RefSlice(inner_fuzzable) => inner_fuzzable._min_length(),
             FuzzableType::RefStr => 1,
+            FuzzableType::Callback(_) => callback::CALLBACK_TABLE_LENGTH,
             FuzzableType::Tuple(inner_fuzzables) => {
                 let mut total_length = 0;
                 for inner_fuzzable in inner_fuzzables {
@@ -273,6 +281,7 @@ pub(crate) fn _to_type_string(&self) -> String {
                 res
             }
             FuzzableType::RefStr => "&str".to_string(),
+            FuzzableType::Callback(signature) => signature._to_type_string(),
             FuzzableType::Tuple(inner_types) => {
                 let mut res = "(".to_string();
                 let first_type = inner_types.first();
@@ -347,8 +356,13 @@ pub(crate) fn fuzzable_call_type(
             FuzzableCallType::Primitive(primitive_type.clone())
         }
 
-        //外部函数，不支持
-        clean::Type::BareFunction(..) => FuzzableCallType::NoFuzzable,
+        //函数指针和闭包，合成一个回调
+        clean::Type::BareFunction(..) | clean::Type::ImplTrait(..) | clean::Type::DynTrait(..) => {
+            match CallbackSignature::from_type(ty_, cache, full_name_map) {
+                Some(signature) => FuzzableCallType::Callback(signature),
+                None => FuzzableCallType::NoFuzzable,
+            }
+        }
         clean::Type::Tuple(inner_types) => {
             let mut vec = Vec::new();
             for inner_type in inner_types {
@@ -445,14 +459,8 @@ pub(crate) fn fuzzable_call_type(
         clean::Type::QPath { .. } => {
             return FuzzableCallType::NoFuzzable;
         }
-        clean::Type::ImplTrait(..) => {
-            return FuzzableCallType::NoFuzzable;
-        }
         clean::Type::Infer => {
             return FuzzableCallType::NoFuzzable;
         }
-        clean::Type::DynTrait(_, _) => {
-            return FuzzableCallType::NoFuzzable;
-        }
     }
 }
@@ -0,0 +1,234 @@
+//! 把API依赖图导出成Graphviz的DOT和JSON，方便查看哪些API和其他API连不起来
+//! 设置`FRIES_EXPORT_GRAPH=<前缀>`时，在生成序列之后写出`<前缀>.dot`和`<前缀>.json`
+//! 节点标注是否是start/end函数、是否unsafe、生成的序列是否覆盖到、在corpus中出现的次数，
+//! 以及所在的连通分量（把依赖边当成无向边）；边标注调用方式和在corpus中出现的次数
+
+use crate::fuzz_targets_gen::api_graph::ApiGraph;
+use crate::fuzz_targets_gen::corpus;
+use rustc_data_structures::fx::FxHashMap;
+use rustc_graphviz as dot;
+use serde::Serialize;
+use std::fs;
+
+static EXPORT_GRAPH_ENV: &'static str = "FRIES_EXPORT_GRAPH";
+
+#[derive(Debug, Clone, Serialize)]
+struct GraphNode {
+    index: usize,
+    full_name: String,
+    signature: String,
+    start: bool,
+    end: bool,
+    #[serde(rename = "unsafe")]
+    unsafe_: bool,
+    visited: bool,
+    corpus_frequency: f64,
+    component: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct GraphEdge {
+    source: usize,
+    target: usize,
+    input_param_index: usize,
+    call_type: String,
+    corpus_weight: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+struct ExportedGraph {
+    crate_name: String,
+    components: usize,
+    nodes: Vec<GraphNode>,
+    edges: Vec<GraphEdge>,
+}
+
+impl ExportedGraph {
+    fn from_graph(api_graph: &ApiGraph<'_>, support_generic: bool) -> Self {
+        let corpus::CorpusInfo { depinfo, funcinfo, .. } = &api_graph.corpus_info;
+
+        let edges = api_graph
+            .api_dependencies
+            .iter()
+            .map(|dependency| {
+                let source = dependency.output_fun.1;
+                let target = dependency.input_fun.1;
+                let corpus_weight = depinfo
+                    .get(&api_graph.api_functions[source].full_name)
+                    .and_then(|inner_map| inner_map.get(&api_graph.api_functions[target].full_name))
+                    .cloned()
+                    .unwrap_or(0.0);
+                GraphEdge {
+                    source,
+                    target,
+                    input_param_index: dependency.input_param_index,
+                    call_type: format!("{:?}", dependency.call_type),
+                    corpus_weight,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        let (components, component_of) =
+            _connected_components(api_graph.api_functions.len(), &edges);
+
+        let nodes = api_graph
+            .api_functions
+            .iter()
+            .enumerate()
+            .map(|(index, api_fun)| GraphNode {
+                index,
+                full_name: api_fun.full_name.clone(),
+                signature: api_fun._pretty_print(api_graph.cache, &api_graph.full_name_map),
+                start: api_fun._is_start_function(
+                    api_graph.cache,
+                    &api_graph.full_name_map,
+                    support_generic,
+                ),
+                end: api_fun._is_end_function(
+                    api_graph.cache,
+                    &api_graph.full_name_map,
+                    support_generic,
+                ),
+                unsafe_: api_fun._unsafe_tag._is_unsafe(),
+                visited: api_graph.api_functions_visited.get(index).cloned().unwrap_or(false),
+                corpus_frequency: funcinfo.get(&api_fun.full_name).cloned().unwrap_or(0.0),
+                component: component_of[index],
+            })
+            .collect();
+
+        ExportedGraph { crate_name: api_graph._crate_name.clone(), components, nodes, edges }
+    }
+}
+
+/// 把依赖边当成无向边求连通分量，返回分量的个数和每个节点所在的分量
+fn _connected_components(node_num: usize, edges: &Vec<GraphEdge>) -> (usize, Vec<usize>) {
+    let mut neighbours: FxHashMap<usize, Vec<usize>> = FxHashMap::default();
+    for edge in edges {
+        neighbours.entry(edge.source).or_default().push(edge.target);
+        neighbours.entry(edge.target).or_default().push(edge.source);
+    }
+    let mut component_of = vec![usize::MAX; node_num];
+    let mut components = 0;
+    for start in 0..node_num {
+        if component_of[start] != usize::MAX {
+            continue;
+        }
+        component_of[start] = components;
+        let mut stack = vec![start];
+        while let Some(node) = stack.pop() {
+            for next in neighbours.get(&node).into_iter().flatten() {
+                if component_of[*next] == usize::MAX {
+                    component_of[*next] = components;
+                    stack.push(*next);
+                }
+            }
+        }
+        components += 1;
+    }
+    (components, component_of)
+}
+
+impl<'a> dot::Labeller<'a> for ExportedGraph {
+    type Node = usize;
+    type Edge = &'a GraphEdge;
+
+    fn graph_id(&'a self) -> dot::Id<'a> {
+        dot::Id::new("api_graph").unwrap()
+    }
+
+    fn node_id(&'a self, n: &usize) -> dot::Id<'a> {
+        dot::Id::new(format!("f{}", n)).unwrap()
+    }
+
+    /// start函数用方框，end函数用双八边形
+    fn node_shape(&'a self, n: &usize) -> Option<dot::LabelText<'a>> {
+        let node = &self.nodes[*n];
+        if node.start {
+            Some(dot::LabelText::label("box"))
+        } else if node.end {
+            Some(dot::LabelText::label("doubleoctagon"))
+        } else {
+            None
+        }
+    }
+
+    fn node_label(&'a self, n: &usize) -> dot::LabelText<'a> {
+        let node = &self.nodes[*n];
+        let mut tags = Vec::new();
+        if node.unsafe_ {
+            tags.push("unsafe".to_string());
+        }
+        if !node.visited {
+            tags.push("unvisited".to_string());
+        }
+        tags.push(format!("freq={}", node.corpus_frequency));
+        dot::LabelText::label(format!("{}\n[{}]", node.full_name, tags.join(", ")))
+    }
+
+    fn edge_label(&'a self, e: &&'a GraphEdge) -> dot::LabelText<'a> {
+        dot::LabelText::label(format!(
+            "#{} {} w={}",
+            e.input_param_index, e.call_type, e.corpus_weight
+        ))
+    }
+
+    /// 没有覆盖到的函数用虚线
+    fn node_style(&'a self, n: &usize) -> dot::Style {
+        if self.nodes[*n].visited { dot::Style::None } else { dot::Style::Dotted }
+    }
+
+    /// corpus中出现过的依赖加粗
+    fn edge_style(&'a self, e: &&'a GraphEdge) -> dot::Style {
+        if e.corpus_weight > 0.0 { dot::Style::Bold } else { dot::Style::None }
+    }
+}
+
+impl<'a> dot::GraphWalk<'a> for ExportedGraph {
+    type Node = usize;
+    type Edge = &'a GraphEdge;
+
+    fn nodes(&'a self) -> dot::Nodes<'a, usize> {
+        (0..self.nodes.len()).collect()
+    }
+
+    fn edges(&'a self) -> dot::Edges<'a, &'a GraphEdge> {
+        self.edges.iter().collect()
+    }
+
+    fn source(&'a self, e: &&'a GraphEdge) -> usize {
+        e.source
+    }
+
+    fn target(&'a self, e: &&'a GraphEdge) -> usize {
+        e.target
+    }
+}
+
+/// 设置了`FRIES_EXPORT_GRAPH`时，导出API依赖图
+pub(crate) fn export_graph(api_graph: &ApiGraph<'_>, support_generic: bool) {
+    let prefix = match std::env::var(EXPORT_GRAPH_ENV) {
+        Ok(prefix) => prefix,
+        Err(_) => return,
+    };
+    let graph = ExportedGraph::from_graph(api_graph, support_generic);
+    println!(
+        "API依赖图有{}个节点，{}条边，{}个连通分量",
+        graph.nodes.len(),
+        graph.edges.len(),
+        graph.components
+    );
+
+    let dot_path = format!("{}.dot", prefix);
+    let mut dot_content = Vec::new();
+    match dot::render(&graph, &mut dot_content).and_then(|_| fs::write(&dot_path, dot_content)) {
+        Ok(()) => println!("API依赖图导出到了{}", dot_path),
+        Err(e) => println!("无法导出API依赖图到{}: {}", dot_path, e),
+    }
+
+    let json_path = format!("{}.json", prefix);
+    let json_content = serde_json::to_string_pretty(&graph).unwrap();
+    match fs::write(&json_path, json_content) {
+        Ok(()) => println!("API依赖图导出到了{}", json_path),
+        Err(e) => println!("无法导出API依赖图到{}: {}", json_path, e),
+    }
+}
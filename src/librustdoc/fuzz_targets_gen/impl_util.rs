@@ -6,12 +6,14 @@
 use rustc_data_structures::fx::FxHashMap;
 use rustc_hir::def_id::DefId;
 use rustc_middle::ty::{TyCtxt, Visibility};
-use rustc_span::Symbol;
+use rustc_span::{sym, Symbol};
+use serde::{Deserialize, Serialize};
 use thin_vec::ThinVec;
 //FIXME: 是否需要为impl里面的method重新设计数据结构？目前沿用了ApiFunction,或者直接对ApiFunction进行扩展
 //两种函数目前相差一个defaultness
-use crate::fuzz_targets_gen::api_function::ApiUnsafety;
+use crate::fuzz_targets_gen::api_function::{ApiAsyncness, ApiUnsafety};
 use crate::fuzz_targets_gen::api_graph::ApiGraph;
+use crate::fuzz_targets_gen::api_id::ApiId;
 use crate::fuzz_targets_gen::prelude_type;
 
 #[derive(Debug, Clone)]
@@ -108,6 +110,55 @@ pub(crate) fn extract_impls_from_cache(
 
     api_graph.set_full_name_map(&full_name_map);
 
+    //记录实现了Clone的类型，被move的值可以先clone再传进去
+    //以及实现的其他标准库trait，用来打印错误值和检查trait的约定
+    let clone_trait = tcx.lang_items().clone_trait();
+    for (did, impls) in type_impl_maps {
+        for impl_ in impls {
+            if let Some(ref trait_) = impl_.inner_impl().trait_ {
+                if Some(trait_.def_id()) == clone_trait {
+                    api_graph.add_clone_type(*did);
+                }
+            }
+            if let Some(std_trait) = StdTrait::from_impl(impl_.inner_impl(), tcx) {
+                api_graph.add_std_trait(*did, std_trait);
+            }
+        }
+    }
+
+    //记录实现了Iterator的类型，之后为它们合成驱动迭代器的函数
+    let iterator_trait = tcx.get_diagnostic_item(sym::Iterator);
+    let double_ended_trait = tcx.get_diagnostic_item(sym::DoubleEndedIterator);
+    for (did, impls) in type_impl_maps {
+        if full_name_map._get_full_name(*did) == None {
+            continue;
+        }
+        for impl_ in impls {
+            let impl_ = impl_.inner_impl();
+            let trait_def_id = match impl_.trait_ {
+                Some(ref trait_) => trait_.def_id(),
+                None => continue,
+            };
+            //带泛型类型参数的impl暂时不考虑
+            if impl_.generics.params.iter().any(|param| param.is_type()) {
+                continue;
+            }
+            if Some(trait_def_id) == iterator_trait {
+                let item = impl_.items.iter().find_map(|item| match &*item.kind {
+                    clean::AssocTypeItem(typedef, _)
+                        if item.name.map_or(false, |name| name.as_str() == "Item") =>
+                    {
+                        Some(typedef.item_type.as_ref().unwrap_or(&typedef.type_).clone())
+                    }
+                    _ => None,
+                });
+                api_graph.add_iterator_type(&impl_.for_, item);
+            } else if Some(trait_def_id) == double_ended_trait {
+                api_graph.set_double_ended_iterator(&impl_.for_);
+            }
+        }
+    }
+
     //首先提取所有type的impl
     for (did, impls) in type_impl_maps {
         //只添加可以在full_name_map中找到对应的did的type
@@ -127,6 +178,11 @@ pub(crate) fn extract_impls_from_cache(
 
     //println!("analyse impl Trait for Type");
     for impl_ in &crate_impl_collection.impl_trait_for_types {
+        //标准库里的转换trait不在full_name_map中，单独处理
+        if let Some(conversion_trait) = ConversionTrait::from_impl(impl_, tcx) {
+            _analyse_conversion_impl(impl_, conversion_trait, tcx, &full_name_map, &mut api_graph);
+            continue;
+        }
         _analyse_impl(impl_, cache, tcx, &full_name_map, &mut api_graph);
     }
     //FIXME: 如何提取trait对应的impl，impl traitA for traitB? impl dyn traitA?下面的逻辑有误
@@ -145,6 +201,202 @@ pub(crate) fn extract_impls_from_cache(
     //println!("{:?}", crate_impl_collection);
 }
 
+/// 标准库中可以用来构造类型的trait，它们的impl会变成可以开始一个序列的函数
+/// 比如`"...".parse::<T>()`，`T::from(x)`，`T::try_from(x)`，`T::default()`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ConversionTrait {
+    FromStr,
+    From,
+    TryFrom,
+    Default,
+}
+
+/// 生成的代码里会用到的标准库trait（以及serde的trait）：打印错误值，以及检查trait的约定
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Serialize, Deserialize)]
+pub(crate) enum StdTrait {
+    Debug,
+    Display,
+    Error,
+    PartialEq,
+    Eq,
+    Hash,
+    PartialOrd,
+    Ord,
+    FromStr,
+    Serialize,
+    Deserialize,
+}
+
+impl StdTrait {
+    const DIAGNOSTIC_ITEMS: [StdTrait; 8] = [
+        StdTrait::Debug,
+        StdTrait::Display,
+        StdTrait::Error,
+        StdTrait::PartialEq,
+        StdTrait::Eq,
+        StdTrait::Hash,
+        StdTrait::PartialOrd,
+        StdTrait::Ord,
+    ];
+
+    fn diagnostic_name(&self) -> Option<Symbol> {
+        match self {
+            StdTrait::Debug => Some(sym::Debug),
+            StdTrait::Display => Some(sym::Display),
+            StdTrait::Error => Some(sym::Error),
+            StdTrait::PartialEq => Some(sym::PartialEq),
+            StdTrait::Eq => Some(sym::Eq),
+            StdTrait::Hash => Some(sym::Hash),
+            StdTrait::PartialOrd => Some(sym::PartialOrd),
+            StdTrait::Ord => Some(sym::Ord),
+            StdTrait::FromStr | StdTrait::Serialize | StdTrait::Deserialize => None,
+        }
+    }
+
+    pub(crate) fn from_impl(impl_: &clean::Impl, tcx: TyCtxt<'_>) -> Option<Self> {
+        let trait_ = impl_.trait_.as_ref()?;
+        let trait_def_id = trait_.def_id();
+        let diagnostic_trait = StdTrait::DIAGNOSTIC_ITEMS.iter().find(|std_trait| {
+            std_trait.diagnostic_name().and_then(|name| tcx.get_diagnostic_item(name))
+                == Some(trait_def_id)
+        });
+        let std_trait = match diagnostic_trait {
+            Some(std_trait) => *std_trait,
+            //没有diagnostic item的trait，通过所在的crate和名字判断
+            None => match (
+                tcx.crate_name(trait_def_id.krate).as_str(),
+                tcx.item_name(trait_def_id).as_str(),
+            ) {
+                ("core", "FromStr") => StdTrait::FromStr,
+                ("serde" | "serde_core", "Serialize") => StdTrait::Serialize,
+                ("serde" | "serde_core", "Deserialize") => StdTrait::Deserialize,
+                _ => return None,
+            },
+        };
+        //PartialEq<Rhs>和PartialOrd<Rhs>只考虑Rhs是Self的情况
+        if let Some(segment) = trait_.segments.last() {
+            if let clean::GenericArgs::AngleBracketed { args, .. } = &segment.args {
+                for arg in args.iter() {
+                    if let clean::GenericArg::Type(rhs) = arg {
+                        if *rhs != impl_.for_ {
+                            return None;
+                        }
+                    }
+                }
+            }
+        }
+        Some(std_trait)
+    }
+}
+
+impl ConversionTrait {
+    pub(crate) fn from_impl(impl_: &clean::Impl, tcx: TyCtxt<'_>) -> Option<Self> {
+        let trait_def_id = impl_.trait_.as_ref()?.def_id();
+        //和StdTrait一样通过所在的crate和名字判断，不依赖core里的diagnostic item
+        match tcx.crate_name(trait_def_id.krate).as_str() {
+            "core" => ConversionTrait::from_core_name(tcx.item_name(trait_def_id).as_str()),
+            _ => None,
+        }
+    }
+
+    /// core中trait的名字
+    pub(crate) fn from_core_name(trait_name: &str) -> Option<Self> {
+        match trait_name {
+            "FromStr" => Some(ConversionTrait::FromStr),
+            "From" => Some(ConversionTrait::From),
+            "TryFrom" => Some(ConversionTrait::TryFrom),
+            "Default" => Some(ConversionTrait::Default),
+            _ => None,
+        }
+    }
+
+    /// 生成的代码中需要use的trait路径
+    pub(crate) fn trait_path(&self) -> &'static str {
+        match self {
+            ConversionTrait::FromStr => "std::str::FromStr",
+            ConversionTrait::From => "std::convert::From",
+            ConversionTrait::TryFrom => "std::convert::TryFrom",
+            ConversionTrait::Default => "std::default::Default",
+        }
+    }
+
+    pub(crate) fn method_name(&self) -> &'static str {
+        match self {
+            ConversionTrait::FromStr => "from_str",
+            ConversionTrait::From => "from",
+            ConversionTrait::TryFrom => "try_from",
+            ConversionTrait::Default => "default",
+        }
+    }
+}
+
+/// 把转换trait的impl变成一个ApiFunction，调用方式是`Type::method(..)`，同时use对应的trait
+pub(crate) fn _analyse_conversion_impl(
+    impl_: &clean::Impl,
+    conversion_trait: ConversionTrait,
+    tcx: TyCtxt<'_>,
+    full_name_map: &FullNameMap,
+    api_graph: &mut ApiGraph<'_>,
+) {
+    //带泛型参数的impl，比如impl<T> From<T> for Foo，暂时不考虑
+    if impl_.generics.params.iter().any(|param| param.is_type()) {
+        return;
+    }
+    //只考虑当前crate中的类型
+    let type_full_name = match impl_.for_.def_id(api_graph.cache) {
+        Some(def_id) => match full_name_map._get_full_name(def_id) {
+            Some(type_name) => type_name.clone(),
+            None => return,
+        },
+        None => return,
+    };
+
+    let method_name = conversion_trait.method_name();
+    for item in &impl_.items {
+        if item.name.map_or(true, |name| name.as_str() != method_name) {
+            continue;
+        }
+        let method = match &*item.kind {
+            clean::MethodItem(method, _) => method,
+            _ => continue,
+        };
+        let fn_header = item.fn_header(tcx).unwrap();
+        let api_asyncness = ApiAsyncness::_get_asyncness_from_fnheader(&fn_header);
+        let output = api_asyncness._return_type(&method.decl);
+        let clean::FnDecl { inputs, .. } = method.decl.clone();
+        let inputs = api_util::_extract_input_types(&inputs)
+            .iter()
+            .map(|ty_| {
+                if is_param_self_type(ty_) {
+                    replace_self_type(ty_, &impl_.for_)
+                } else {
+                    ty_.clone()
+                }
+            })
+            .collect();
+        let output = api_util::_extract_output_type(&output).map(|ty_| {
+            if is_param_self_type(&ty_) { replace_self_type(&ty_, &impl_.for_) } else { ty_ }
+        });
+
+        let api_function = ApiFunction {
+            full_name: format!("{}::{}", type_full_name, method_name),
+            _generics: method.generics.clone(),
+            generic_substitutions: FxHashMap::default(),
+            inputs,
+            output,
+            _trait_full_path: Some(conversion_trait.trait_path().to_string()),
+            _unsafe_tag: ApiUnsafety::_get_unsafety_from_fnheader(&fn_header),
+            _async_tag: api_asyncness,
+            //trait impl里面的方法和trait的可见性一致，都是公开的
+            visibility: Visibility::Public,
+        };
+        if let Some(def_id) = item.item_id.as_def_id() {
+            api_graph.add_api_id(&api_function.full_name, ApiId::from_def_id(tcx, def_id));
+        }
+        api_graph.add_api_function(api_function);
+    }
+}
+
 fn full_path(paths: &Vec<Symbol>) -> String {
     let mut full = String::new();
     match paths.first() {
@@ -208,7 +460,10 @@ pub(crate) fn _analyse_impl(
             }
             clean::MethodItem(_method, _) => {
                 let decl = _method.decl.clone();
-                let clean::FnDecl { inputs, output, .. } = decl;
+                let fn_header = item.fn_header(tcx).unwrap();
+                let api_asyncness = ApiAsyncness::_get_asyncness_from_fnheader(&fn_header);
+                let output = api_asyncness._return_type(&decl);
+                let clean::FnDecl { inputs, .. } = decl;
                 let _generics = _method.generics.clone();
                 let mut inputs = api_util::_extract_input_types(&inputs);
                 let output = api_util::_extract_output_type(&output);
@@ -266,8 +521,7 @@ pub(crate) fn _analyse_impl(
                 method_name.push_str(item.name.as_ref().unwrap().as_str());
                 //println!("method name in impl:{:?}", method_name);
 
-                let api_unsafety =
-                    ApiUnsafety::_get_unsafety_from_fnheader(&item.fn_header(tcx).unwrap());
+                let api_unsafety = ApiUnsafety::_get_unsafety_from_fnheader(&fn_header);
 
                 let visibility = match item.visibility(tcx) {
                     Some(visib) => visib.expect_local(),
@@ -289,6 +543,7 @@ pub(crate) fn _analyse_impl(
                         output,
                         _trait_full_path: None,
                         _unsafe_tag: api_unsafety,
+                        _async_tag: api_asyncness,
                         visibility,
                     },
                     Some(_) => {
@@ -302,6 +557,7 @@ pub(crate) fn _analyse_impl(
                                 output,
                                 _trait_full_path: Some(real_trait_name.clone()),
                                 _unsafe_tag: api_unsafety,
+                                _async_tag: api_asyncness,
                                 visibility,
                             }
                         } else {
@@ -310,6 +566,9 @@ pub(crate) fn _analyse_impl(
                         }
                     }
                 };
+                if let Some(def_id) = item.item_id.as_def_id() {
+                    api_graph.add_api_id(&api_function.full_name, ApiId::from_def_id(tcx, def_id));
+                }
                 api_graph.add_api_function(api_function);
             }
             _ => {
@@ -321,7 +580,7 @@ pub(crate) fn _analyse_impl(
 
 //递归判断一个参数是否是self类型的
 //FIXME: 考虑在resolved path里面的括号里面可能存在self type
-fn is_param_self_type(ty_: &clean::Type) -> bool {
+pub(crate) fn is_param_self_type(ty_: &clean::Type) -> bool {
     if ty_.is_self_type() {
         return true;
     }
@@ -371,7 +630,7 @@ fn is_param_self_type(ty_: &clean::Type) -> bool {
 }
 
 //将self类型替换为相应的结构体类型
-fn replace_self_type(self_type: &clean::Type, impl_type: &clean::Type) -> clean::Type {
+pub(crate) fn replace_self_type(self_type: &clean::Type, impl_type: &clean::Type) -> clean::Type {
     if self_type.is_self_type() {
         return impl_type.clone();
     }
@@ -0,0 +1,259 @@
+//! 互逆API对的性质测试
+//! corpus里经常出现`encode`/`decode`这样的调用链：A的返回值直接传给B，并且B的返回值类型和A的参数类型相同
+//! ExtractInfo把这样的API对输出到inversepairinfo，人工确认之后整理到inversepairinfo.txt
+//! 对确认过的每一对生成一个测试：用fuzzable的x调用A，再把结果交给B，检查`B(A(x)) == x`
+//! 只考虑A的参数都是fuzzable类型，并且x是引用或者Copy的情况，调用A之后x还可以拿来比较
+//! B的返回值要能通过`_same_type`转换成x传给A时的类型（必要时加上`&`或者`*`），并且这个类型实现了PartialEq
+//! API对的文件由环境变量`FRIES_INVERSE_PAIR_FILE`指定，默认是corpus目录下的inversepairinfo.txt
+//! 文件里的名字是corpus中的写法，corpus文件的inverse_pairs记下了同名API对在调用点上的ApiId，
+//! 和其他corpus信息一样通过ApiResolver对应到库模式的full_name
+
+use crate::clean;
+use crate::fuzz_targets_gen::api_graph::{ApiGraph, ApiType};
+use crate::fuzz_targets_gen::api_id::ApiResolver;
+use crate::fuzz_targets_gen::api_sequence::{ApiSequence, ParamType};
+use crate::fuzz_targets_gen::api_util;
+use crate::fuzz_targets_gen::call_type::CallType;
+use crate::fuzz_targets_gen::corpus;
+use crate::fuzz_targets_gen::impl_util::StdTrait;
+use itertools::Itertools;
+use rustc_data_structures::fx::FxHashSet;
+use std::path::PathBuf;
+
+/// B(A(x)) != x 时输出的前缀，用来和其他crash区分
+static MISMATCH_PREFIX: &'static str = "FRIES_INVERSE_PAIR_MISMATCH";
+static INVERSE_PAIR_FILE_ENV: &'static str = "FRIES_INVERSE_PAIR_FILE";
+
+/// 序列中的一对互逆调用
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub(crate) struct InversePair {
+    /// 传给A的fuzzable变量，也就是x
+    pub(crate) input: usize,
+    /// A和B在序列中的位置
+    pub(crate) forward_call: usize,
+    pub(crate) inverse_call: usize,
+    /// x是A的第几个参数
+    pub(crate) input_param: usize,
+    /// B的返回值转换成x传给A时的类型
+    pub(crate) output_call_type: CallType,
+}
+
+fn _inverse_pair_file_path(lib_name: &str) -> PathBuf {
+    match std::env::var(INVERSE_PAIR_FILE_ENV) {
+        Ok(path) => PathBuf::from(path),
+        Err(_) => corpus::corpus_dir(lib_name).join("inversepairinfo.txt"),
+    }
+}
+
+/// 读取人工确认过的API对，格式和depinfo.txt一样
+fn _read_inverse_pairs(lib_name: &str) -> Vec<(String, String)> {
+    use std::fs::File;
+    use std::io::{BufRead, BufReader};
+
+    let mut pairs = Vec::new();
+    if let Ok(file) = File::open(_inverse_pair_file_path(lib_name)) {
+        let reader = BufReader::new(file);
+        for line in reader.lines() {
+            let line = line.unwrap();
+            let parts = line.split("  |  ").collect_vec();
+            if parts.len() < 3 {
+                continue;
+            }
+            if let Some((forward, inverse)) =
+                parts[2].split("   ").map(|x| x.to_string()).collect_tuple()
+            {
+                pairs.push((forward, inverse));
+            }
+        }
+    }
+    pairs
+}
+
+/// 确认过的API对在库模式中的full_name
+/// corpus里同名的API对可能对应不同的ApiId，每个都对应一次；corpus里没有的只按名字对应
+fn _resolve_inverse_pairs(api_graph: &ApiGraph<'_>, lib_name: &str) -> Vec<(String, String)> {
+    let confirmed = _read_inverse_pairs(lib_name);
+    if confirmed.is_empty() {
+        return Vec::new();
+    }
+    let corpus_pairs = corpus::_read_corpus_files(lib_name)
+        .into_iter()
+        .flat_map(|corpus_file| corpus_file.inverse_pairs.into_iter())
+        .collect_vec();
+    let resolver = ApiResolver::new(api_graph);
+    let mut resolved = FxHashSet::default();
+    for (forward, inverse) in confirmed {
+        let mut api_ids = corpus_pairs
+            .iter()
+            .filter(|pair| pair.first == forward && pair.second == inverse)
+            .map(|pair| (pair.first_id.as_ref(), pair.second_id.as_ref()))
+            .collect_vec();
+        if api_ids.is_empty() {
+            api_ids.push((None, None));
+        }
+        for (forward_id, inverse_id) in api_ids {
+            match (resolver.resolve(&forward, forward_id), resolver.resolve(&inverse, inverse_id)) {
+                (Some(forward), Some(inverse)) => {
+                    resolved.insert((forward, inverse));
+                }
+                _ => println!("互逆API对在库中找不到: {} -> {}", forward, inverse),
+            }
+        }
+    }
+    resolved.into_iter().sorted().collect()
+}
+
+/// 为每一对确认过的API生成`B(A(x)) == x`的测试序列
+pub(crate) fn _inverse_pair_sequences(
+    api_graph: &ApiGraph<'_>,
+    lib_name: &str,
+) -> Vec<ApiSequence> {
+    let mut res = Vec::new();
+    for (forward, inverse) in _resolve_inverse_pairs(api_graph, lib_name) {
+        let find = |name: &String| {
+            api_graph.api_functions.iter().position(|api_fun| api_fun.full_name == *name)
+        };
+        let sequence = match (find(&forward), find(&inverse)) {
+            (Some(forward_index), Some(inverse_index)) => {
+                _inverse_pair_sequence(api_graph, forward_index, inverse_index)
+            }
+            _ => None,
+        };
+        match sequence {
+            Some(sequence) => {
+                println!("互逆API对: {} -> {}", forward, inverse);
+                res.push(sequence);
+            }
+            None => println!("没有为互逆API对生成测试: {} -> {}", forward, inverse),
+        }
+    }
+    res
+}
+
+fn _inverse_pair_sequence(
+    api_graph: &ApiGraph<'_>,
+    forward_index: usize,
+    inverse_index: usize,
+) -> Option<ApiSequence> {
+    //A的参数必须都是fuzzable的，x是其中第一个调用之后还能用的
+    let sequence =
+        api_graph.is_fun_satisfied(&ApiType::BareFunction, forward_index, &ApiSequence::new())?;
+    let forward_function = &api_graph.api_functions[forward_index];
+    let (input_param, input) = forward_function
+        .inputs
+        .iter()
+        .zip(sequence.functions[0].params.iter())
+        .enumerate()
+        .find_map(|(input_param, (input_type, (param_type, param_index, _)))| {
+            let usable_after_call = match input_type {
+                clean::Type::BorrowedRef { mutability, .. } => !mutability.is_mut(),
+                _ => api_util::_copy_type(input_type),
+            };
+            if *param_type == ParamType::_FuzzableType && usable_after_call {
+                Some((input_param, *param_index))
+            } else {
+                None
+            }
+        })?;
+
+    //B的返回值要能转换成x的类型，并且可以比较
+    let input_type = &forward_function.inputs[input_param];
+    let output_type = api_graph.api_functions[inverse_index].output.as_ref()?;
+    let output_call_type = api_util::_same_type(
+        output_type,
+        input_type,
+        true,
+        api_graph.cache,
+        &api_graph.full_name_map,
+    );
+    if !_is_plain_conversion(&output_call_type) || !_is_partial_eq(api_graph, input_type) {
+        return None;
+    }
+
+    //B至少有一个参数来自A的返回值
+    let mut sequence =
+        api_graph.is_fun_satisfied(&ApiType::BareFunction, inverse_index, &sequence)?;
+    if !sequence.functions[1].params.iter().any(|(param_type, param_index, _)| {
+        *param_type == ParamType::_FunctionReturn && *param_index == 0
+    }) {
+        return None;
+    }
+    sequence._inverse_pair = Some(InversePair {
+        input,
+        forward_call: 0,
+        inverse_call: 1,
+        input_param,
+        output_call_type,
+    });
+    Some(sequence)
+}
+
+/// 只接受取引用和解引用，unwrap之类的转换需要额外的辅助函数，也会改变比较的含义
+fn _is_plain_conversion(call_type: &CallType) -> bool {
+    match call_type {
+        CallType::_DirectCall => true,
+        CallType::_BorrowedRef(inner) | CallType::_Deref(inner) => _is_plain_conversion(inner),
+        _ => false,
+    }
+}
+
+/// 引用去掉之后的类型是否实现了PartialEq
+fn _is_partial_eq(api_graph: &ApiGraph<'_>, ty: &clean::Type) -> bool {
+    match ty {
+        clean::Type::BorrowedRef { type_, .. } => _is_partial_eq(api_graph, type_),
+        clean::Type::Primitive(_) => true,
+        clean::Type::Slice(inner) | clean::Type::Array(inner, _) => {
+            _is_partial_eq(api_graph, inner)
+        }
+        clean::Type::Tuple(types) => types.iter().all(|ty| _is_partial_eq(api_graph, ty)),
+        _ => match ty.def_id(api_graph.cache) {
+            Some(def_id) => api_graph.has_std_trait(def_id, StdTrait::PartialEq),
+            None => false,
+        },
+    }
+}
+
+/// B调用之后的检查
+pub(crate) fn _inverse_check(
+    api_graph: &ApiGraph<'_>,
+    sequence: &ApiSequence,
+    inverse_pair: &InversePair,
+    param_prefix: &str,
+    local_param_prefix: &str,
+) -> String {
+    let forward_call = &sequence.functions[inverse_pair.forward_call];
+    let inverse_call = &sequence.functions[inverse_pair.inverse_call];
+    let cache = api_graph.cache;
+    let full_name_map = &api_graph.full_name_map;
+    //两边都写成x传给A时的样子
+    let output = inverse_pair.output_call_type._to_call_string(
+        &format!("{}{}", local_param_prefix, inverse_pair.inverse_call),
+        cache,
+        full_name_map,
+    );
+    let input = forward_call.params[inverse_pair.input_param].2._to_call_string(
+        &format!("{}{}", param_prefix, inverse_pair.input),
+        cache,
+        full_name_map,
+    );
+    let condition = format!("{} == {}", output, input);
+    format!(
+        "_check_inverse({}, \"{}\", \"{}\");",
+        condition,
+        api_graph.api_functions[forward_call.func.1].full_name,
+        api_graph.api_functions[inverse_call.func.1].full_name
+    )
+}
+
+/// 测试文件中检查用到的辅助函数
+pub(crate) fn _inverse_helper_function() -> String {
+    format!(
+        "fn _check_inverse(holds: bool, forward: &str, inverse: &str) {{
+    if !holds {{
+        eprintln!(\"{prefix}: `{{}}` does not invert `{{}}`\", inverse, forward);
+        std::process::abort();
+    }}
+}}\n",
+        prefix = MISMATCH_PREFIX
+    )
+}
@@ -0,0 +1,233 @@
+//! 驱动API返回的迭代器
+//! 通过Cache中的impl找到实现了Iterator的类型，再加上返回`impl Iterator<Item = ..>`的函数，
+//! 为每种迭代器合成几个辅助函数：
+//! 1. `_iterator_next`和`_iterator_next_back`：取出一个元素，元素可以作为后续调用的参数
+//! 2. `_drive_iterator`和`_drive_double_ended_iterator`：由输入控制步数，调用next、size_hint和rev，
+//!    并检查size_hint的约定。生成序列之后，它们被追加在序列的末尾
+//!
+//! 辅助函数的代码是共用的，但在图中每种迭代器的辅助函数有自己的名字，比如`url::PathSegments::__fries_next`，
+//! 这样focus_apis、excluded_functions、api_ids等按full_name索引的地方不会把不同迭代器混在一起
+
+use crate::clean::{self, GenericArgs, GenericBound, Term, TypeBindingKind};
+use crate::fuzz_targets_gen::api_function::{ApiAsyncness, ApiFunction, ApiUnsafety};
+use rustc_data_structures::fx::FxHashMap;
+use rustc_hir::Mutability;
+use rustc_middle::ty::Visibility;
+
+pub(crate) static ITERATOR_NEXT: &'static str = "_iterator_next";
+pub(crate) static ITERATOR_NEXT_BACK: &'static str = "_iterator_next_back";
+pub(crate) static DRIVE_ITERATOR: &'static str = "_drive_iterator";
+pub(crate) static DRIVE_DOUBLE_ENDED_ITERATOR: &'static str = "_drive_double_ended_iterator";
+
+/// 图中辅助函数名字的后缀和对应的辅助函数
+static HELPER_SUFFIXES: [(&'static str, &'static str); 4] = [
+    ("__fries_next", ITERATOR_NEXT),
+    ("__fries_next_back", ITERATOR_NEXT_BACK),
+    ("__fries_drive", DRIVE_ITERATOR),
+    ("__fries_drive_double_ended", DRIVE_DOUBLE_ENDED_ITERATOR),
+];
+
+/// 一种迭代器
+#[derive(Debug, Clone)]
+pub(crate) struct IteratorInfo {
+    /// 迭代器本身的类型，结构体或者`impl Iterator<..>`
+    pub(crate) type_: clean::Type,
+    /// Item的类型，不知道的时候为None，此时只驱动，不取出元素
+    pub(crate) item: Option<clean::Type>,
+    pub(crate) double_ended: bool,
+}
+
+impl IteratorInfo {
+    pub(crate) fn new(type_: &clean::Type) -> Self {
+        IteratorInfo { type_: type_.clone(), item: None, double_ended: false }
+    }
+
+    /// 从返回值类型`impl Iterator<Item = ..>`中提取
+    pub(crate) fn from_impl_trait(ty: &clean::Type) -> Option<Self> {
+        let bounds = match ty {
+            clean::Type::ImplTrait(bounds) => bounds,
+            _ => return None,
+        };
+        let mut info = IteratorInfo::new(ty);
+        let mut is_iterator = false;
+        for bound in bounds {
+            let poly_trait = match bound {
+                GenericBound::TraitBound(poly_trait, _) => poly_trait,
+                GenericBound::Outlives(..) => continue,
+            };
+            let segment = match poly_trait.trait_.segments.last() {
+                Some(segment) => segment,
+                None => continue,
+            };
+            match segment.name.as_str() {
+                "Iterator" | "ExactSizeIterator" | "FusedIterator" => is_iterator = true,
+                "DoubleEndedIterator" => {
+                    is_iterator = true;
+                    info.double_ended = true;
+                }
+                _ => continue,
+            }
+            if let GenericArgs::AngleBracketed { bindings, .. } = &segment.args {
+                for binding in bindings.iter() {
+                    if binding.assoc.name.as_str() != "Item" {
+                        continue;
+                    }
+                    if let TypeBindingKind::Equality { term: Term::Type(item) } = &binding.kind {
+                        info.item = Some(item.clone());
+                    }
+                }
+            }
+        }
+        if is_iterator { Some(info) } else { None }
+    }
+
+    /// 为这种迭代器合成的辅助函数，`type_name`用来区分不同迭代器的辅助函数
+    pub(crate) fn to_api_functions(&self, type_name: &str) -> Vec<ApiFunction> {
+        let mut_ref = clean::Type::BorrowedRef {
+            lifetime: None,
+            mutability: Mutability::Mut,
+            type_: Box::new(self.type_.clone()),
+        };
+        let mut res = Vec::new();
+        if let Some(item) = &self.item {
+            res.push(_helper_api_function(
+                type_name,
+                ITERATOR_NEXT,
+                vec![mut_ref.clone()],
+                Some(item.clone()),
+            ));
+            if self.double_ended {
+                res.push(_helper_api_function(
+                    type_name,
+                    ITERATOR_NEXT_BACK,
+                    vec![mut_ref.clone()],
+                    Some(item.clone()),
+                ));
+            }
+        }
+        let drive_name =
+            if self.double_ended { DRIVE_DOUBLE_ENDED_ITERATOR } else { DRIVE_ITERATOR };
+        let steps = clean::Type::Primitive(clean::PrimitiveType::U8);
+        res.push(_helper_api_function(type_name, drive_name, vec![mut_ref, steps], None));
+        res
+    }
+}
+
+/// 是否是追加在序列末尾的驱动函数
+pub(crate) fn is_drive_function(full_name: &str) -> bool {
+    match _helper_function_name(full_name) {
+        Some(name) => name == DRIVE_ITERATOR || name == DRIVE_DOUBLE_ENDED_ITERATOR,
+        None => false,
+    }
+}
+
+/// 图中的辅助函数在测试文件中实际调用的函数名，不是迭代器辅助函数的返回None
+pub(crate) fn _helper_function_name(full_name: &str) -> Option<&'static str> {
+    let suffix = full_name.rsplit("::").next()?;
+    HELPER_SUFFIXES
+        .iter()
+        .find(|(helper_suffix, _)| *helper_suffix == suffix)
+        .map(|(_, name)| *name)
+}
+
+fn _helper_api_function(
+    type_name: &str,
+    name: &str,
+    inputs: Vec<clean::Type>,
+    output: Option<clean::Type>,
+) -> ApiFunction {
+    let suffix = HELPER_SUFFIXES.iter().find(|(_, helper)| *helper == name).unwrap().0;
+    ApiFunction {
+        full_name: format!("{}::{}", type_name, suffix),
+        _generics: clean::Generics::default(),
+        generic_substitutions: FxHashMap::default(),
+        inputs,
+        output,
+        _trait_full_path: None,
+        _unsafe_tag: ApiUnsafety::Normal,
+        _async_tag: ApiAsyncness::NotAsync,
+        visibility: Visibility::Public,
+    }
+}
+
+/// 辅助函数在测试文件中的定义，不是迭代器辅助函数的返回None
+pub(crate) fn _helper_function_code(full_name: &str) -> Option<&'static str> {
+    let name = _helper_function_name(full_name)?;
+    if name == ITERATOR_NEXT {
+        Some(_iterator_next())
+    } else if name == ITERATOR_NEXT_BACK {
+        Some(_iterator_next_back())
+    } else if name == DRIVE_ITERATOR {
+        Some(_drive_iterator())
+    } else if name == DRIVE_DOUBLE_ENDED_ITERATOR {
+        Some(_drive_double_ended_iterator())
+    } else {
+        None
+    }
+}
+
+fn _iterator_next() -> &'static str {
+    "fn _iterator_next<I: Iterator>(iter: &mut I) -> I::Item {
+    match iter.next() {
+        Some(item) => item,
+        None => {
+            use std::process;
+            process::exit(0);
+        }
+    }
+}\n"
+}
+
+fn _iterator_next_back() -> &'static str {
+    "fn _iterator_next_back<I: DoubleEndedIterator>(iter: &mut I) -> I::Item {
+    match iter.by_ref().rev().next() {
+        Some(item) => item,
+        None => {
+            use std::process;
+            process::exit(0);
+        }
+    }
+}\n"
+}
+
+fn _drive_iterator() -> &'static str {
+    "fn _drive_iterator<I: Iterator>(iter: &mut I, steps: u8) {
+    let (lower, upper) = iter.size_hint();
+    if let Some(upper) = upper {
+        assert!(lower <= upper, \"size_hint: lower bound {} > upper bound {}\", lower, upper);
+    }
+    let mut count = 0;
+    for _ in 0..steps % 32 {
+        if iter.next().is_none() {
+            assert!(count >= lower, \"iterator ended after {} items, size_hint lower bound was {}\", count, lower);
+            break;
+        }
+        count += 1;
+        if let Some(upper) = upper {
+            assert!(count <= upper, \"iterator yielded {} items, size_hint upper bound was {}\", count, upper);
+        }
+    }
+}\n"
+}
+
+fn _drive_double_ended_iterator() -> &'static str {
+    "fn _drive_double_ended_iterator<I: DoubleEndedIterator>(iter: &mut I, steps: u8) {
+    let (lower, upper) = iter.size_hint();
+    if let Some(upper) = upper {
+        assert!(lower <= upper, \"size_hint: lower bound {} > upper bound {}\", lower, upper);
+    }
+    let mut count = 0;
+    for i in 0..steps % 16 {
+        // 高4位决定每一步从哪一端取
+        let item = if (steps >> (4 + i % 4)) & 1 == 0 { iter.next() } else { iter.by_ref().rev().next() };
+        if item.is_none() {
+            assert!(count >= lower, \"iterator ended after {} items, size_hint lower bound was {}\", count, lower);
+            break;
+        }
+        count += 1;
+        if let Some(upper) = upper {
+            assert!(count <= upper, \"iterator yielded {} items, size_hint upper bound was {}\", count, upper);
+        }
+    }
+}\n"
+}
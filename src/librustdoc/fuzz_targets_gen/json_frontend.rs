@@ -0,0 +1,704 @@
+//! 从rustdoc JSON抽取API的前端，待测库不需要用打过补丁的编译器编译
+//! 1. 用输出格式版本和这棵树一致的nightly生成JSON：
+//!    `RUSTDOCFLAGS="-Z unstable-options --output-format json" cargo +nightly doc`
+//! 2. `rustdoc fries-json <crate.json>`从JSON抽取ApiFunction、impl里面的方法和模块可见性，
+//!    之后的过滤、依赖分析、生成序列和写文件与编译器前端共用（context::_analyse_api_graph和_fries_generate）
+//!    设置了`FRIES_CACHE_DIR`并且JSON没变时，直接从缓存恢复分析好的API图，不再解析impl和模块
+//! JSON中的id形如`crate:index[:name]`，直接当作DefId，只用来区分类型，不会拿去查询编译器
+//! 限制：
+//! 1. 固定用这棵树里的rustdoc-json-types解析，只接受SUPPORTED_FORMAT_VERSIONS中的格式版本，
+//!    其他版本的字段可能已经变了，直接报错，不尽力解析
+//! 2. 没有合成结构体和枚举的构造函数（需要字段的可见性和类型检查），也没有记录其他标准库trait的impl（打印错误值和检查约定用）
+//! 3. 函数指针、`<T as Trait>::Name`和常量泛型参数不支持，用到它们的API会被过滤掉
+
+use crate::clean::{self, PrimitiveType};
+use crate::formats::cache::Cache;
+use crate::formats::item_type::ItemType;
+use crate::fuzz_targets_gen::analysis_cache::{self, CacheKey};
+use crate::fuzz_targets_gen::api_function::{ApiAsyncness, ApiFunction, ApiUnsafety};
+use crate::fuzz_targets_gen::api_graph::ApiGraph;
+use crate::fuzz_targets_gen::context;
+use crate::fuzz_targets_gen::impl_util::{self, ConversionTrait, FullNameMap};
+use crate::fuzz_targets_gen::prelude_type;
+use itertools::Itertools;
+use rustc_data_structures::fx::{FxHashMap, FxHasher};
+use rustc_hir::def::{DefKind, Res};
+use rustc_hir::def_id::{CrateNum, DefId, DefIndex, CRATE_DEF_ID};
+use rustc_hir::{self as hir, Mutability};
+use rustc_middle::ty::Visibility;
+use rustc_span::Symbol;
+use rustdoc_json_types as json;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::time::Instant;
+use thin_vec::ThinVec;
+
+/// 过滤的原因，用`rustdoc explain`查询时显示
+static UNSUPPORTED_REASON: &'static str = "type not supported by the rustdoc JSON front end";
+/// 可以解析的格式版本，schema是这棵树里的rustdoc-json-types
+static SUPPORTED_FORMAT_VERSIONS: [u32; 1] = [json::FORMAT_VERSION];
+
+/// JSON的id形如`crate:index`或者`crate:index:name`，合成的impl（`a:`、`b:`开头）和原生类型（`p:`）没有DefId
+fn _def_id(id: &json::Id) -> Option<DefId> {
+    let mut parts = id.0.split(':');
+    let krate = parts.next()?.parse::<u32>().ok()?;
+    let index = parts.next()?.parse::<u32>().ok()?;
+    Some(DefId { krate: CrateNum::from_u32(krate), index: DefIndex::from_u32(index) })
+}
+
+fn _item_type(kind: &json::ItemKind) -> Option<ItemType> {
+    match kind {
+        json::ItemKind::Module => Some(ItemType::Module),
+        json::ItemKind::Struct => Some(ItemType::Struct),
+        json::ItemKind::Union => Some(ItemType::Union),
+        json::ItemKind::Enum => Some(ItemType::Enum),
+        json::ItemKind::Variant => Some(ItemType::Variant),
+        json::ItemKind::Function => Some(ItemType::Function),
+        json::ItemKind::Typedef => Some(ItemType::Typedef),
+        json::ItemKind::Trait => Some(ItemType::Trait),
+        json::ItemKind::TraitAlias => Some(ItemType::TraitAlias),
+        json::ItemKind::Constant => Some(ItemType::Constant),
+        json::ItemKind::Static => Some(ItemType::Static),
+        json::ItemKind::ForeignType => Some(ItemType::ForeignType),
+        json::ItemKind::Primitive => Some(ItemType::Primitive),
+        _ => None,
+    }
+}
+
+fn _def_kind(item_type: Option<ItemType>) -> DefKind {
+    match item_type {
+        Some(ItemType::Enum) => DefKind::Enum,
+        Some(ItemType::Union) => DefKind::Union,
+        Some(ItemType::Trait) => DefKind::Trait,
+        Some(ItemType::TraitAlias) => DefKind::TraitAlias,
+        Some(ItemType::Typedef) => DefKind::TyAlias,
+        Some(ItemType::ForeignType) => DefKind::ForeignTy,
+        _ => DefKind::Struct,
+    }
+}
+
+/// 只有公开和私有的区别，过滤时只看是否公开
+/// JSON中impl里面的方法是继承的可见性（Default），和编译器前端一样当作公开
+fn _visibility(visibility: &json::Visibility, inherited_is_public: bool) -> Visibility {
+    match visibility {
+        json::Visibility::Public => Visibility::Public,
+        json::Visibility::Default if inherited_is_public => Visibility::Public,
+        _ => Visibility::Restricted(CRATE_DEF_ID),
+    }
+}
+
+fn _lifetime(name: &String) -> clean::Lifetime {
+    clean::Lifetime(Symbol::intern(name))
+}
+
+/// async函数的返回值在JSON中是`impl Future<Output = T>`，取出.await之后的T
+fn _sugared_async_output(output: &json::Type) -> Option<&json::Type> {
+    let bounds = match output {
+        json::Type::ImplTrait(bounds) => bounds,
+        _ => return None,
+    };
+    bounds.iter().find_map(|bound| match bound {
+        json::GenericBound::TraitBound { trait_, .. } if trait_.name.ends_with("Future") => {
+            match trait_.args.as_deref() {
+                Some(json::GenericArgs::AngleBracketed { bindings, .. }) => {
+                    bindings.iter().find_map(|binding| match &binding.binding {
+                        json::TypeBindingKind::Equality(json::Term::Type(ty))
+                            if binding.name == "Output" =>
+                        {
+                            Some(ty)
+                        }
+                        _ => None,
+                    })
+                }
+                _ => None,
+            }
+        }
+        _ => None,
+    })
+}
+
+/// 把JSON中的类型转换成clean::Type，不支持的类型返回None
+struct JsonConverter<'a> {
+    krate: &'a json::Crate,
+}
+
+impl<'a> JsonConverter<'a> {
+    fn convert_type(&self, ty: &json::Type) -> Option<clean::Type> {
+        let ty = match ty {
+            json::Type::ResolvedPath(path) => clean::Type::Path { path: self.convert_path(path)? },
+            json::Type::DynTrait(dyn_trait) => clean::Type::DynTrait(
+                dyn_trait
+                    .traits
+                    .iter()
+                    .map(|poly_trait| {
+                        Some(clean::PolyTrait {
+                            trait_: self.convert_path(&poly_trait.trait_)?,
+                            generic_params: Vec::new(),
+                        })
+                    })
+                    .collect::<Option<Vec<_>>>()?,
+                dyn_trait.lifetime.as_ref().map(_lifetime),
+            ),
+            json::Type::Generic(name) => clean::Type::Generic(Symbol::intern(name)),
+            json::Type::Primitive(name) => {
+                clean::Type::Primitive(PrimitiveType::from_symbol(Symbol::intern(name))?)
+            }
+            json::Type::Tuple(types) => clean::Type::Tuple(
+                types.iter().map(|ty| self.convert_type(ty)).collect::<Option<Vec<_>>>()?,
+            ),
+            json::Type::Slice(ty) => clean::Type::Slice(Box::new(self.convert_type(ty)?)),
+            json::Type::Array { type_, len } => {
+                clean::Type::Array(Box::new(self.convert_type(type_)?), len.clone().into())
+            }
+            json::Type::ImplTrait(bounds) => clean::Type::ImplTrait(self.convert_bounds(bounds)?),
+            json::Type::Infer => clean::Type::Infer,
+            json::Type::RawPointer { mutable, type_ } => clean::Type::RawPointer(
+                if *mutable { Mutability::Mut } else { Mutability::Not },
+                Box::new(self.convert_type(type_)?),
+            ),
+            json::Type::BorrowedRef { lifetime, mutable, type_ } => clean::Type::BorrowedRef {
+                lifetime: lifetime.as_ref().map(_lifetime),
+                mutability: if *mutable { Mutability::Mut } else { Mutability::Not },
+                type_: Box::new(self.convert_type(type_)?),
+            },
+            json::Type::FunctionPointer(_) | json::Type::QualifiedPath { .. } => return None,
+        };
+        Some(ty)
+    }
+
+    /// 和clean::Path一样，按照写出来的路径分段，泛型参数放在最后一段
+    fn convert_path(&self, path: &json::Path) -> Option<clean::Path> {
+        let def_id = _def_id(&path.id)?;
+        let item_type =
+            self.krate.paths.get(&path.id).and_then(|summary| _item_type(&summary.kind));
+        let names = path.name.split("::").collect_vec();
+        let mut segments = ThinVec::new();
+        for (index, name) in names.iter().enumerate() {
+            let args = match &path.args {
+                Some(args) if index + 1 == names.len() => self.convert_generic_args(args)?,
+                _ => clean::GenericArgs::AngleBracketed {
+                    args: Box::new([]),
+                    bindings: ThinVec::new(),
+                },
+            };
+            segments.push(clean::PathSegment { name: Symbol::intern(name), args });
+        }
+        Some(clean::Path { res: Res::Def(_def_kind(item_type), def_id), segments })
+    }
+
+    fn convert_generic_args(&self, args: &json::GenericArgs) -> Option<clean::GenericArgs> {
+        let args = match args {
+            json::GenericArgs::AngleBracketed { args, bindings } => {
+                clean::GenericArgs::AngleBracketed {
+                    args: args
+                        .iter()
+                        .map(|arg| match arg {
+                            json::GenericArg::Lifetime(name) => {
+                                Some(clean::GenericArg::Lifetime(_lifetime(name)))
+                            }
+                            json::GenericArg::Type(ty) => {
+                                Some(clean::GenericArg::Type(self.convert_type(ty)?))
+                            }
+                            json::GenericArg::Const(_) => None,
+                            json::GenericArg::Infer => Some(clean::GenericArg::Infer),
+                        })
+                        .collect::<Option<Vec<_>>>()?
+                        .into(),
+                    bindings: bindings
+                        .iter()
+                        .map(|binding| self.convert_binding(binding))
+                        .collect::<Option<ThinVec<_>>>()?,
+                }
+            }
+            json::GenericArgs::Parenthesized { inputs, output } => {
+                clean::GenericArgs::Parenthesized {
+                    inputs: inputs
+                        .iter()
+                        .map(|ty| self.convert_type(ty))
+                        .collect::<Option<Vec<_>>>()?
+                        .into(),
+                    output: match output {
+                        Some(output) => Some(Box::new(self.convert_type(output)?)),
+                        None => None,
+                    },
+                }
+            }
+        };
+        Some(args)
+    }
+
+    fn convert_binding(&self, binding: &json::TypeBinding) -> Option<clean::TypeBinding> {
+        let kind = match &binding.binding {
+            json::TypeBindingKind::Equality(json::Term::Type(ty)) => {
+                clean::TypeBindingKind::Equality { term: clean::Term::Type(self.convert_type(ty)?) }
+            }
+            json::TypeBindingKind::Equality(json::Term::Constant(_)) => return None,
+            json::TypeBindingKind::Constraint(bounds) => {
+                clean::TypeBindingKind::Constraint { bounds: self.convert_bounds(bounds)? }
+            }
+        };
+        Some(clean::TypeBinding {
+            assoc: clean::PathSegment {
+                name: Symbol::intern(&binding.name),
+                args: self.convert_generic_args(&binding.args)?,
+            },
+            kind,
+        })
+    }
+
+    fn convert_bounds(&self, bounds: &Vec<json::GenericBound>) -> Option<Vec<clean::GenericBound>> {
+        bounds
+            .iter()
+            .map(|bound| match bound {
+                json::GenericBound::TraitBound { trait_, modifier, .. } => {
+                    let modifier = match modifier {
+                        json::TraitBoundModifier::None => hir::TraitBoundModifier::None,
+                        json::TraitBoundModifier::Maybe => hir::TraitBoundModifier::Maybe,
+                        json::TraitBoundModifier::MaybeConst => hir::TraitBoundModifier::MaybeConst,
+                    };
+                    Some(clean::GenericBound::TraitBound(
+                        clean::PolyTrait {
+                            trait_: self.convert_path(trait_)?,
+                            generic_params: Vec::new(),
+                        },
+                        modifier,
+                    ))
+                }
+                json::GenericBound::Outlives(name) => {
+                    Some(clean::GenericBound::Outlives(_lifetime(name)))
+                }
+            })
+            .collect()
+    }
+
+    /// 泛型参数没有DefId，用crate根代替，只有is_type和约束会被用到
+    fn convert_generics(&self, generics: &json::Generics) -> Option<clean::Generics> {
+        let params = generics
+            .params
+            .iter()
+            .map(|param| {
+                let name = Symbol::intern(&param.name);
+                let kind = match &param.kind {
+                    json::GenericParamDefKind::Lifetime { outlives } => {
+                        clean::GenericParamDefKind::Lifetime {
+                            outlives: outlives.iter().map(_lifetime).collect(),
+                        }
+                    }
+                    json::GenericParamDefKind::Type { bounds, default, synthetic } => {
+                        clean::GenericParamDefKind::Type {
+                            did: CRATE_DEF_ID.to_def_id(),
+                            bounds: self.convert_bounds(bounds)?,
+                            default: match default {
+                                Some(default) => Some(Box::new(self.convert_type(default)?)),
+                                None => None,
+                            },
+                            synthetic: *synthetic,
+                        }
+                    }
+                    json::GenericParamDefKind::Const { .. } => return None,
+                };
+                Some(clean::GenericParamDef { name, kind })
+            })
+            .collect::<Option<ThinVec<_>>>()?;
+        //只保留类型上的约束，回调参数需要where子句里的Fn约束
+        let where_predicates = generics
+            .where_predicates
+            .iter()
+            .filter_map(|predicate| match predicate {
+                json::WherePredicate::BoundPredicate { type_, bounds, .. } => {
+                    Some(clean::WherePredicate::BoundPredicate {
+                        ty: self.convert_type(type_)?,
+                        bounds: self.convert_bounds(bounds)?,
+                        bound_params: Vec::new(),
+                    })
+                }
+                _ => None,
+            })
+            .collect();
+        Some(clean::Generics { params, where_predicates })
+    }
+
+    /// 把函数转换成ApiFunction，self_type是impl的类型，用来替换参数和返回值中的Self
+    fn convert_function(
+        &self,
+        full_name: String,
+        function: &json::Function,
+        self_type: Option<&clean::Type>,
+        trait_full_path: Option<String>,
+        visibility: Visibility,
+    ) -> Option<ApiFunction> {
+        let replace_self = |ty: clean::Type| match self_type {
+            Some(self_type) if impl_util::is_param_self_type(&ty) => {
+                impl_util::replace_self_type(&ty, self_type)
+            }
+            _ => ty,
+        };
+        let inputs = function
+            .decl
+            .inputs
+            .iter()
+            .map(|(_, ty)| self.convert_type(ty).map(replace_self))
+            .collect::<Option<Vec<_>>>()?;
+        let output = match &function.decl.output {
+            Some(output) => {
+                let output = if function.header.async_ {
+                    _sugared_async_output(output).unwrap_or(output)
+                } else {
+                    output
+                };
+                //返回()的async函数
+                match output {
+                    json::Type::Tuple(types) if types.is_empty() => None,
+                    _ => Some(replace_self(self.convert_type(output)?)),
+                }
+            }
+            None => None,
+        };
+        Some(ApiFunction {
+            full_name,
+            _generics: self.convert_generics(&function.generics)?,
+            generic_substitutions: FxHashMap::default(),
+            inputs,
+            output,
+            _trait_full_path: trait_full_path,
+            _unsafe_tag: if function.header.unsafe_ {
+                ApiUnsafety::Unsafe
+            } else {
+                ApiUnsafety::Normal
+            },
+            _async_tag: if function.header.async_ {
+                ApiAsyncness::Async
+            } else {
+                ApiAsyncness::NotAsync
+            },
+            visibility,
+        })
+    }
+
+    /// core里面的trait的名字
+    fn core_trait_name(&self, trait_: &json::Path) -> Option<&'a str> {
+        let summary = self.krate.paths.get(&trait_.id)?;
+        if summary.path.first().map(|s| s.as_str()) != Some("core") {
+            return None;
+        }
+        summary.path.last().map(|s| s.as_str())
+    }
+}
+
+/// 和extract_impls_from_cache一样：当前crate的路径都记下来，其他crate只记prelude类型
+fn _fill_paths(krate: &json::Crate, cache: &mut Cache, full_name_map: &mut FullNameMap) {
+    for (id, summary) in krate.paths.iter().sorted_by(|a, b| a.0.0.cmp(&b.0.0)) {
+        let (def_id, item_type) = match (_def_id(id), _item_type(&summary.kind)) {
+            (Some(def_id), Some(item_type)) => (def_id, item_type),
+            _ => continue,
+        };
+        let full_name = summary.path.join("::");
+        let symbols = summary.path.iter().map(|s| Symbol::intern(s)).collect_vec();
+        if summary.crate_id == 0 {
+            cache.paths.insert(def_id, (symbols, item_type));
+            full_name_map.push_mapping(def_id, &full_name, item_type);
+        } else {
+            cache.external_paths.insert(def_id, (symbols, item_type));
+            if prelude_type::is_preluded_type(&full_name) {
+                full_name_map.push_mapping(def_id, &full_name, item_type);
+            }
+        }
+    }
+}
+
+fn _add_function(
+    api_graph: &mut ApiGraph<'_>,
+    converter: &JsonConverter<'_>,
+    full_name: String,
+    function: &json::Function,
+    self_type: Option<&clean::Type>,
+    trait_full_path: Option<String>,
+    visibility: Visibility,
+) {
+    match converter.convert_function(
+        full_name.clone(),
+        function,
+        self_type,
+        trait_full_path,
+        visibility,
+    ) {
+        Some(api_fun) => api_graph.add_api_function(api_fun),
+        None => api_graph.exclude_function(&full_name, UNSUPPORTED_REASON),
+    }
+}
+
+/// impl块，和_analyse_impl一样命名：参数里有Self时用trait的路径，否则用类型的路径
+fn _add_impl(api_graph: &mut ApiGraph<'_>, converter: &JsonConverter<'_>, impl_: &json::Impl) {
+    if impl_.synthetic || impl_.negative || impl_.blanket_impl.is_some() {
+        return;
+    }
+    let self_type = match converter.convert_type(&impl_.for_) {
+        Some(self_type) => self_type,
+        None => return,
+    };
+    //只考虑当前crate中的类型
+    let type_def_id = match self_type.def_id(api_graph.cache) {
+        Some(def_id) => def_id,
+        None => return,
+    };
+    let type_full_name = match api_graph.full_name_map._get_full_name(type_def_id) {
+        Some(type_full_name) => type_full_name.clone(),
+        None => return,
+    };
+    let trait_full_name = impl_.trait_.as_ref().and_then(|trait_| {
+        _def_id(&trait_.id)
+            .and_then(|def_id| api_graph.full_name_map._get_full_name(def_id).cloned())
+    });
+
+    //记录实现了Clone和Iterator的类型
+    let core_trait_name =
+        impl_.trait_.as_ref().and_then(|trait_| converter.core_trait_name(trait_));
+    let conversion_trait = match core_trait_name {
+        Some("Clone") => {
+            api_graph.add_clone_type(type_def_id);
+            None
+        }
+        Some("Iterator") => {
+            let item = impl_.items.iter().find_map(|id| match converter.krate.index.get(id) {
+                Some(json::Item {
+                    name: Some(name),
+                    inner: json::ItemEnum::AssocType { default: Some(ty), .. },
+                    ..
+                }) if name == "Item" => converter.convert_type(ty),
+                _ => None,
+            });
+            api_graph.add_iterator_type(&self_type, item);
+            None
+        }
+        Some("DoubleEndedIterator") => {
+            api_graph.set_double_ended_iterator(&self_type);
+            None
+        }
+        Some(trait_name) => ConversionTrait::from_core_name(trait_name),
+        None => None,
+    };
+    //和_analyse_conversion_impl一样，带泛型参数的impl暂时不考虑
+    let has_type_params = impl_
+        .generics
+        .params
+        .iter()
+        .any(|param| matches!(param.kind, json::GenericParamDefKind::Type { .. }));
+    if conversion_trait.is_some() && has_type_params {
+        return;
+    }
+
+    for id in &impl_.items {
+        let item = match converter.krate.index.get(id) {
+            Some(item) => item,
+            None => continue,
+        };
+        let (name, function) = match (&item.name, &item.inner) {
+            (Some(name), json::ItemEnum::Function(function)) => (name, function),
+            _ => continue,
+        };
+        //转换trait只取对应的方法，调用方式是`Type::method(..)`
+        if let Some(conversion_trait) = conversion_trait {
+            if name.as_str() != conversion_trait.method_name() {
+                continue;
+            }
+            _add_function(
+                api_graph,
+                converter,
+                format!("{}::{}", type_full_name, name),
+                function,
+                Some(&self_type),
+                Some(conversion_trait.trait_path().to_string()),
+                Visibility::Public,
+            );
+            continue;
+        }
+        let contains_self_type = function.decl.inputs.iter().any(|(_, ty)| {
+            converter.convert_type(ty).map_or(false, |ty| impl_util::is_param_self_type(&ty))
+        });
+        let method_type_name = match (&impl_.trait_, &trait_full_name) {
+            (Some(_), None) => continue,
+            (Some(_), Some(trait_full_name)) if contains_self_type => trait_full_name.clone(),
+            _ => type_full_name.clone(),
+        };
+        _add_function(
+            api_graph,
+            converter,
+            format!("{}::{}", method_type_name, name),
+            function,
+            Some(&self_type),
+            trait_full_name.clone(),
+            _visibility(&item.visibility, true),
+        );
+    }
+}
+
+/// 递归遍历模块，记录模块的可见性和其中的函数，mod_path包括crate名
+fn _add_module(
+    api_graph: &mut ApiGraph<'_>,
+    converter: &JsonConverter<'_>,
+    item: &json::Item,
+    mod_path: &mut Vec<String>,
+) {
+    let module = match &item.inner {
+        json::ItemEnum::Module(module) => module,
+        _ => return,
+    };
+    mod_path.push(item.name.clone().unwrap_or_default());
+    let mod_name = mod_path.join("::");
+    api_graph.add_mod_visibility(&mod_name, &_visibility(&item.visibility, false));
+
+    for id in &module.items {
+        let child = match converter.krate.index.get(id) {
+            Some(child) => child,
+            None => continue,
+        };
+        match (&child.name, &child.inner) {
+            (_, json::ItemEnum::Module(_)) => _add_module(api_graph, converter, child, mod_path),
+            (Some(name), json::ItemEnum::Function(function)) => _add_function(
+                api_graph,
+                converter,
+                format!("{}::{}", mod_name, name),
+                function,
+                None,
+                None,
+                _visibility(&child.visibility, false),
+            ),
+            _ => {}
+        }
+    }
+    mod_path.pop();
+}
+
+/// 返回解析出来的crate和JSON内容的hash，hash用作缓存key的一部分
+fn _read_crate(path: &str) -> Result<(json::Crate, u64), String> {
+    let content = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut hasher = FxHasher::default();
+    content.hash(&mut hasher);
+    let value: serde_json::Value = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+    let format_version = match value.get("format_version").and_then(|v| v.as_u64()) {
+        Some(format_version) => format_version,
+        None => return Err("no format_version, this is not rustdoc JSON output".to_string()),
+    };
+    if !SUPPORTED_FORMAT_VERSIONS.iter().any(|supported| *supported as u64 == format_version) {
+        return Err(format!(
+            "unsupported rustdoc JSON format version {}, fries-json only reads format version {}; \
+             generate the JSON with a nightly whose rustdoc emits that version",
+            format_version,
+            SUPPORTED_FORMAT_VERSIONS.iter().join(", ")
+        ));
+    }
+    let krate = serde_json::from_value(value).map_err(|e| {
+        format!("format version {} but does not match the schema: {}", format_version, e)
+    })?;
+    Ok((krate, hasher.finish()))
+}
+
+/// `rustdoc fries-json <crate.json>`
+pub(crate) fn json_frontend_main(args: &[String]) -> i32 {
+    let (path, seed) = match args {
+        [path] => (path, None),
+        [flag, seed, path] if flag == "--seed" => match seed.parse::<u64>() {
+            Ok(seed) => (path, Some(seed)),
+            Err(_) => {
+                eprintln!("invalid seed: {}", seed);
+                return 1;
+            }
+        },
+        _ => {
+            eprintln!("usage: rustdoc fries-json [--seed <seed>] <crate.json>");
+            return 1;
+        }
+    };
+    let (krate, source_hash) = match _read_crate(path) {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("can not read rustdoc json {}: {}", path, e);
+            return 1;
+        }
+    };
+    rustc_span::create_default_session_globals_then(|| _run(&krate, source_hash, seed));
+    0
+}
+
+/// 和编译器前端一样，先提取impl，再提取模块里的函数
+fn _extract_api_graph<'a>(
+    krate: &json::Crate,
+    root: &json::Item,
+    kname: &String,
+    cache: &'a Cache,
+    full_name_map: &FullNameMap,
+) -> ApiGraph<'a> {
+    let converter = JsonConverter { krate };
+    let mut api_graph = ApiGraph::new(kname, cache);
+    api_graph.set_full_name_map(full_name_map);
+
+    let impls = krate
+        .index
+        .values()
+        .filter(|item| item.crate_id == 0)
+        .filter_map(|item| match &item.inner {
+            json::ItemEnum::Impl(impl_) => Some((&item.id, impl_)),
+            _ => None,
+        })
+        .sorted_by(|a, b| a.0.0.cmp(&b.0.0));
+    for (_, impl_) in impls {
+        _add_impl(&mut api_graph, &converter, impl_);
+    }
+    _add_module(&mut api_graph, &converter, root, &mut Vec::new());
+    api_graph
+}
+
+fn _run(krate: &json::Crate, source_hash: u64, seed: Option<u64>) {
+    let start = Instant::now();
+    let root = match krate.index.get(&krate.root) {
+        Some(root) => root,
+        None => {
+            println!("rustdoc JSON中没有根模块");
+            return;
+        }
+    };
+    let kname = root.name.clone().unwrap_or_default();
+    println!(
+        "\nStart to parse tested crate from rustdoc JSON and generate test file.\nThe name of the tested crate is {}.",
+        kname
+    );
+
+    let support_generic = false;
+
+    let cache_key = CacheKey::from_json(
+        &kname,
+        krate.crate_version.as_ref(),
+        krate.format_version,
+        source_hash,
+    );
+    let cached_graph = analysis_cache::load_api_graph(&cache_key, support_generic);
+    let mut cache = Cache::default();
+    let mut full_name_map = FullNameMap::new();
+    let mut api_graph = match &cached_graph {
+        Some(cached_graph) => cached_graph.to_api_graph(),
+        None => {
+            cache.crate_version = krate.crate_version.clone();
+            _fill_paths(krate, &mut cache, &mut full_name_map);
+            let mut api_graph = _extract_api_graph(krate, root, &kname, &cache, &full_name_map);
+            context::_analyse_api_graph(&mut api_graph, support_generic);
+            analysis_cache::save_api_graph(&api_graph, &cache_key, support_generic);
+            api_graph
+        }
+    };
+    if let Some(seed) = seed {
+        api_graph.set_seed(seed);
+    }
+    context::_after_analysis(&mut api_graph);
+    println!("total functions in crate : {:?}", api_graph.api_functions.len());
+
+    let max_num = 100;
+    let max_len = 15;
+    context::_fries_generate(
+        &mut api_graph,
+        kname.replace("_", "-").as_str(),
+        max_num,
+        max_len,
+        support_generic,
+        start,
+    );
+}
@@ -1,20 +1,43 @@
 //mod afl_function_util;
 //mod afl_param_util;
 mod afl_util;
+mod analysis_cache;
+mod api_diff;
 mod api_function;
 mod api_graph;
+mod api_id;
 mod api_sequence;
 mod api_util;
+mod async_util;
+mod borrow_check;
 mod call_type;
+mod callback;
+mod compile_check;
+mod constructor;
 mod context;
+mod corpus;
+mod corpus_driver;
+mod differential;
+mod error_path;
+mod explain;
 mod extract_dep;
 mod extract_info;
 mod file_util;
+mod focus;
 mod fuzz_type;
 mod generic_function;
+mod graph_export;
 mod impl_util;
+mod inverse_pair;
+mod iterator;
+mod json_frontend;
 mod mod_visibility;
+mod oracle;
 mod prelude_type;
 mod replay_util;
+mod type_index;
 
 pub(crate) use context::Context;
+pub(crate) use corpus_driver::{corpus_driver_main, is_rustc_wrapper_invocation};
+pub(crate) use explain::explain_main;
+pub(crate) use json_frontend::json_frontend_main;
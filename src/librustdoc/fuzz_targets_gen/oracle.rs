@@ -0,0 +1,248 @@
+//! 标准库trait约定的检查
+//! 除了panic和崩溃之外的另一种oracle：序列中产生的每个值，如果它的类型实现了对应的trait，就检查：
+//! 1. Clone：`x.clone() == x`（要求Eq，避免NaN之类的误报）
+//! 2. Eq：自反，`x == x`
+//! 3. Hash：和Eq一致，相等的值hash也相等
+//! 4. Ord：和PartialOrd一致，自反，并且对同一类型的两个存活的值满足反对称
+//! 另外检查往返转换是否稳定：
+//! 5. Display和FromStr：`x.to_string()`可以parse回来，并且再打印出来的字符串不变
+//! 6. Serialize和Deserialize：用serde_json序列化再反序列化，再序列化的结果不变
+//! 单个值的检查紧跟在产生它的调用后面；两个值之间的检查放在函数体的最后，只检查那时还能读的值
+//! 违反约定的时候打印出违反的约定和整个序列，然后abort，和普通的panic区分开；
+//! 往返转换不一致的作为逻辑错误单独报告。在replay文件中运行时（比如用afl-tmin最小化之后的输入），
+//! 报告里还会打印出触发它的输入
+//! 通过环境变量`FRIES_TRAIT_ORACLE`打开，serde的往返检查需要测试crate依赖serde_json，
+//! 要另外设置`FRIES_SERDE_ROUND_TRIP`
+
+use crate::clean;
+use crate::fuzz_targets_gen::api_graph::ApiGraph;
+use crate::fuzz_targets_gen::api_util;
+use crate::fuzz_targets_gen::impl_util::StdTrait;
+
+/// 设置了这个环境变量（并且不是0）的时候检查trait的约定
+static TRAIT_ORACLE_ENV: &'static str = "FRIES_TRAIT_ORACLE";
+static SERDE_ROUND_TRIP_ENV: &'static str = "FRIES_SERDE_ROUND_TRIP";
+/// 违反约定时输出的前缀，用来和其他crash区分
+static VIOLATION_PREFIX: &'static str = "FRIES_TRAIT_LAW_VIOLATION";
+static LOGIC_BUG_PREFIX: &'static str = "FRIES_ROUND_TRIP_MISMATCH";
+
+/// 是否检查trait的约定
+pub(crate) fn is_oracle_mode() -> bool {
+    match std::env::var(TRAIT_ORACLE_ENV) {
+        Ok(value) => value != "0" && !value.is_empty(),
+        Err(_) => false,
+    }
+}
+
+/// 是否用serde_json检查serde的往返转换
+pub(crate) fn is_serde_round_trip_mode() -> bool {
+    match std::env::var(SERDE_ROUND_TRIP_ENV) {
+        Ok(value) => value != "0" && !value.is_empty(),
+        Err(_) => false,
+    }
+}
+
+/// 一个类型可以检查的约定
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct TraitLaws {
+    /// 只有类型相同的值之间才做两两检查
+    pub(crate) type_: clean::Type,
+    /// 生成代码里的类型名，parse和反序列化的时候要用
+    pub(crate) type_name: String,
+    pub(crate) clone: bool,
+    pub(crate) eq: bool,
+    pub(crate) hash: bool,
+    pub(crate) ord: bool,
+    pub(crate) display_from_str: bool,
+    pub(crate) serde: bool,
+}
+
+impl TraitLaws {
+    /// 只考虑不带泛型参数（也就不带生命周期）的类型，没有任何可以检查的约定时返回None
+    pub(crate) fn from_type(api_graph: &ApiGraph<'_>, ty: &clean::Type) -> Option<Self> {
+        let path = match ty {
+            clean::Type::Path { path } => path,
+            _ => return None,
+        };
+        let has_generic_args = path.segments.iter().any(|segment| match &segment.args {
+            clean::GenericArgs::AngleBracketed { args, bindings } => {
+                !args.is_empty() || !bindings.is_empty()
+            }
+            clean::GenericArgs::Parenthesized { .. } => true,
+        });
+        if has_generic_args {
+            return None;
+        }
+        let def_id = ty.def_id(api_graph.cache)?;
+        let has = |std_trait| api_graph.has_std_trait(def_id, std_trait);
+        let eq = has(StdTrait::PartialEq) && has(StdTrait::Eq);
+        let laws = TraitLaws {
+            type_: ty.clone(),
+            type_name: api_util::_type_name(ty, api_graph.cache, &api_graph.full_name_map),
+            clone: eq && api_graph.clone_types.contains(&def_id),
+            eq,
+            hash: eq && has(StdTrait::Hash),
+            ord: has(StdTrait::PartialOrd) && has(StdTrait::Ord),
+            display_from_str: has(StdTrait::Display) && has(StdTrait::FromStr),
+            serde: is_serde_round_trip_mode()
+                && has(StdTrait::Serialize)
+                && has(StdTrait::Deserialize),
+        };
+        if laws.clone || laws.eq || laws.hash || laws.ord || laws.display_from_str || laws.serde {
+            Some(laws)
+        } else {
+            None
+        }
+    }
+
+    /// 单个值的检查
+    pub(crate) fn unary_checks(&self, value: &String) -> Vec<String> {
+        let mut res = Vec::new();
+        if self.clone {
+            res.push(_check("Clone", format!("{}.clone() == {}", value, value)));
+        }
+        if self.eq {
+            res.push(_check("Eq", format!("{} == {}", value, value)));
+        }
+        if self.hash {
+            let other = if self.clone { format!("{}.clone()", value) } else { value.clone() };
+            res.push(_check("Hash", format!("_hash_of(&{}) == _hash_of(&{})", value, other)));
+        }
+        if self.ord {
+            res.push(_check(
+                "Ord",
+                format!("{}.partial_cmp(&{}) == Some({}.cmp(&{}))", value, value, value, value),
+            ));
+            res.push(_check(
+                "Ord",
+                format!("{}.cmp(&{}) == std::cmp::Ordering::Equal", value, value),
+            ));
+        }
+        if self.display_from_str {
+            let law = "Display/FromStr";
+            let mut checks =
+                vec![_check_round_trip(law, "_parsed.to_string() == _string".to_string())];
+            if self.eq {
+                checks.push(_check_round_trip(law, format!("_parsed == {}", value)));
+            }
+            res.push(format!(
+                "{{let _string = {}.to_string(); match _string.parse::<{}>() {{Ok(_parsed) => {{{}}} Err(_) => {}}}}}",
+                value,
+                self.type_name,
+                checks.join(" "),
+                _round_trip_failure(law, "the string can not be parsed back")
+            ));
+        }
+        if self.serde {
+            let law = "Serialize/Deserialize";
+            let mut checks = vec![_check_round_trip(
+                law,
+                "serde_json::to_string(&_parsed).ok() == Some(_json.clone())".to_string(),
+            )];
+            if self.eq {
+                checks.push(_check_round_trip(law, format!("_parsed == {}", value)));
+            }
+            //序列化本身失败的不算
+            res.push(format!(
+                "if let Ok(_json) = serde_json::to_string(&{}) {{match serde_json::from_str::<{}>(&_json) {{Ok(_parsed) => {{{}}} Err(_) => {}}}}}",
+                value,
+                self.type_name,
+                checks.join(" "),
+                _round_trip_failure(law, "the json can not be deserialized back")
+            ));
+        }
+        res
+    }
+
+    /// 同一类型的两个值之间的检查
+    pub(crate) fn pair_checks(&self, left: &String, right: &String) -> Vec<String> {
+        let mut res = Vec::new();
+        if self.eq {
+            res.push(_check("Eq", format!("({} == {}) == ({} == {})", left, right, right, left)));
+        }
+        if self.hash {
+            res.push(_check(
+                "Hash",
+                format!("{} != {} || _hash_of(&{}) == _hash_of(&{})", left, right, left, right),
+            ));
+        }
+        if self.ord {
+            res.push(_check(
+                "Ord",
+                format!("{}.partial_cmp(&{}) == Some({}.cmp(&{}))", left, right, left, right),
+            ));
+            res.push(_check(
+                "Ord",
+                format!("{}.cmp(&{}) == {}.cmp(&{}).reverse()", left, right, right, left),
+            ));
+            if self.eq {
+                res.push(_check(
+                    "Ord",
+                    format!(
+                        "({} == {}) == ({}.cmp(&{}) == std::cmp::Ordering::Equal)",
+                        left, right, left, right
+                    ),
+                ));
+            }
+        }
+        res
+    }
+}
+
+fn _check(law: &str, condition: String) -> String {
+    format!("_check_law({}, \"{}\", {:?});", condition, law, condition)
+}
+
+fn _check_round_trip(law: &str, condition: String) -> String {
+    format!("_check_round_trip({}, \"{}\", {:?});", condition, law, condition)
+}
+
+/// 转换不回去的时候直接报告
+fn _round_trip_failure(law: &str, reason: &str) -> String {
+    format!("_check_round_trip(false, \"{}\", {:?});", law, reason)
+}
+
+/// 测试文件中检查用到的辅助函数，sequence是打印出来的序列，违反约定的时候一起输出
+/// `_ORACLE_INPUT`只在replay文件中被填上，fuzz的时候输入由fuzzer自己保存
+pub(crate) fn _oracle_helper_functions(sequence: &String) -> String {
+    format!(
+        "static _ORACLE_SEQUENCE: &str = {sequence:?};
+static _ORACLE_INPUT: std::sync::Mutex<Vec<u8>> = std::sync::Mutex::new(Vec::new());
+
+fn _report_input() {{
+    if let Ok(input) = _ORACLE_INPUT.lock() {{
+        if !input.is_empty() {{
+            eprintln!(\"input: {{:?}}\", *input);
+        }}
+    }}
+}}
+
+fn _check_law(holds: bool, law: &str, check: &str) {{
+    if !holds {{
+        eprintln!(\"{prefix} [{{}}]: `{{}}` does not hold\", law, check);
+        eprintln!(\"sequence: {{}}\", _ORACLE_SEQUENCE);
+        _report_input();
+        std::process::abort();
+    }}
+}}
+
+fn _check_round_trip(holds: bool, law: &str, check: &str) {{
+    if !holds {{
+        eprintln!(\"{logic_bug_prefix} [{{}}]: `{{}}` does not hold\", law, check);
+        eprintln!(\"sequence: {{}}\", _ORACLE_SEQUENCE);
+        _report_input();
+        std::process::abort();
+    }}
+}}
+
+fn _hash_of<T: std::hash::Hash>(value: &T) -> u64 {{
+    use std::hash::Hasher;
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}}\n",
+        sequence = sequence,
+        prefix = VIOLATION_PREFIX,
+        logic_bug_prefix = LOGIC_BUG_PREFIX
+    )
+}
@@ -248,7 +248,8 @@ pub(crate) fn _from_call_type(call_type: &CallType) -> FxHashSet<_PreludeHelper>
             | CallType::_Deref(inner_call_type)
             | CallType::_ToOption(inner_call_type)
             | CallType::_ToResult(inner_call_type)
-            | CallType::_UnsafeDeref(inner_call_type) => {
+            | CallType::_UnsafeDeref(inner_call_type)
+            | CallType::_Clone(inner_call_type) => {
                 _PreludeHelper::_from_call_type(&**inner_call_type)
             }
             CallType::_UnwrapOption(inner_call_type) => {
@@ -19,3 +19,10 @@ pub fn _read_crash_file_data() -> &'static str {
     data
 }\n"
 }
+
+/// 把读到的输入记下来，trait约定或者往返转换检查失败的时候和序列一起打印
+pub fn _record_oracle_input() -> &'static str {
+    "    if let Ok(mut input) = _ORACLE_INPUT.lock() {
+        input.extend_from_slice(data);
+    }\n"
+}
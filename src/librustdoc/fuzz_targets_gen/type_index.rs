@@ -0,0 +1,179 @@
+//! 按类型建立的消费者索引，用来加速find_all_dependencies
+//! _same_type会剥掉输入类型的引用、裸指针、Option和Result，以及输出类型的Option、Result、裸指针和引用，
+//! 在任意一层两边的类型相同，或者剥到底之后是同一个结构体（不考虑生命周期）、都是数字或者char时才可能兼容
+//! 所以把每个类型剥开的每一层和最里面的类型归一化成key，只有key有交集的生产者和消费者才需要调用_same_type
+//! key只是兼容的必要条件，最终的调用方式仍然由_same_type决定，得到的依赖和逐个比较时完全一样
+
+use crate::clean::{self, PrimitiveType};
+use crate::formats::cache::Cache;
+use crate::fuzz_targets_gen::impl_util::FullNameMap;
+use crate::fuzz_targets_gen::prelude_type::{self, PreludeType};
+use rustc_data_structures::fx::FxHashMap;
+use rustc_hir::def::Res;
+
+/// 归一化之后的类型
+#[derive(Debug, Clone, Hash, Eq, PartialEq)]
+enum NormalizedType {
+    /// 剥开过程中的某一层，完全相同时直接调用
+    Exact(clean::Type),
+    /// 剥到底的结构体、枚举，不考虑生命周期和泛型参数
+    Path(Res),
+    /// 剥到底的数字和char，可以用as互相转换
+    Number,
+}
+
+/// 驻留之后的类型key
+#[derive(Debug, Clone, Copy, Hash, Eq, PartialEq, Ord, PartialOrd)]
+pub(crate) struct TypeKey(u32);
+
+#[derive(Debug, Default)]
+pub(crate) struct TypeInterner {
+    keys: FxHashMap<NormalizedType, TypeKey>,
+}
+
+impl TypeInterner {
+    fn intern(&mut self, normalized: NormalizedType) -> TypeKey {
+        let next_key = TypeKey(self.keys.len() as u32);
+        *self.keys.entry(normalized).or_insert(next_key)
+    }
+
+    /// 只查询，不存在的类型不会有对应的消费者
+    fn get(&self, normalized: &NormalizedType) -> Option<TypeKey> {
+        self.keys.get(normalized).cloned()
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.keys.len()
+    }
+}
+
+fn _is_prelude(ty: &clean::Type, cache: &Cache, full_name_map: &FullNameMap) -> bool {
+    prelude_type::_prelude_type_need_special_dealing(ty, cache, full_name_map)
+}
+
+fn _final_type(ty: &clean::Type, cache: &Cache, full_name_map: &FullNameMap) -> clean::Type {
+    PreludeType::from_type(ty, cache, full_name_map)._get_final_type()
+}
+
+/// 剥到底之后的类型
+fn _core_type(ty: &clean::Type) -> Option<NormalizedType> {
+    match ty {
+        clean::Type::Path { path } => Some(NormalizedType::Path(path.res)),
+        clean::Type::Primitive(primitive_type) => match primitive_type {
+            PrimitiveType::Isize
+            | PrimitiveType::I8
+            | PrimitiveType::I16
+            | PrimitiveType::I32
+            | PrimitiveType::I64
+            | PrimitiveType::I128
+            | PrimitiveType::Usize
+            | PrimitiveType::U8
+            | PrimitiveType::U16
+            | PrimitiveType::U32
+            | PrimitiveType::U64
+            | PrimitiveType::U128
+            | PrimitiveType::F32
+            | PrimitiveType::F64
+            | PrimitiveType::Char => Some(NormalizedType::Number),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// 作为参数时，依次剥掉引用、裸指针、Option和Result
+fn _input_layers(
+    ty: &clean::Type,
+    cache: &Cache,
+    full_name_map: &FullNameMap,
+) -> Vec<NormalizedType> {
+    let mut layers = Vec::new();
+    let mut current = ty.clone();
+    loop {
+        layers.push(NormalizedType::Exact(current.clone()));
+        let next = match &current {
+            clean::Type::BorrowedRef { type_, .. } | clean::Type::RawPointer(_, type_) => {
+                (**type_).clone()
+            }
+            _ if _is_prelude(&current, cache, full_name_map) => {
+                _final_type(&current, cache, full_name_map)
+            }
+            _ => break,
+        };
+        current = next;
+    }
+    layers.extend(_core_type(&current));
+    layers
+}
+
+/// 作为返回值时，依次剥掉Option、Result、裸指针和引用
+fn _output_layers(
+    ty: &clean::Type,
+    cache: &Cache,
+    full_name_map: &FullNameMap,
+) -> Vec<NormalizedType> {
+    let mut layers = Vec::new();
+    let mut current = ty.clone();
+    loop {
+        layers.push(NormalizedType::Exact(current.clone()));
+        let next = match &current {
+            clean::Type::Path { .. } if _is_prelude(&current, cache, full_name_map) => {
+                _final_type(&current, cache, full_name_map)
+            }
+            clean::Type::BorrowedRef { type_, .. } | clean::Type::RawPointer(_, type_) => {
+                (**type_).clone()
+            }
+            _ => break,
+        };
+        current = next;
+    }
+    layers.extend(_core_type(&current));
+    layers
+}
+
+/// 参数类型key -> (函数下标, 参数位置)
+#[derive(Debug, Default)]
+pub(crate) struct ConsumerIndex {
+    pub(crate) interner: TypeInterner,
+    consumers: FxHashMap<TypeKey, Vec<(usize, usize)>>,
+}
+
+impl ConsumerIndex {
+    /// 第fun_index个函数的第param_index个参数
+    pub(crate) fn add_consumer(
+        &mut self,
+        input_type: &clean::Type,
+        fun_index: usize,
+        param_index: usize,
+        cache: &Cache,
+        full_name_map: &FullNameMap,
+    ) {
+        for layer in _input_layers(input_type, cache, full_name_map) {
+            let key = self.interner.intern(layer);
+            let consumers = self.consumers.entry(key).or_default();
+            if consumers.last() != Some(&(fun_index, param_index)) {
+                consumers.push((fun_index, param_index));
+            }
+        }
+    }
+
+    /// 可能和这个返回值类型兼容的参数，按函数下标和参数位置排好序
+    pub(crate) fn candidate_consumers(
+        &self,
+        output_type: &clean::Type,
+        cache: &Cache,
+        full_name_map: &FullNameMap,
+    ) -> Vec<(usize, usize)> {
+        let mut candidates = Vec::new();
+        for layer in _output_layers(output_type, cache, full_name_map) {
+            if let Some(key) = self.interner.get(&layer) {
+                if let Some(consumers) = self.consumers.get(&key) {
+                    candidates.extend(consumers.iter().cloned());
+                }
+            }
+        }
+        candidates.sort();
+        candidates.dedup();
+        candidates
+    }
+}
@@ -44,6 +44,7 @@
 extern crate rustc_errors;
 extern crate rustc_expand;
 extern crate rustc_feature;
+extern crate rustc_graphviz;
 extern crate rustc_hir;
 extern crate rustc_hir_analysis;
 extern crate rustc_hir_pretty;
@@ -161,6 +162,21 @@ pub fn main() {
         }
     }
 
+    // `rustdoc explain ...` answers queries about a saved api graph offline, without a session
+    let args = std::env::args().collect::<Vec<_>>();
+    if args.get(1).map_or(false, |arg| arg == "explain") {
+        process::exit(fuzz_targets_gen::explain_main(&args[2..]));
+    }
+    // `rustdoc fries-json <crate.json>` builds the api graph from rustdoc JSON output instead
+    if args.get(1).map_or(false, |arg| arg == "fries-json") {
+        process::exit(fuzz_targets_gen::json_frontend_main(&args[2..]));
+    }
+    // used as `RUSTC_WORKSPACE_WRAPPER`, cargo passes the path of rustc first: compile as rustc
+    // and extract the corpus of each target
+    if fuzz_targets_gen::is_rustc_wrapper_invocation(&args) {
+        process::exit(fuzz_targets_gen::corpus_driver_main(&args[1..]));
+    }
+
     rustc_driver::install_ice_hook();
 
     // When using CI artifacts (with `download_stage1 = true`), tracing is unconditionally built